@@ -1,27 +1,151 @@
-use event::Event;
+use rand::XorShiftRng;
+
+use character::Character;
+use entity::EntityState;
+use event::{Event, EventKind};
+use world::World;
+use world::two_dimensional::{Field, World2d};
+use super::SavedBehaviour;
+
+/// The result of a behaviour handling an event. Decides how the behaviour chain of an entity
+/// continues.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BehaviourResult {
+    /// The event was handled with the given response. The rest of the chain is skipped.
+    Handled(Event),
+    /// The behaviour does not handle the event. It falls through to the next behaviour.
+    Pass,
+    /// The next behaviours get the given event instead
+    Transform(Event),
+    /// The event is swallowed. The rest of the chain is skipped and there is no response.
+    Stop,
+}
 
 /// A behaviour defining how entities react to certain events
 pub trait Behaviour: BehaviourClone {
-    /// Handle an event
-    fn handle_event(&self, Event) -> Event;
+    /// Handle an event. The state of the entity can be changed to remember things for later
+    /// events.
+    fn handle_event(&self, event: &Event, state: &mut EntityState) -> BehaviourResult;
+
+    /// Handle an event sent by the player. Behaviours that depend on the character of the
+    /// player, like dialogues, can inspect and change it. Falls back to `handle_event`.
+    fn handle_player_event(&self,
+                           event: &Event,
+                           state: &mut EntityState,
+                           _character: &mut Character)
+                           -> BehaviourResult {
+        self.handle_event(event, state)
+    }
+
+    /// Returns `true` if the behaviour handles events of the given kind. Other events are passed
+    /// to the next behaviour without calling `handle_event`.
+    fn handles(&self, _kind: EventKind) -> bool {
+        true
+    }
+
+    /// Called on every update of the world the entity is in. Used by behaviours acting on their
+    /// own, like a `BehaviourTree`.
+    fn tick(&self, _context: &mut TickContext, _state: &mut EntityState) {}
+
+    /// Saves the behaviour, so it can be restored by a `BehaviourRegistry`. Returns `None` if the
+    /// behaviour can't be saved, entities with such a behaviour can't be saved either.
+    fn save(&self) -> Option<SavedBehaviour> {
+        None
+    }
+}
+
+/// The surroundings of an entity during a tick of its behaviours
+///
+/// The entity itself is taken out of its field while it is ticked, so the world only contains
+/// the other entities.
+pub struct TickContext<'a> {
+    /// The world the entity is in
+    pub world: &'a World2d,
+    /// The position of the entity in the world
+    pub position: (usize, usize),
+    /// The character of the player
+    pub character: &'a Character,
+    /// The random number generator of the game
+    pub rng: &'a mut XorShiftRng,
+    movement: Option<(i64, i64)>,
+}
+
+impl<'a> TickContext<'a> {
+    /// Creates a new instance of `TickContext` for the entity at the given position
+    pub fn new(world: &'a World2d,
+               position: (usize, usize),
+               character: &'a Character,
+               rng: &'a mut XorShiftRng)
+               -> TickContext<'a> {
+        TickContext {
+            world,
+            position,
+            character,
+            rng,
+            movement: None,
+        }
+    }
+
+    /// Returns the position of the player
+    pub fn player_position(&self) -> (usize, usize) {
+        self.world.get_position()
+    }
+
+    /// Returns the distance to the player in fields, counting diagonal steps as one
+    pub fn distance_to_player(&self) -> usize {
+        let (x, y) = self.player_position();
+        let distance_x = (x as i64 - self.position.0 as i64).abs();
+        let distance_y = (y as i64 - self.position.1 as i64).abs();
+        distance_x.max(distance_y) as usize
+    }
+
+    /// Returns the direction towards the player, one step on each axis at most
+    pub fn direction_to_player(&self) -> (i64, i64) {
+        let (x, y) = self.player_position();
+        ((x as i64 - self.position.0 as i64).signum(), (y as i64 - self.position.1 as i64).signum())
+    }
+
+    /// Returns the field at the given offset from the entity, or `None` if it is outside of the
+    /// world
+    pub fn field(&self, offset: (i64, i64)) -> Option<&'a Field> {
+        let x = self.position.0 as i64 + offset.0;
+        let y = self.position.1 as i64 + offset.1;
+        if x < 0 || y < 0 {
+            return None;
+        }
+
+        self.world.get_field((x as usize, y as usize))
+    }
+
+    /// Moves the entity one field into the given direction after the tick. The last call wins.
+    /// Like pushing, the move only happens if the field is free and not higher than the current
+    /// one.
+    pub fn walk(&mut self, direction: (i64, i64)) {
+        self.movement = Some((direction.0.signum(), direction.1.signum()));
+    }
+
+    /// Returns the direction the entity walks into after the tick
+    pub fn movement(&self) -> Option<(i64, i64)> {
+        self.movement
+    }
 }
 
 /// A helper trait for cloning `Behaviour` objects
 pub trait BehaviourClone {
     /// A clone method that returns a boxed behavior
-    fn clone_box(&self) -> Box<Behaviour>;
+    fn clone_box(&self) -> Box<dyn Behaviour>;
 }
 
 impl<T> BehaviourClone for T
     where T: 'static + Behaviour + Clone
 {
-    fn clone_box(&self) -> Box<Behaviour> {
+    fn clone_box(&self) -> Box<dyn Behaviour> {
         Box::new(self.clone())
     }
 }
 
-impl Clone for Box<Behaviour> {
-    fn clone(&self) -> Box<Behaviour> {
+impl Clone for Box<dyn Behaviour> {
+    fn clone(&self) -> Box<dyn Behaviour> {
         self.clone_box()
     }
 }
@@ -0,0 +1,653 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use entity::EntityState;
+use event::{Event, EventKind};
+use super::{Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour, TickContext};
+
+/// A condition of a behaviour tree, checked with the surroundings and the state of the entity
+pub type TreeCondition = Rc<dyn Fn(&TickContext, &EntityState) -> bool>;
+
+/// An action of a behaviour tree, like walking or attacking
+pub type TreeAction = Rc<dyn Fn(&mut TickContext, &mut EntityState) -> Status>;
+
+/// A condition registered with a name, see `register_tree_condition`
+pub type ConditionFn = fn(&TickContext, &EntityState) -> bool;
+
+/// An action registered with a name, see `register_tree_action`
+pub type ActionFn = fn(&mut TickContext, &mut EntityState) -> Status;
+
+#[derive(Default)]
+struct TreeRegistry {
+    conditions: HashMap<String, ConditionFn>,
+    actions: HashMap<String, ActionFn>,
+}
+
+// Like the behaviours, the named nodes are looked up while decoding, so their registry is global
+static TREE_REGISTRY: Mutex<Option<TreeRegistry>> = Mutex::new(None);
+
+fn with_tree_registry<T, F: FnOnce(&mut TreeRegistry) -> T>(f: F) -> T {
+    let mut registry = TREE_REGISTRY.lock().unwrap_or_else(|error| error.into_inner());
+    f(registry.get_or_insert_with(TreeRegistry::default))
+}
+
+/// Registers a condition with the given name, replacing a previous one with the same name.
+/// Trees using it through `Node::named_condition` can be saved and loaded again.
+pub fn register_tree_condition(name: &str, condition: ConditionFn) {
+    with_tree_registry(|registry| registry.conditions.insert(name.to_owned(), condition));
+}
+
+/// Registers an action with the given name, replacing a previous one with the same name.
+/// Trees using it through `Node::named_action` can be saved and loaded again.
+pub fn register_tree_action(name: &str, action: ActionFn) {
+    with_tree_registry(|registry| registry.actions.insert(name.to_owned(), action));
+}
+
+fn tree_condition(name: &str) -> Option<ConditionFn> {
+    with_tree_registry(|registry| registry.conditions.get(name).cloned())
+}
+
+fn tree_action(name: &str) -> Option<ActionFn> {
+    with_tree_registry(|registry| registry.actions.get(name).cloned())
+}
+
+/// The status of a node after it was ticked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The node reached its goal
+    Success,
+    /// The node could not reach its goal
+    Failure,
+    /// The node needs more ticks to reach its goal
+    Running,
+}
+
+/// Changes the status of the child of a decorator node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decorator {
+    /// Swaps `Status::Success` and `Status::Failure`
+    Invert,
+    /// Turns `Status::Failure` into `Status::Success`
+    Succeed,
+    /// Turns `Status::Success` into `Status::Failure`
+    Fail,
+    /// Ticks the child again as long as it succeeds, at most the given amount of times.
+    /// Succeeds without ticking the child if the amount is `0`.
+    Repeat(u32),
+}
+
+/// A node of a behaviour tree
+#[derive(Clone)]
+pub enum Node {
+    /// Ticks the children in order until one of them does not succeed and returns its status.
+    /// Succeeds if all children succeed.
+    Sequence(Vec<Node>),
+    /// Ticks the children in order until one of them does not fail and returns its status.
+    /// Fails if all children fail.
+    Selector(Vec<Node>),
+    /// Ticks all children. Succeeds if at least the given amount of children succeed and fails
+    /// as soon as that is not possible anymore, otherwise it is running.
+    Parallel(usize, Vec<Node>),
+    /// Changes the status of its child
+    Decorator(Decorator, Box<Node>),
+    /// Succeeds if the condition holds, otherwise it fails
+    Condition(TreeCondition),
+    /// Runs an action
+    Action(TreeAction),
+    /// Succeeds if the condition registered with the name holds, otherwise it fails
+    NamedCondition(String, ConditionFn),
+    /// Runs the action registered with the name
+    NamedAction(String, ActionFn),
+}
+
+impl Node {
+    /// Creates a new sequence node
+    pub fn sequence(children: Vec<Node>) -> Node {
+        Node::Sequence(children)
+    }
+
+    /// Creates a new selector node
+    pub fn selector(children: Vec<Node>) -> Node {
+        Node::Selector(children)
+    }
+
+    /// Creates a new parallel node succeeding if at least `threshold` children succeed
+    pub fn parallel(threshold: usize, children: Vec<Node>) -> Node {
+        Node::Parallel(threshold, children)
+    }
+
+    /// Creates a new decorator node
+    pub fn decorate(decorator: Decorator, child: Node) -> Node {
+        Node::Decorator(decorator, Box::new(child))
+    }
+
+    /// Creates a new condition node
+    pub fn condition<F>(condition: F) -> Node
+        where F: Fn(&TickContext, &EntityState) -> bool + 'static
+    {
+        Node::Condition(Rc::new(condition))
+    }
+
+    /// Creates a new action node
+    pub fn action<F>(action: F) -> Node
+        where F: Fn(&mut TickContext, &mut EntityState) -> Status + 'static
+    {
+        Node::Action(Rc::new(action))
+    }
+
+    /// Creates a new condition node running the condition registered with the given name. Unlike
+    /// closures, named conditions can be saved.
+    ///
+    /// # Panics
+    ///
+    /// **Panics** if no condition is registered with the name, see `register_tree_condition`.
+    pub fn named_condition(name: &str) -> Node {
+        match tree_condition(name) {
+            Some(condition) => Node::NamedCondition(name.to_owned(), condition),
+            None => panic!("no tree condition registered as {:?}", name),
+        }
+    }
+
+    /// Creates a new action node running the action registered with the given name. Unlike
+    /// closures, named actions can be saved.
+    ///
+    /// # Panics
+    ///
+    /// **Panics** if no action is registered with the name, see `register_tree_action`.
+    pub fn named_action(name: &str) -> Node {
+        match tree_action(name) {
+            Some(action) => Node::NamedAction(name.to_owned(), action),
+            None => panic!("no tree action registered as {:?}", name),
+        }
+    }
+
+    /// Returns `true` if the node and its children can be saved, which is the case if they
+    /// don't contain any closures
+    pub fn can_be_saved(&self) -> bool {
+        match *self {
+            Node::Sequence(ref children) |
+            Node::Selector(ref children) |
+            Node::Parallel(_, ref children) => children.iter().all(Node::can_be_saved),
+            Node::Decorator(_, ref child) => child.can_be_saved(),
+            Node::Condition(_) | Node::Action(_) => false,
+            Node::NamedCondition(..) | Node::NamedAction(..) => true,
+        }
+    }
+
+    /// Ticks the node and its children
+    pub fn tick(&self, context: &mut TickContext, state: &mut EntityState) -> Status {
+        match *self {
+            Node::Sequence(ref children) => {
+                for child in children {
+                    match child.tick(context, state) {
+                        Status::Success => {}
+                        status => return status,
+                    }
+                }
+
+                Status::Success
+            }
+            Node::Selector(ref children) => {
+                for child in children {
+                    match child.tick(context, state) {
+                        Status::Failure => {}
+                        status => return status,
+                    }
+                }
+
+                Status::Failure
+            }
+            Node::Parallel(threshold, ref children) => {
+                let mut succeeded = 0;
+                let mut failed = 0;
+                for child in children {
+                    match child.tick(context, state) {
+                        Status::Success => succeeded += 1,
+                        Status::Failure => failed += 1,
+                        Status::Running => {}
+                    }
+                }
+
+                if succeeded >= threshold {
+                    Status::Success
+                } else if children.len() - failed < threshold {
+                    Status::Failure
+                } else {
+                    Status::Running
+                }
+            }
+            Node::Decorator(Decorator::Repeat(0), _) => Status::Success,
+            Node::Decorator(decorator, ref child) => {
+                match (decorator, child.tick(context, state)) {
+                    (Decorator::Invert, Status::Success) => Status::Failure,
+                    (Decorator::Invert, Status::Failure) => Status::Success,
+                    (Decorator::Succeed, Status::Failure) => Status::Success,
+                    (Decorator::Fail, Status::Success) => Status::Failure,
+                    (Decorator::Repeat(times), Status::Success) => {
+                        for _ in 1..times {
+                            match child.tick(context, state) {
+                                Status::Success => {}
+                                status => return status,
+                            }
+                        }
+
+                        Status::Success
+                    }
+                    (_, status) => status,
+                }
+            }
+            Node::Condition(ref condition) => {
+                if condition(context, state) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            Node::Action(ref action) => action(context, state),
+            Node::NamedCondition(_, condition) => {
+                if condition(context, state) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            Node::NamedAction(_, action) => action(context, state),
+        }
+    }
+}
+
+impl Encodable for Decorator {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_enum("Decorator", |e| {
+            match *self {
+                Decorator::Invert => e.emit_enum_variant("Invert", 0, 0, |_| Ok(())),
+                Decorator::Succeed => e.emit_enum_variant("Succeed", 1, 0, |_| Ok(())),
+                Decorator::Fail => e.emit_enum_variant("Fail", 2, 0, |_| Ok(())),
+                Decorator::Repeat(times) => {
+                    e.emit_enum_variant("Repeat", 3, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| times.encode(e))
+                    })
+                }
+            }
+        })
+    }
+}
+
+impl Decodable for Decorator {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Decorator, D::Error> {
+        let names = ["Invert", "Succeed", "Fail", "Repeat"];
+        d.read_enum("Decorator", |d| {
+            d.read_enum_variant(&names, |d, index| {
+                match index {
+                    0 => Ok(Decorator::Invert),
+                    1 => Ok(Decorator::Succeed),
+                    2 => Ok(Decorator::Fail),
+                    _ => d.read_enum_variant_arg(0, Decodable::decode).map(Decorator::Repeat),
+                }
+            })
+        })
+    }
+}
+
+/// Named conditions and actions are saved by their name and restored using the ones registered
+/// with `register_tree_condition` and `register_tree_action`. Closures are saved as anonymous
+/// nodes, which can't be loaded again, see `Node::can_be_saved`.
+impl Encodable for Node {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_enum("Node", |e| {
+            match *self {
+                Node::Sequence(ref children) => {
+                    e.emit_enum_variant("Sequence", 0, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| children.encode(e))
+                    })
+                }
+                Node::Selector(ref children) => {
+                    e.emit_enum_variant("Selector", 1, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| children.encode(e))
+                    })
+                }
+                Node::Parallel(threshold, ref children) => {
+                    e.emit_enum_variant("Parallel", 2, 2, |e| {
+                        e.emit_enum_variant_arg(0, |e| threshold.encode(e))?;
+                        e.emit_enum_variant_arg(1, |e| children.encode(e))
+                    })
+                }
+                Node::Decorator(decorator, ref child) => {
+                    e.emit_enum_variant("Decorator", 3, 2, |e| {
+                        e.emit_enum_variant_arg(0, |e| decorator.encode(e))?;
+                        e.emit_enum_variant_arg(1, |e| child.encode(e))
+                    })
+                }
+                Node::NamedCondition(ref name, _) => {
+                    e.emit_enum_variant("Condition", 4, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| name.encode(e))
+                    })
+                }
+                Node::NamedAction(ref name, _) => {
+                    e.emit_enum_variant("Action", 5, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| name.encode(e))
+                    })
+                }
+                Node::Condition(_) | Node::Action(_) => {
+                    e.emit_enum_variant("Anonymous", 6, 0, |_| Ok(()))
+                }
+            }
+        })
+    }
+}
+
+impl Decodable for Node {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Node, D::Error> {
+        let names = ["Sequence", "Selector", "Parallel", "Decorator", "Condition", "Action",
+                     "Anonymous"];
+        d.read_enum("Node", |d| {
+            d.read_enum_variant(&names, |d, index| {
+                match index {
+                    0 => d.read_enum_variant_arg(0, Decodable::decode).map(Node::Sequence),
+                    1 => d.read_enum_variant_arg(0, Decodable::decode).map(Node::Selector),
+                    2 => {
+                        let threshold = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        let children = d.read_enum_variant_arg(1, Decodable::decode)?;
+                        Ok(Node::Parallel(threshold, children))
+                    }
+                    3 => {
+                        let decorator = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        let child = d.read_enum_variant_arg(1, Decodable::decode)?;
+                        Ok(Node::Decorator(decorator, child))
+                    }
+                    4 => {
+                        let name: String = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        match tree_condition(&name) {
+                            Some(condition) => Ok(Node::NamedCondition(name, condition)),
+                            None => Err(d.error(&format!("unknown tree condition {:?}", name))),
+                        }
+                    }
+                    5 => {
+                        let name: String = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        match tree_action(&name) {
+                            Some(action) => Ok(Node::NamedAction(name, action)),
+                            None => Err(d.error(&format!("unknown tree action {:?}", name))),
+                        }
+                    }
+                    _ => Err(d.error("anonymous tree nodes can't be loaded")),
+                }
+            })
+        })
+    }
+}
+
+/// A behaviour tree controlling an entity on its own, like a wolf hunting the player
+///
+/// The tree is ticked from the root on every update of the world, so a node that was running
+/// is not resumed directly but reached again through the conditions in front of it. Anything
+/// that has to be remembered between ticks is stored in the state of the entity. Events are
+/// passed on to the next behaviour.
+///
+/// Only trees without closures can be saved, so a tree that has to be saved uses conditions and
+/// actions registered with a name instead, see `Node::named_condition` and `Node::named_action`.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rand;
+/// # extern crate rpg;
+/// # use rand::{SeedableRng, XorShiftRng};
+/// # use rpg::behaviour::{BehaviourTree, Node, Status};
+/// # use rpg::character::Character;
+/// # use rpg::entity::Entity;
+/// # use rpg::world::two_dimensional::{Field, FieldType, World2d};
+/// # fn main() {
+/// let tree = BehaviourTree::new(Node::selector(vec![
+///     // Hunt the player if they are close
+///     Node::sequence(vec![
+///         Node::condition(|context, _| context.distance_to_player() <= 3),
+///         Node::action(|context, _| {
+///             let direction = context.direction_to_player();
+///             context.walk(direction);
+///             Status::Running
+///         }),
+///     ]),
+///     // Otherwise sleep
+///     Node::action(|_, state| {
+///         state.set("asleep", true);
+///         Status::Success
+///     }),
+/// ]));
+///
+/// let mut wolf = Entity::new("Wolf");
+/// wolf.append_behaviour(tree);
+///
+/// let mut world = World2d::new("Forest", (10, 1));
+/// world.set_field(Field::new(FieldType::Grass).entity(wolf), (3, 0));
+///
+/// let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+/// world.tick(&Character::new("Michael"), &mut rng);
+///
+/// assert!(world.get_field((2, 0)).unwrap().entity.is_some());
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BehaviourTree {
+    root: Node,
+}
+
+impl BehaviourTree {
+    /// Creates a new instance of `BehaviourTree` with the given root node
+    pub fn new(root: Node) -> BehaviourTree {
+        BehaviourTree { root }
+    }
+}
+
+impl Behaviour for BehaviourTree {
+    fn handle_event(&self, _: &Event, _: &mut EntityState) -> BehaviourResult {
+        BehaviourResult::Pass
+    }
+
+    fn handles(&self, _: EventKind) -> bool {
+        false
+    }
+
+    fn tick(&self, context: &mut TickContext, state: &mut EntityState) {
+        self.root.tick(context, state);
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        if self.root.can_be_saved() {
+            SavedBehaviour::new(self)
+        } else {
+            None
+        }
+    }
+}
+
+impl SaveableBehaviour for BehaviourTree {
+    const TAG: &'static str = "behaviour_tree";
+}
+
+impl_serialize_struct!(BehaviourTree { root });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, XorShiftRng};
+    use behaviour::DefaultResponse;
+    use character::{Attribute, Character};
+    use entity::Entity;
+    use rustc_serialize::json;
+    use world::two_dimensional::{Field, FieldType, World2d};
+
+    fn status(status: Status) -> Node {
+        Node::action(move |_, _| status)
+    }
+
+    fn count(key: &'static str, status: Status) -> Node {
+        Node::action(move |_, state| {
+            let count = state.get_int(key).unwrap_or(0);
+            state.set(key, count + 1);
+            status
+        })
+    }
+
+    fn tick(node: &Node, state: &mut EntityState) -> Status {
+        let world = World2d::new("Void", (1, 1));
+        let character = Character::new("Michael");
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut context = TickContext::new(&world, (0, 0), &character, &mut rng);
+        node.tick(&mut context, state)
+    }
+
+    #[test]
+    fn composite_nodes() {
+        let mut state = EntityState::new();
+
+        let sequence = Node::sequence(vec![count("a", Status::Success),
+                                           count("b", Status::Running),
+                                           count("c", Status::Success)]);
+        assert_eq!(tick(&sequence, &mut state), Status::Running);
+        assert_eq!(state.get_int("c"), None);
+
+        let selector = Node::selector(vec![count("d", Status::Failure),
+                                           count("e", Status::Success),
+                                           count("f", Status::Success)]);
+        assert_eq!(tick(&selector, &mut state), Status::Success);
+        assert_eq!(state.get_int("f"), None);
+
+        let parallel = Node::parallel(2,
+                                      vec![status(Status::Success),
+                                           status(Status::Running),
+                                           status(Status::Failure)]);
+        assert_eq!(tick(&parallel, &mut state), Status::Running);
+        let parallel = Node::parallel(2,
+                                      vec![status(Status::Success),
+                                           status(Status::Failure),
+                                           status(Status::Failure)]);
+        assert_eq!(tick(&parallel, &mut state), Status::Failure);
+        let parallel = Node::parallel(1, vec![status(Status::Running), status(Status::Success)]);
+        assert_eq!(tick(&parallel, &mut state), Status::Success);
+    }
+
+    #[test]
+    fn decorators() {
+        let mut state = EntityState::new();
+        let decorated = |decorator, child| Node::decorate(decorator, child);
+
+        assert_eq!(tick(&decorated(Decorator::Invert, status(Status::Success)), &mut state),
+                   Status::Failure);
+        assert_eq!(tick(&decorated(Decorator::Invert, status(Status::Running)), &mut state),
+                   Status::Running);
+        assert_eq!(tick(&decorated(Decorator::Succeed, status(Status::Failure)), &mut state),
+                   Status::Success);
+        assert_eq!(tick(&decorated(Decorator::Fail, status(Status::Success)), &mut state),
+                   Status::Failure);
+
+        let repeat = decorated(Decorator::Repeat(3), count("repeat", Status::Success));
+        assert_eq!(tick(&repeat, &mut state), Status::Success);
+        assert_eq!(state.get_int("repeat"), Some(3));
+
+        let never = decorated(Decorator::Repeat(0), count("never", Status::Failure));
+        assert_eq!(tick(&never, &mut state), Status::Success);
+        assert_eq!(state.get_int("never"), None);
+    }
+
+    fn is_awake(_: &TickContext, state: &EntityState) -> bool {
+        state.get_bool("awake").unwrap_or(false)
+    }
+
+    fn wake_up(_: &mut TickContext, state: &mut EntityState) -> Status {
+        state.set("awake", true);
+        Status::Success
+    }
+
+    #[test]
+    fn save_named_nodes() {
+        register_tree_condition("is_awake", is_awake);
+        register_tree_action("wake_up", wake_up);
+
+        let tree = BehaviourTree::new(Node::selector(vec![
+            Node::named_condition("is_awake"),
+            Node::decorate(Decorator::Repeat(2), Node::named_action("wake_up")),
+        ]));
+        let mut bear = Entity::new("Bear");
+        bear.append_behaviour(tree.clone());
+        assert!(bear.can_be_saved());
+
+        let loaded: BehaviourTree = json::decode(&json::encode(&tree).unwrap()).unwrap();
+        let mut state = EntityState::new();
+        assert_eq!(tick(&loaded.root, &mut state), Status::Success);
+        assert_eq!(state.get_bool("awake"), Some(true));
+
+        let anonymous = BehaviourTree::new(Node::sequence(vec![Node::named_action("wake_up"),
+                                                               status(Status::Success)]));
+        assert!(anonymous.save().is_none());
+        assert!(json::decode::<BehaviourTree>(&json::encode(&anonymous).unwrap()).is_err());
+
+        let unknown = r#"{"root":{"variant":"Action","fields":["sleep"]}}"#;
+        match json::decode::<BehaviourTree>(unknown) {
+            Err(json::DecoderError::ApplicationError(error)) => {
+                assert_eq!(error, "unknown tree action \"sleep\"")
+            }
+            _ => panic!("a tree with an unknown action was loaded"),
+        }
+    }
+
+    #[test]
+    fn hunt_weak_player() {
+        let tree = BehaviourTree::new(Node::selector(vec![
+            // Flee from strong players
+            Node::sequence(vec![
+                Node::condition(|context, _| {
+                    context.character.get_attribute_value(&Attribute::Strength) > 20
+                }),
+                Node::action(|context, _| {
+                    let (x, y) = context.direction_to_player();
+                    context.walk((-x, -y));
+                    Status::Running
+                }),
+            ]),
+            Node::sequence(vec![
+                Node::condition(|context, _| context.distance_to_player() > 1),
+                Node::action(|context, _| {
+                    let direction = context.direction_to_player();
+                    context.walk(direction);
+                    Status::Running
+                }),
+            ]),
+            Node::action(|_, state| {
+                state.set("attacking", true);
+                Status::Success
+            }),
+        ]));
+
+        let mut wolf = Entity::new("Wolf");
+        wolf.append_behaviour(tree);
+        wolf.append_behaviour(DefaultResponse::new("Grrr!"));
+        assert!(!wolf.can_be_saved());
+
+        let mut world = World2d::new("Forest", (6, 1));
+        world.set_field(Field::new(FieldType::Grass).entity(wolf), (3, 0));
+
+        let mut character = Character::new("Michael");
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let attacking = |world: &World2d, position| {
+            world.get_field(position)
+                .and_then(|field| field.entity.as_ref())
+                .and_then(|wolf| wolf.state().get_bool("attacking"))
+        };
+
+        world.tick(&character, &mut rng);
+        world.tick(&character, &mut rng);
+        assert_eq!(attacking(&world, (1, 0)), None);
+        world.tick(&character, &mut rng);
+        assert_eq!(attacking(&world, (1, 0)), Some(true));
+
+        character.update_attribute(&Attribute::Strength, 30);
+        world.tick(&character, &mut rng);
+        assert_eq!(attacking(&world, (2, 0)), Some(true));
+
+        // Events still reach the rest of the chain
+        assert_eq!(world.interact((2, 0), Event::Nothing), Some(Event::Tell("Grrr!".into())));
+    }
+}
@@ -0,0 +1,113 @@
+use entity::EntityState;
+use event::{Event, EventKind};
+use item::Item;
+use super::{Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour};
+
+/// A container like a chest or a barrel holding a limited amount of items
+///
+/// Opening it responds with an `Event::Options` offering every item inside as an
+/// `Event::Give`. Given items are put inside and answered with `Event::Nothing`, or handed back
+/// if the container is full. The items are stored in the entity under `items`.
+#[derive(Clone)]
+pub struct Container {
+    capacity: usize,
+}
+
+impl_serialize_struct!(Container { capacity });
+
+impl Container {
+    /// Creates a new instance of `Container` holding at most `capacity` items
+    pub fn new(capacity: usize) -> Container {
+        Container { capacity }
+    }
+
+    /// Returns the items inside the container
+    pub fn items(state: &EntityState) -> Vec<Item> {
+        state.get_items("items").map(|items| items.to_vec()).unwrap_or_default()
+    }
+
+    /// Puts an item inside the container. Returns `Err(Item)` if the container is full.
+    pub fn put(&self, state: &mut EntityState, item: Item) -> Result<(), Item> {
+        let mut items = Container::items(state);
+        if items.len() >= self.capacity {
+            return Err(item);
+        }
+
+        items.push(item);
+        state.set("items", items);
+        Ok(())
+    }
+
+    /// Takes the first item with the given name out of the container
+    pub fn take(state: &mut EntityState, name: &str) -> Option<Item> {
+        let mut items = Container::items(state);
+        let index = items.iter().position(|item| item.name == name)?;
+        let item = items.remove(index);
+        state.set("items", items);
+        Some(item)
+    }
+}
+
+impl Behaviour for Container {
+    fn handle_event(&self, event: &Event, state: &mut EntityState) -> BehaviourResult {
+        match *event {
+            Event::Open => {
+                let items = Container::items(state).into_iter().map(Event::Give).collect();
+                BehaviourResult::Handled(Event::Options(items))
+            }
+            Event::Give(ref item) => {
+                match self.put(state, item.clone()) {
+                    Ok(()) => BehaviourResult::Handled(Event::Nothing),
+                    Err(item) => BehaviourResult::Handled(Event::Give(item)),
+                }
+            }
+            _ => BehaviourResult::Pass,
+        }
+    }
+
+    fn handles(&self, kind: EventKind) -> bool {
+        kind == EventKind::Open || kind == EventKind::Give
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        SavedBehaviour::new(self)
+    }
+}
+
+impl SaveableBehaviour for Container {
+    const TAG: &'static str = "container";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::Entity;
+    use item::{ItemRarity, ItemType};
+
+    fn item(name: &str) -> Item {
+        Item {
+            name: name.to_owned(),
+            item_type: ItemType::ConsumableFood,
+            influence: None,
+            stack_size: 10,
+            rarity: ItemRarity::Common,
+        }
+    }
+
+    #[test]
+    fn give_and_open() {
+        let mut chest = Entity::new("Chest");
+        chest.append_behaviour(Container::new(2));
+
+        assert_eq!(chest.send_event(Event::Open), Event::Options(vec![]));
+        assert_eq!(chest.send_event(Event::Give(item("Apple"))), Event::Nothing);
+        assert_eq!(chest.send_event(Event::Give(item("Bread"))), Event::Nothing);
+        assert_eq!(chest.send_event(Event::Give(item("Cheese"))), Event::Give(item("Cheese")));
+        assert_eq!(chest.send_event(Event::Open),
+                   Event::Options(vec![Event::Give(item("Apple")), Event::Give(item("Bread"))]));
+
+        assert_eq!(Container::take(chest.state_mut(), "Apple"), Some(item("Apple")));
+        assert_eq!(Container::take(chest.state_mut(), "Apple"), None);
+        assert_eq!(Container::items(chest.state()), vec![item("Bread")]);
+    }
+}
@@ -1,12 +1,16 @@
+use entity::EntityState;
 use event::Event;
-use super::Behaviour;
+use super::{Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour};
 
-/// A default reponse when a character is talking to an entity
+/// A default reponse when a character is talking to an entity. Handles every event, so it is
+/// usually the last behaviour of an entity.
 #[derive(Clone)]
 pub struct DefaultResponse {
     response: String,
 }
 
+impl_serialize_struct!(DefaultResponse { response });
+
 impl DefaultResponse {
     /// Creates a new instance of `DefaultResponse`
     pub fn new(response: &str) -> DefaultResponse {
@@ -15,11 +19,19 @@ impl DefaultResponse {
 }
 
 impl Behaviour for DefaultResponse {
-    fn handle_event(&self, _: Event) -> Event {
-        Event::Tell(self.response.clone())
+    fn handle_event(&self, _: &Event, _: &mut EntityState) -> BehaviourResult {
+        BehaviourResult::Handled(Event::Tell(self.response.clone()))
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        SavedBehaviour::new(self)
     }
 }
 
+impl SaveableBehaviour for DefaultResponse {
+    const TAG: &'static str = "default_response";
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
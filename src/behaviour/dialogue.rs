@@ -0,0 +1,615 @@
+use std::collections::BTreeMap;
+
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use character::{Attribute, Character};
+use entity::EntityState;
+use event::{Event, EventKind};
+use item::Item;
+use types::{AttributeValue, Gold};
+use super::{Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour};
+
+/// The key the current node of a dialogue is stored under in the state of the entity
+const CURRENT_NODE: &str = "dialogue";
+
+/// A condition a choice of a dialogue is only offered under
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueCondition {
+    /// The attribute of the character is at least the given value
+    Attribute(Attribute, AttributeValue),
+    /// The character has an item with the given name
+    Item(String),
+    /// The character has at least the given amount of gold
+    Gold(Gold),
+    /// The flag with the given name is set in the state of the entity
+    Flag(String),
+    /// The number with the given name in the state of the entity is at least the given value
+    Number(String, i64),
+    /// The condition does not hold
+    Not(Box<DialogueCondition>),
+}
+
+impl DialogueCondition {
+    /// Returns `true` if the condition holds. Conditions on the character never hold without
+    /// one.
+    pub fn holds(&self, state: &EntityState, character: Option<&Character>) -> bool {
+        match *self {
+            DialogueCondition::Attribute(ref attribute, value) => {
+                character.is_some_and(|character| {
+                    character.get_attribute_value(attribute) >= value
+                })
+            }
+            DialogueCondition::Item(ref name) => {
+                character.is_some_and(|character| character.inventory().contains(name))
+            }
+            DialogueCondition::Gold(gold) => {
+                character.is_some_and(|character| character.inventory().gold() >= gold)
+            }
+            DialogueCondition::Flag(ref name) => state.get_bool(name).unwrap_or(false),
+            DialogueCondition::Number(ref name, value) => state.get_int(name).unwrap_or(0) >= value,
+            DialogueCondition::Not(ref condition) => !condition.holds(state, character),
+        }
+    }
+}
+
+impl Encodable for DialogueCondition {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_enum("DialogueCondition", |e| {
+            match *self {
+                DialogueCondition::Attribute(ref attribute, value) => {
+                    e.emit_enum_variant("Attribute", 0, 2, |e| {
+                        e.emit_enum_variant_arg(0, |e| attribute.encode(e))?;
+                        e.emit_enum_variant_arg(1, |e| value.encode(e))
+                    })
+                }
+                DialogueCondition::Item(ref name) => {
+                    e.emit_enum_variant("Item", 1, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| name.encode(e))
+                    })
+                }
+                DialogueCondition::Gold(gold) => {
+                    e.emit_enum_variant("Gold", 2, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| gold.encode(e))
+                    })
+                }
+                DialogueCondition::Flag(ref name) => {
+                    e.emit_enum_variant("Flag", 3, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| name.encode(e))
+                    })
+                }
+                DialogueCondition::Number(ref name, value) => {
+                    e.emit_enum_variant("Number", 4, 2, |e| {
+                        e.emit_enum_variant_arg(0, |e| name.encode(e))?;
+                        e.emit_enum_variant_arg(1, |e| value.encode(e))
+                    })
+                }
+                DialogueCondition::Not(ref condition) => {
+                    e.emit_enum_variant("Not", 5, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| condition.encode(e))
+                    })
+                }
+            }
+        })
+    }
+}
+
+impl Decodable for DialogueCondition {
+    fn decode<D: Decoder>(d: &mut D) -> Result<DialogueCondition, D::Error> {
+        let names = ["Attribute", "Item", "Gold", "Flag", "Number", "Not"];
+        d.read_enum("DialogueCondition", |d| {
+            d.read_enum_variant(&names, |d, index| {
+                match index {
+                    0 => {
+                        let attribute = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        let value = d.read_enum_variant_arg(1, Decodable::decode)?;
+                        Ok(DialogueCondition::Attribute(attribute, value))
+                    }
+                    1 => d.read_enum_variant_arg(0, Decodable::decode).map(DialogueCondition::Item),
+                    2 => d.read_enum_variant_arg(0, Decodable::decode).map(DialogueCondition::Gold),
+                    3 => d.read_enum_variant_arg(0, Decodable::decode).map(DialogueCondition::Flag),
+                    4 => {
+                        let name = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        let value = d.read_enum_variant_arg(1, Decodable::decode)?;
+                        Ok(DialogueCondition::Number(name, value))
+                    }
+                    _ => d.read_enum_variant_arg(0, Decodable::decode).map(DialogueCondition::Not),
+                }
+            })
+        })
+    }
+}
+
+/// A side effect of choosing a choice of a dialogue
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueEffect {
+    /// Gives the item to the character. It is lost if the inventory is full.
+    GiveItem(Item),
+    /// Takes an item with the given name from the character
+    TakeItem(String),
+    /// Gives the amount of gold to the character
+    GiveGold(Gold),
+    /// Takes the amount of gold from the character, if it has enough
+    TakeGold(Gold),
+    /// Sets the flag with the given name in the state of the entity
+    SetFlag(String, bool),
+    /// Sets the number with the given name in the state of the entity
+    SetNumber(String, i64),
+}
+
+impl DialogueEffect {
+    /// Applies the effect. Effects on the character are skipped without one.
+    pub fn apply(&self, state: &mut EntityState, character: Option<&mut Character>) {
+        match *self {
+            DialogueEffect::SetFlag(ref name, value) => state.set(name, value),
+            DialogueEffect::SetNumber(ref name, value) => state.set(name, value),
+            _ => {}
+        }
+
+        let inventory = match character {
+            Some(character) => character.inventory_mut(),
+            None => return,
+        };
+        match *self {
+            DialogueEffect::GiveItem(ref item) => {
+                let _ = inventory.add_item(item.clone());
+            }
+            DialogueEffect::TakeItem(ref name) => {
+                inventory.remove_item(name);
+            }
+            DialogueEffect::GiveGold(gold) => inventory.add_gold(gold),
+            DialogueEffect::TakeGold(gold) => {
+                inventory.remove_gold(gold);
+            }
+            DialogueEffect::SetFlag(..) | DialogueEffect::SetNumber(..) => {}
+        }
+    }
+}
+
+impl Encodable for DialogueEffect {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_enum("DialogueEffect", |e| {
+            match *self {
+                DialogueEffect::GiveItem(ref item) => {
+                    e.emit_enum_variant("GiveItem", 0, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| item.encode(e))
+                    })
+                }
+                DialogueEffect::TakeItem(ref name) => {
+                    e.emit_enum_variant("TakeItem", 1, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| name.encode(e))
+                    })
+                }
+                DialogueEffect::GiveGold(gold) => {
+                    e.emit_enum_variant("GiveGold", 2, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| gold.encode(e))
+                    })
+                }
+                DialogueEffect::TakeGold(gold) => {
+                    e.emit_enum_variant("TakeGold", 3, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| gold.encode(e))
+                    })
+                }
+                DialogueEffect::SetFlag(ref name, value) => {
+                    e.emit_enum_variant("SetFlag", 4, 2, |e| {
+                        e.emit_enum_variant_arg(0, |e| name.encode(e))?;
+                        e.emit_enum_variant_arg(1, |e| value.encode(e))
+                    })
+                }
+                DialogueEffect::SetNumber(ref name, value) => {
+                    e.emit_enum_variant("SetNumber", 5, 2, |e| {
+                        e.emit_enum_variant_arg(0, |e| name.encode(e))?;
+                        e.emit_enum_variant_arg(1, |e| value.encode(e))
+                    })
+                }
+            }
+        })
+    }
+}
+
+impl Decodable for DialogueEffect {
+    fn decode<D: Decoder>(d: &mut D) -> Result<DialogueEffect, D::Error> {
+        let names = ["GiveItem", "TakeItem", "GiveGold", "TakeGold", "SetFlag", "SetNumber"];
+        d.read_enum("DialogueEffect", |d| {
+            d.read_enum_variant(&names, |d, index| {
+                match index {
+                    0 => {
+                        d.read_enum_variant_arg(0, Decodable::decode).map(DialogueEffect::GiveItem)
+                    }
+                    1 => {
+                        d.read_enum_variant_arg(0, Decodable::decode).map(DialogueEffect::TakeItem)
+                    }
+                    2 => {
+                        d.read_enum_variant_arg(0, Decodable::decode).map(DialogueEffect::GiveGold)
+                    }
+                    3 => {
+                        d.read_enum_variant_arg(0, Decodable::decode).map(DialogueEffect::TakeGold)
+                    }
+                    4 => {
+                        let name = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        let value = d.read_enum_variant_arg(1, Decodable::decode)?;
+                        Ok(DialogueEffect::SetFlag(name, value))
+                    }
+                    _ => {
+                        let name = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        let value = d.read_enum_variant_arg(1, Decodable::decode)?;
+                        Ok(DialogueEffect::SetNumber(name, value))
+                    }
+                }
+            })
+        })
+    }
+}
+
+/// A choice of the player in a dialogue
+#[derive(Clone, Debug, PartialEq)]
+pub struct Choice {
+    /// The text the player says
+    pub text: String,
+    /// The node the dialogue continues with, or `None` if the choice ends it
+    pub next: Option<String>,
+    /// The conditions that have to hold for the choice to be offered
+    pub conditions: Vec<DialogueCondition>,
+    /// The effects of choosing the choice
+    pub effects: Vec<DialogueEffect>,
+}
+
+impl_serialize_struct!(Choice {
+    text,
+    next,
+    conditions,
+    effects,
+});
+
+impl Choice {
+    /// Creates a new instance of `Choice` continuing with the given node
+    pub fn new(text: &str, next: &str) -> Choice {
+        Choice {
+            text: text.to_owned(),
+            next: Some(next.to_owned()),
+            conditions: Vec::new(),
+            effects: Vec::new(),
+        }
+    }
+
+    /// Creates a new instance of `Choice` ending the dialogue
+    pub fn end(text: &str) -> Choice {
+        Choice {
+            next: None,
+            ..Choice::new(text, "")
+        }
+    }
+
+    /// A builder method for adding a condition
+    pub fn condition(mut self, condition: DialogueCondition) -> Choice {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// A builder method for adding an effect
+    pub fn effect(mut self, effect: DialogueEffect) -> Choice {
+        self.effects.push(effect);
+        self
+    }
+
+    /// Returns `true` if all conditions of the choice hold
+    pub fn is_available(&self, state: &EntityState, character: Option<&Character>) -> bool {
+        self.conditions.iter().all(|condition| condition.holds(state, character))
+    }
+}
+
+/// A node of a dialogue: a line of the NPC, followed either by choices of the player or by the
+/// next node
+#[derive(Clone, Debug, PartialEq)]
+pub struct DialogueNode {
+    /// The line the NPC says
+    pub line: String,
+    /// The node the dialogue continues with if there are no choices. Without choices and a next
+    /// node, the dialogue ends after the line.
+    pub next: Option<String>,
+    /// The choices of the player
+    pub choices: Vec<Choice>,
+}
+
+impl_serialize_struct!(DialogueNode { line, next, choices });
+
+impl DialogueNode {
+    /// Creates a new instance of `DialogueNode` with the given line
+    pub fn new(line: &str) -> DialogueNode {
+        DialogueNode {
+            line: line.to_owned(),
+            next: None,
+            choices: Vec::new(),
+        }
+    }
+
+    /// A builder method for setting the next node
+    pub fn next(mut self, next: &str) -> DialogueNode {
+        self.next = Some(next.to_owned());
+        self
+    }
+
+    /// A builder method for adding a choice
+    pub fn choice(mut self, choice: Choice) -> DialogueNode {
+        self.choices.push(choice);
+        self
+    }
+}
+
+/// A branching dialogue an NPC has with the player
+///
+/// Every `Event::Tell` moves the dialogue on:
+///
+/// * Entering a node responds with its line as an `Event::Tell`.
+/// * On a node with choices, telling the text of an available choice applies its effects and
+///   enters its next node. Telling anything else responds with an `Event::Options` of the
+///   texts of all available choices as `Event::Tell`.
+/// * On a node without choices, the dialogue enters the next node.
+///
+/// A choice or node without a next node ends the dialogue, which starts again with the next
+/// `Event::Tell`. The current node is stored in the entity under `dialogue`.
+///
+/// Conditions and effects on the character of the player only work with events sent by
+/// `Entity::send_player_event`.
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::behaviour::{Choice, Dialogue, DialogueEffect, DialogueNode};
+/// # use rpg::character::Character;
+/// # use rpg::entity::Entity;
+/// # use rpg::event::Event;
+/// let dialogue = Dialogue::new("start")
+///     .node("start",
+///           DialogueNode::new("Care for a coin?")
+///               .choice(Choice::end("Yes").effect(DialogueEffect::GiveGold(1)))
+///               .choice(Choice::end("No")));
+///
+/// let mut beggar = Entity::new("Beggar");
+/// beggar.append_behaviour(dialogue);
+/// let mut character = Character::new("Michael");
+///
+/// let tell = |text: &str| Event::Tell(text.to_owned());
+/// assert_eq!(beggar.send_player_event(tell("Hello"), &mut character), tell("Care for a coin?"));
+/// assert_eq!(beggar.send_player_event(tell("Hmm"), &mut character),
+///            Event::Options(vec![tell("Yes"), tell("No")]));
+/// assert_eq!(beggar.send_player_event(tell("Yes"), &mut character), Event::Nothing);
+/// assert_eq!(character.inventory().gold(), 1);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dialogue {
+    start: String,
+    nodes: BTreeMap<String, DialogueNode>,
+}
+
+impl_serialize_struct!(Dialogue { start, nodes });
+
+impl Dialogue {
+    /// Creates a new instance of `Dialogue` starting with the given node
+    pub fn new(start: &str) -> Dialogue {
+        Dialogue {
+            start: start.to_owned(),
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// A builder method for adding a node, replacing a previous one with the same name
+    pub fn node(mut self, name: &str, node: DialogueNode) -> Dialogue {
+        self.nodes.insert(name.to_owned(), node);
+        self
+    }
+
+    /// Returns the name of the node the dialogue starts with
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+
+    /// Returns the node with the given name
+    pub fn get_node(&self, name: &str) -> Option<&DialogueNode> {
+        self.nodes.get(name)
+    }
+
+    /// Returns the name of the current node, or `None` if the dialogue has not started
+    pub fn current(state: &EntityState) -> Option<&str> {
+        state.get_text(CURRENT_NODE)
+    }
+
+    fn respond(&self,
+               text: &str,
+               state: &mut EntityState,
+               mut character: Option<&mut Character>)
+               -> BehaviourResult {
+        let current = match Dialogue::current(state) {
+            Some(current) => current.to_owned(),
+            None => return self.enter(&self.start, state),
+        };
+        let node = match self.nodes.get(&current) {
+            Some(node) => node,
+            None => return self.enter(&self.start, state),
+        };
+
+        if node.choices.is_empty() {
+            return match node.next {
+                Some(ref next) => self.enter(next, state),
+                None => self.enter(&self.start, state),
+            };
+        }
+
+        let available: Vec<&Choice> = node.choices
+            .iter()
+            .filter(|choice| choice.is_available(state, character.as_deref()))
+            .collect();
+
+        match available.iter().find(|choice| choice.text == text) {
+            Some(choice) => {
+                for effect in &choice.effects {
+                    effect.apply(state, character.as_deref_mut());
+                }
+
+                match choice.next {
+                    Some(ref next) => self.enter(next, state),
+                    None => {
+                        state.remove(CURRENT_NODE);
+                        BehaviourResult::Handled(Event::Nothing)
+                    }
+                }
+            }
+            None => {
+                let options = available.iter()
+                    .map(|choice| Event::Tell(choice.text.clone()))
+                    .collect();
+                BehaviourResult::Handled(Event::Options(options))
+            }
+        }
+    }
+
+    fn enter(&self, name: &str, state: &mut EntityState) -> BehaviourResult {
+        match self.nodes.get(name) {
+            Some(node) => {
+                if node.choices.is_empty() && node.next.is_none() {
+                    state.remove(CURRENT_NODE);
+                } else {
+                    state.set(CURRENT_NODE, name);
+                }
+
+                BehaviourResult::Handled(Event::Tell(node.line.clone()))
+            }
+            None => {
+                state.remove(CURRENT_NODE);
+                BehaviourResult::Handled(Event::Nothing)
+            }
+        }
+    }
+}
+
+impl Behaviour for Dialogue {
+    fn handle_event(&self, event: &Event, state: &mut EntityState) -> BehaviourResult {
+        match *event {
+            Event::Tell(ref text) => self.respond(text, state, None),
+            _ => BehaviourResult::Pass,
+        }
+    }
+
+    fn handle_player_event(&self,
+                           event: &Event,
+                           state: &mut EntityState,
+                           character: &mut Character)
+                           -> BehaviourResult {
+        match *event {
+            Event::Tell(ref text) => self.respond(text, state, Some(character)),
+            _ => BehaviourResult::Pass,
+        }
+    }
+
+    fn handles(&self, kind: EventKind) -> bool {
+        kind == EventKind::Tell
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        SavedBehaviour::new(self)
+    }
+}
+
+impl SaveableBehaviour for Dialogue {
+    const TAG: &'static str = "dialogue";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::Entity;
+    use item::{ItemRarity, ItemType};
+    use rustc_serialize::json;
+
+    fn tell(text: &str) -> Event {
+        Event::Tell(text.to_owned())
+    }
+
+    fn sword() -> Item {
+        Item {
+            name: "Sword".to_owned(),
+            item_type: ItemType::WeaponSword,
+            influence: None,
+            stack_size: 1,
+            rarity: ItemRarity::Rare,
+        }
+    }
+
+    fn blacksmith() -> Dialogue {
+        Dialogue::new("start")
+            .node("start",
+                  DialogueNode::new("What do you want?")
+                      .choice(Choice::new("Buy a sword", "sold")
+                          .condition(DialogueCondition::Gold(20))
+                          .effect(DialogueEffect::TakeGold(20))
+                          .effect(DialogueEffect::GiveItem(sword())))
+                      .choice(Choice::new("Give me a sword", "angry")
+                          .condition(DialogueCondition::Attribute(Attribute::Strength, 25))
+                          .condition(DialogueCondition::Not(Box::new(
+                              DialogueCondition::Flag("insulted".to_owned()))))
+                          .effect(DialogueEffect::SetFlag("insulted".to_owned(), true)))
+                      .choice(Choice::end("Nothing")))
+            .node("sold", DialogueNode::new("Here you go.").next("thanks"))
+            .node("thanks", DialogueNode::new("Come again!"))
+            .node("angry", DialogueNode::new("Get out!"))
+    }
+
+    #[test]
+    fn conversation() {
+        let mut smith = Entity::new("Blacksmith");
+        smith.append_behaviour(blacksmith());
+        let mut character = Character::new("Michael");
+
+        assert_eq!(smith.send_player_event(tell("Hi"), &mut character),
+                   tell("What do you want?"));
+        assert_eq!(smith.send_player_event(tell("Buy a sword"), &mut character),
+                   Event::Options(vec![tell("Nothing")]));
+
+        character.inventory_mut().add_gold(25);
+        character.update_attribute(&Attribute::Strength, 30);
+
+        assert_eq!(smith.send_player_event(tell("Buy a sword"), &mut character),
+                   tell("Here you go."));
+        assert_eq!(character.inventory().gold(), 5);
+        assert!(character.inventory().contains("Sword"));
+        assert_eq!(Dialogue::current(smith.state()), Some("sold"));
+
+        assert_eq!(smith.send_player_event(tell("Thanks"), &mut character),
+                   tell("Come again!"));
+        assert_eq!(Dialogue::current(smith.state()), None);
+
+        // The dialogue starts again
+        assert_eq!(smith.send_player_event(tell("Hi"), &mut character),
+                   tell("What do you want?"));
+        assert_eq!(smith.send_player_event(tell("Give me a sword"), &mut character),
+                   tell("Get out!"));
+        assert_eq!(smith.send_player_event(tell("Hi"), &mut character),
+                   tell("What do you want?"));
+        assert_eq!(smith.send_player_event(tell("?"), &mut character),
+                   Event::Options(vec![tell("Nothing")]));
+    }
+
+    #[test]
+    fn without_character() {
+        let mut smith = Entity::new("Blacksmith");
+        smith.append_behaviour(blacksmith());
+
+        assert_eq!(smith.send_event(tell("Hi")), tell("What do you want?"));
+        assert_eq!(smith.send_event(tell("?")), Event::Options(vec![tell("Nothing")]));
+        assert_eq!(smith.send_event(tell("Nothing")), Event::Nothing);
+        assert_eq!(smith.send_event(Event::Push), Event::Nothing);
+    }
+
+    #[test]
+    fn save_dialogue() {
+        let mut smith = Entity::new("Blacksmith");
+        smith.append_behaviour(blacksmith());
+        smith.send_event(tell("Hi"));
+
+        let mut loaded: Entity = json::decode(&json::encode(&smith).unwrap()).unwrap();
+
+        assert_eq!(loaded.send_event(tell("?")), Event::Options(vec![tell("Nothing")]));
+
+        let dialogue: Dialogue = json::decode(&json::encode(&blacksmith()).unwrap()).unwrap();
+        assert_eq!(dialogue, blacksmith());
+    }
+}
@@ -0,0 +1,611 @@
+use std::error::Error;
+use std::fmt;
+
+use character::Attribute;
+use item::Item;
+use super::{Choice, Dialogue, DialogueCondition, DialogueEffect, DialogueNode};
+
+/// The target of a divert ending the dialogue
+const END: &str = "END";
+
+/// An error in a dialogue script
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptError {
+    /// The line of the error, starting at `1`
+    pub line: usize,
+    /// The column of the error, starting at `1`
+    pub column: usize,
+    /// A description of the error
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ScriptError {}
+
+/// A parser for dialogue scripts, so dialogues can be written without touching any code
+///
+/// A script consists of labels, each starting a node of the dialogue. Blank lines and lines
+/// starting with `#` are skipped.
+///
+/// * `== name` starts a label. The dialogue starts at the first one. Names may contain
+///   letters, digits, `-` and `_`.
+/// * Every other line is said by the NPC. Consecutive lines are said one after another.
+/// * `-> name` continues with the given label after the last line, `-> END` ends the dialogue.
+///   Without it, the dialogue ends after the last line.
+/// * `* text -> name` is a choice of the player. It is followed by indented statements:
+///   * `if condition` only offers the choice if the condition holds. Conditions are `flag`,
+///     `variable >= 3`, `strength >= 20` for any attribute, `gold >= 10`, `has item name` and
+///     `not condition`.
+///   * `set flag = true` and `set variable = 3` set a flag or a variable of the entity.
+///   * `give gold 10` and `take gold 10` give gold to or take gold from the character.
+///   * `give item name` and `take item name` give or take an item. Items given have to be
+///     added to the parser with `DialogueParser::item`.
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::behaviour::Dialogue;
+/// # use rpg::entity::Entity;
+/// # use rpg::event::Event;
+/// let dialogue = Dialogue::parse("
+/// == start
+/// Halt! Who goes there?
+/// * A friend -> friend
+/// * None of your business -> END
+///     set rude = true
+///
+/// == friend
+/// Welcome, friend.
+/// ").unwrap();
+///
+/// let mut guard = Entity::new("Guard");
+/// guard.append_behaviour(dialogue);
+///
+/// let tell = |text: &str| Event::Tell(text.to_owned());
+/// assert_eq!(guard.send_event(tell("Hello")), tell("Halt! Who goes there?"));
+/// assert_eq!(guard.send_event(tell("A friend")), tell("Welcome, friend."));
+/// ```
+#[derive(Clone, Debug)]
+pub struct DialogueParser {
+    items: Vec<Item>,
+}
+
+impl DialogueParser {
+    /// Creates a new instance of `DialogueParser` without any items
+    pub fn new() -> DialogueParser {
+        DialogueParser { items: Vec::new() }
+    }
+
+    /// A builder method for adding an item the script can give to the character
+    pub fn item(mut self, item: Item) -> DialogueParser {
+        self.items.push(item);
+        self
+    }
+
+    /// Parses the given script
+    pub fn parse(&self, source: &str) -> Result<Dialogue, ScriptError> {
+        let mut script = Script {
+            items: &self.items,
+            nodes: Vec::new(),
+            label: None,
+            in_choice: false,
+            diverts: Vec::new(),
+        };
+
+        for (index, text) in source.lines().enumerate() {
+            let line = Line {
+                text,
+                number: index + 1,
+            };
+            let content = text.trim();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+
+            let indented = content.len() < text.trim_end().len();
+            script.statement(&line, content, indented)?;
+        }
+
+        script.finish()
+    }
+}
+
+impl Default for DialogueParser {
+    fn default() -> DialogueParser {
+        DialogueParser::new()
+    }
+}
+
+impl Dialogue {
+    /// Parses the given dialogue script. See `DialogueParser` for the format.
+    pub fn parse(source: &str) -> Result<Dialogue, ScriptError> {
+        DialogueParser::new().parse(source)
+    }
+}
+
+/// A line of a script
+struct Line<'a> {
+    text: &'a str,
+    number: usize,
+}
+
+impl<'a> Line<'a> {
+    /// Creates an error at the start of `part`, which has to be a part of the line
+    fn error(&self, part: &str, message: String) -> ScriptError {
+        let offset = part.as_ptr() as usize - self.text.as_ptr() as usize;
+        ScriptError {
+            line: self.number,
+            column: self.text[..offset].chars().count() + 1,
+            message,
+        }
+    }
+
+    /// Creates an error at the end of the line
+    fn error_at_end(&self, message: String) -> ScriptError {
+        let text = self.text.trim_end();
+        self.error(&text[text.len()..], message)
+    }
+}
+
+/// The label the parser is in
+struct Label {
+    name: String,
+    /// The amount of lines of the NPC after the label
+    lines: usize,
+    /// Whether the label ended with a divert
+    diverted: bool,
+    /// Whether the label ended with choices
+    choices: bool,
+    error: ScriptError,
+}
+
+/// A label a choice or divert leads to. Checked after all labels are known.
+struct Divert {
+    target: String,
+    error: ScriptError,
+}
+
+/// The state of a script while it is parsed
+struct Script<'a> {
+    items: &'a [Item],
+    nodes: Vec<(String, DialogueNode)>,
+    label: Option<Label>,
+    /// Whether the last statement was a choice, so indented statements belong to it
+    in_choice: bool,
+    diverts: Vec<Divert>,
+}
+
+impl<'a> Script<'a> {
+    fn statement(&mut self, line: &Line, content: &str, indented: bool) -> Result<(), ScriptError> {
+        if content.starts_with("==") {
+            return self.label(line, content);
+        }
+
+        if self.label.is_none() {
+            return Err(line.error(content, "expected a label like `== start`".to_owned()));
+        }
+
+        if indented && self.in_choice {
+            return self.choice_statement(line, content);
+        }
+
+        self.in_choice = false;
+        if let Some(rest) = content.strip_prefix("->") {
+            self.divert(line, rest)
+        } else if let Some(rest) = content.strip_prefix('*') {
+            self.choice(line, rest)
+        } else {
+            self.line(line, content)
+        }
+    }
+
+    fn label(&mut self, line: &Line, content: &str) -> Result<(), ScriptError> {
+        self.finish_label()?;
+
+        let name = content.trim_start_matches('=').trim();
+        if !is_name(name) {
+            return Err(line.error(name, format!("invalid label {:?}", name)));
+        }
+        if self.nodes.iter().any(|(other, _)| other == name) {
+            return Err(line.error(name, format!("duplicate label {:?}", name)));
+        }
+
+        self.label = Some(Label {
+            name: name.to_owned(),
+            lines: 0,
+            diverted: false,
+            choices: false,
+            error: line.error(content, format!("the label {:?} has no lines", name)),
+        });
+        self.in_choice = false;
+        Ok(())
+    }
+
+    fn line(&mut self, line: &Line, content: &str) -> Result<(), ScriptError> {
+        let label = self.label.as_mut().expect("a line outside of a label");
+        if label.diverted || label.choices {
+            return Err(line.error(content,
+                                  "unreachable line after choices or a divert".to_owned()));
+        }
+
+        let name = if label.lines == 0 {
+            label.name.clone()
+        } else {
+            format!("{}:{}", label.name, label.lines)
+        };
+        if label.lines > 0 {
+            if let Some((_, ref mut previous)) = self.nodes.last_mut() {
+                previous.next = Some(name.clone());
+            }
+        }
+
+        label.lines += 1;
+        self.nodes.push((name, DialogueNode::new(content)));
+        Ok(())
+    }
+
+    fn divert(&mut self, line: &Line, rest: &str) -> Result<(), ScriptError> {
+        let target = self.target(line, rest.trim())?;
+        {
+            let label = self.label.as_mut().expect("a divert outside of a label");
+            if label.lines == 0 {
+                return Err(line.error(rest,
+                                      "a divert has to follow a line of the NPC".to_owned()));
+            }
+            if label.diverted || label.choices {
+                return Err(line.error(rest,
+                                      "unexpected divert after choices or a divert".to_owned()));
+            }
+            label.diverted = true;
+        }
+
+        if let Some((_, ref mut node)) = self.nodes.last_mut() {
+            node.next = target;
+        }
+        Ok(())
+    }
+
+    fn choice(&mut self, line: &Line, rest: &str) -> Result<(), ScriptError> {
+        let arrow = match rest.find("->") {
+            Some(arrow) => arrow,
+            None => {
+                return Err(line.error_at_end("expected `->` followed by the label the choice \
+                                              leads to"
+                    .to_owned()))
+            }
+        };
+        let text = rest[..arrow].trim();
+        if text.is_empty() {
+            return Err(line.error(rest, "the choice has no text".to_owned()));
+        }
+        let next = self.target(line, rest[arrow + 2..].trim())?;
+
+        {
+            let label = self.label.as_mut().expect("a choice outside of a label");
+            if label.lines == 0 {
+                return Err(line.error(rest,
+                                      "choices have to follow a line of the NPC".to_owned()));
+            }
+            if label.diverted {
+                return Err(line.error(rest, "unexpected choice after a divert".to_owned()));
+            }
+            label.choices = true;
+        }
+
+        if let Some((_, ref mut node)) = self.nodes.last_mut() {
+            node.choices.push(Choice {
+                text: text.to_owned(),
+                next,
+                conditions: Vec::new(),
+                effects: Vec::new(),
+            });
+        }
+        self.in_choice = true;
+        Ok(())
+    }
+
+    /// Parses a statement belonging to the last choice
+    fn choice_statement(&mut self, line: &Line, content: &str) -> Result<(), ScriptError> {
+        let (keyword, rest) = split_word(content);
+        let choice = self.nodes
+            .last_mut()
+            .and_then(|(_, node)| node.choices.last_mut())
+            .expect("a statement outside of a choice");
+
+        match keyword {
+            "if" => choice.conditions.push(condition(line, rest)?),
+            "set" => {
+                let equals = match rest.find('=') {
+                    Some(equals) => equals,
+                    None => return Err(line.error_at_end("expected `=`".to_owned())),
+                };
+                let name = rest[..equals].trim();
+                let value = rest[equals + 1..].trim();
+                if !is_name(name) {
+                    return Err(line.error(rest, format!("invalid variable {:?}", name)));
+                }
+
+                let effect = match value {
+                    "true" => DialogueEffect::SetFlag(name.to_owned(), true),
+                    "false" => DialogueEffect::SetFlag(name.to_owned(), false),
+                    _ => DialogueEffect::SetNumber(name.to_owned(), number(line, value)?),
+                };
+                choice.effects.push(effect);
+            }
+            "give" | "take" => {
+                let (kind, argument) = split_word(rest);
+                let effect = match (keyword, kind) {
+                    ("give", "gold") => DialogueEffect::GiveGold(gold(line, argument)?),
+                    ("take", "gold") => DialogueEffect::TakeGold(gold(line, argument)?),
+                    (_, "item") if argument.is_empty() => {
+                        return Err(line.error_at_end("expected the name of an item".to_owned()))
+                    }
+                    ("give", "item") => {
+                        match self.items.iter().find(|item| item.name == argument) {
+                            Some(item) => DialogueEffect::GiveItem(item.clone()),
+                            None => {
+                                return Err(line.error(argument,
+                                                      format!("unknown item {:?}", argument)))
+                            }
+                        }
+                    }
+                    ("take", "item") => DialogueEffect::TakeItem(argument.to_owned()),
+                    _ => {
+                        return Err(line.error(kind,
+                                              format!("expected `gold` or `item`, found {:?}",
+                                                      kind)))
+                    }
+                };
+                choice.effects.push(effect);
+            }
+            _ => {
+                return Err(line.error(keyword,
+                                      format!("unknown statement {:?}, expected `if`, `set`, \
+                                               `give` or `take`",
+                                              keyword)))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses the label a choice or divert leads to
+    fn target(&mut self, line: &Line, target: &str) -> Result<Option<String>, ScriptError> {
+        if target == END {
+            return Ok(None);
+        }
+        if target.is_empty() {
+            return Err(line.error_at_end("expected a label".to_owned()));
+        }
+        if !is_name(target) {
+            return Err(line.error(target, format!("invalid label {:?}", target)));
+        }
+
+        self.diverts.push(Divert {
+            target: target.to_owned(),
+            error: line.error(target, format!("unknown label {:?}", target)),
+        });
+        Ok(Some(target.to_owned()))
+    }
+
+    /// Checks that the current label has lines
+    fn finish_label(&mut self) -> Result<(), ScriptError> {
+        match self.label.take() {
+            Some(ref label) if label.lines == 0 => Err(label.error.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(mut self) -> Result<Dialogue, ScriptError> {
+        self.finish_label()?;
+
+        for divert in &self.diverts {
+            if !self.nodes.iter().any(|(name, _)| *name == divert.target) {
+                return Err(divert.error.clone());
+            }
+        }
+
+        let start = match self.nodes.first() {
+            Some((start, _)) => start.clone(),
+            None => {
+                return Err(ScriptError {
+                    line: 1,
+                    column: 1,
+                    message: "the script has no labels".to_owned(),
+                })
+            }
+        };
+
+        Ok(self.nodes.into_iter().fold(Dialogue::new(&start), |dialogue, (name, node)| {
+            dialogue.node(&name, node)
+        }))
+    }
+}
+
+fn condition(line: &Line, text: &str) -> Result<DialogueCondition, ScriptError> {
+    let (keyword, rest) = split_word(text);
+    match keyword {
+        "not" => return Ok(DialogueCondition::Not(Box::new(condition(line, rest)?))),
+        "has" if !rest.is_empty() => return Ok(DialogueCondition::Item(rest.to_owned())),
+        "has" => return Err(line.error_at_end("expected the name of an item".to_owned())),
+        _ => {}
+    }
+
+    match text.find(">=") {
+        Some(operator) => {
+            let name = text[..operator].trim();
+            let value = text[operator + 2..].trim();
+            if name == "gold" {
+                Ok(DialogueCondition::Gold(gold(line, value)?))
+            } else if let Some(attribute) = Attribute::from_name(name) {
+                Ok(DialogueCondition::Attribute(attribute, number(line, value)?))
+            } else if is_name(name) {
+                Ok(DialogueCondition::Number(name.to_owned(), number(line, value)?))
+            } else {
+                Err(line.error(text, format!("invalid variable {:?}", name)))
+            }
+        }
+        None if is_name(text) => Ok(DialogueCondition::Flag(text.to_owned())),
+        None if text.is_empty() => Err(line.error_at_end("expected a condition".to_owned())),
+        None => Err(line.error(text, format!("invalid condition {:?}", text))),
+    }
+}
+
+fn number(line: &Line, text: &str) -> Result<i64, ScriptError> {
+    if text.is_empty() {
+        return Err(line.error_at_end("expected a number".to_owned()));
+    }
+
+    text.parse().map_err(|_| line.error(text, format!("invalid number {:?}", text)))
+}
+
+fn gold(line: &Line, text: &str) -> Result<usize, ScriptError> {
+    if text.is_empty() {
+        return Err(line.error_at_end("expected an amount of gold".to_owned()));
+    }
+
+    text.parse().map_err(|_| line.error(text, format!("invalid amount of gold {:?}", text)))
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() &&
+    name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Splits the first word off the given text. Both parts are slices of the text.
+fn split_word(text: &str) -> (&str, &str) {
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], text[end..].trim_start()),
+        None => (text, &text[text.len()..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use character::Character;
+    use entity::Entity;
+    use event::Event;
+    use item::{ItemRarity, ItemType};
+
+    fn tell(text: &str) -> Event {
+        Event::Tell(text.to_owned())
+    }
+
+    fn sword() -> Item {
+        Item {
+            name: "Sword".to_owned(),
+            item_type: ItemType::WeaponSword,
+            influence: None,
+            stack_size: 1,
+            rarity: ItemRarity::Rare,
+        }
+    }
+
+    const BLACKSMITH: &str = "
+# The blacksmith of the village
+== start
+Welcome to my forge.
+What do you want?
+* Buy a sword -> sold
+    if gold >= 20
+    take gold 20
+    give item Sword
+* Give me a sword -> angry
+    if strength >= 25
+    if not insulted
+    set insulted = true
+    set visits = 2
+* Nothing -> END
+
+== sold
+Here you go.
+-> start
+
+== angry
+Get out!
+";
+
+    #[test]
+    fn parse_script() {
+        let dialogue = DialogueParser::new().item(sword()).parse(BLACKSMITH).unwrap();
+
+        let expected = Dialogue::new("start")
+            .node("start", DialogueNode::new("Welcome to my forge.").next("start:1"))
+            .node("start:1",
+                  DialogueNode::new("What do you want?")
+                      .choice(Choice::new("Buy a sword", "sold")
+                          .condition(DialogueCondition::Gold(20))
+                          .effect(DialogueEffect::TakeGold(20))
+                          .effect(DialogueEffect::GiveItem(sword())))
+                      .choice(Choice::new("Give me a sword", "angry")
+                          .condition(DialogueCondition::Attribute(Attribute::Strength, 25))
+                          .condition(DialogueCondition::Not(Box::new(
+                              DialogueCondition::Flag("insulted".to_owned()))))
+                          .effect(DialogueEffect::SetFlag("insulted".to_owned(), true))
+                          .effect(DialogueEffect::SetNumber("visits".to_owned(), 2)))
+                      .choice(Choice::end("Nothing")))
+            .node("sold", DialogueNode::new("Here you go.").next("start"))
+            .node("angry", DialogueNode::new("Get out!"));
+
+        assert_eq!(dialogue, expected);
+    }
+
+    #[test]
+    fn run_script() {
+        let dialogue = DialogueParser::new().item(sword()).parse(BLACKSMITH).unwrap();
+        let mut smith = Entity::new("Blacksmith");
+        smith.append_behaviour(dialogue);
+        let mut character = Character::new("Michael");
+        character.inventory_mut().add_gold(30);
+
+        assert_eq!(smith.send_player_event(tell("Hi"), &mut character),
+                   tell("Welcome to my forge."));
+        assert_eq!(smith.send_player_event(tell("..."), &mut character),
+                   tell("What do you want?"));
+        assert_eq!(smith.send_player_event(tell("?"), &mut character),
+                   Event::Options(vec![tell("Buy a sword"), tell("Nothing")]));
+        assert_eq!(smith.send_player_event(tell("Buy a sword"), &mut character),
+                   tell("Here you go."));
+        assert!(character.inventory().contains("Sword"));
+        assert_eq!(smith.send_player_event(tell("Thanks"), &mut character),
+                   tell("Welcome to my forge."));
+    }
+
+    fn error(source: &str) -> (usize, usize, String) {
+        let error = Dialogue::parse(source).unwrap_err();
+        (error.line, error.column, error.message)
+    }
+
+    #[test]
+    fn script_errors() {
+        assert_eq!(error(""), (1, 1, "the script has no labels".to_owned()));
+        assert_eq!(error("Hello"), (1, 1, "expected a label like `== start`".to_owned()));
+        assert_eq!(error("== start\nHi\n== start"),
+                   (3, 4, "duplicate label \"start\"".to_owned()));
+        assert_eq!(error("== start\n== end\nBye"),
+                   (1, 1, "the label \"start\" has no lines".to_owned()));
+        assert_eq!(error("== start\nHi\n* Bye"),
+                   (3, 6, "expected `->` followed by the label the choice leads to".to_owned()));
+        assert_eq!(error("== start\nHi\n* Bye -> nowhere"),
+                   (3, 10, "unknown label \"nowhere\"".to_owned()));
+        assert_eq!(error("== start\nHi\n* Bye -> END\n  if gold >= lots"),
+                   (4, 14, "invalid amount of gold \"lots\"".to_owned()));
+        assert_eq!(error("== start\nHi\n* Bye -> END\n  give item Sword"),
+                   (4, 13, "unknown item \"Sword\"".to_owned()));
+        assert_eq!(error("== start\nHi\n* Bye -> END\n  dance"),
+                   (4,
+                    3,
+                    "unknown statement \"dance\", expected `if`, `set`, `give` or `take`"
+                        .to_owned()));
+        assert_eq!(error("== start\nHi\n-> END\nBye"),
+                   (4, 1, "unreachable line after choices or a divert".to_owned()));
+        assert_eq!(error("== start\nHi\n-> END\n* Bye -> END"),
+                   (4, 2, "unexpected choice after a divert".to_owned()));
+    }
+}
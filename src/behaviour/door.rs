@@ -0,0 +1,120 @@
+use entity::EntityState;
+use event::{Event, EventKind};
+use super::{Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour};
+
+/// A door that can be opened and closed. A locked door is unlocked by giving it its key.
+///
+/// Responds with `Event::Open` or `Event::Close` when the door moved and with the key when it
+/// was unlocked. Everything else, like opening a locked door, falls through to the next
+/// behaviour. The state is stored in the flags `open` and `locked` of the entity.
+#[derive(Clone)]
+pub struct Door {
+    key: Option<String>,
+}
+
+impl_serialize_struct!(Door { key });
+
+impl Door {
+    /// Creates a new instance of `Door` without a lock
+    pub fn new() -> Door {
+        Door { key: None }
+    }
+
+    /// Creates a new instance of `Door`, which is locked until it is given the item with the
+    /// given name
+    pub fn locked(key: &str) -> Door {
+        Door { key: Some(key.to_owned()) }
+    }
+
+    /// Returns `true` if the door is locked
+    pub fn is_locked(&self, state: &EntityState) -> bool {
+        self.key.is_some() && state.get_bool("locked").unwrap_or(true)
+    }
+
+    /// Returns `true` if the door is open
+    pub fn is_open(state: &EntityState) -> bool {
+        state.get_bool("open").unwrap_or(false)
+    }
+}
+
+impl Default for Door {
+    fn default() -> Door {
+        Door::new()
+    }
+}
+
+impl Behaviour for Door {
+    fn handle_event(&self, event: &Event, state: &mut EntityState) -> BehaviourResult {
+        match *event {
+            Event::Give(ref item) if self.key.as_ref() == Some(&item.name) => {
+                state.set("locked", false);
+                BehaviourResult::Handled(Event::Give(item.clone()))
+            }
+            Event::Open if !self.is_locked(state) && !Door::is_open(state) => {
+                state.set("open", true);
+                BehaviourResult::Handled(Event::Open)
+            }
+            Event::Close if Door::is_open(state) => {
+                state.set("open", false);
+                BehaviourResult::Handled(Event::Close)
+            }
+            _ => BehaviourResult::Pass,
+        }
+    }
+
+    fn handles(&self, kind: EventKind) -> bool {
+        kind == EventKind::Open || kind == EventKind::Close || kind == EventKind::Give
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        SavedBehaviour::new(self)
+    }
+}
+
+impl SaveableBehaviour for Door {
+    const TAG: &'static str = "door";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use behaviour::DefaultResponse;
+    use entity::Entity;
+    use item::{Item, ItemRarity, ItemType};
+
+    fn item(name: &str) -> Item {
+        Item {
+            name: name.to_owned(),
+            item_type: ItemType::Usable,
+            influence: None,
+            stack_size: 1,
+            rarity: ItemRarity::Common,
+        }
+    }
+
+    #[test]
+    fn open_and_close() {
+        let mut door = Entity::new("Door");
+        door.append_behaviour(Door::new());
+
+        assert_eq!(door.send_event(Event::Close), Event::Nothing);
+        assert_eq!(door.send_event(Event::Open), Event::Open);
+        assert_eq!(door.send_event(Event::Open), Event::Nothing);
+        assert_eq!(door.send_event(Event::Close), Event::Close);
+        assert!(!Door::is_open(door.state()));
+    }
+
+    #[test]
+    fn unlock_with_key() {
+        let mut door = Entity::new("Door");
+        door.append_behaviour(Door::locked("Iron key"));
+        door.append_behaviour(DefaultResponse::new("The door is locked."));
+
+        assert_eq!(door.send_event(Event::Open), Event::Tell("The door is locked.".to_owned()));
+        assert_eq!(door.send_event(Event::Give(item("Stick"))),
+                   Event::Tell("The door is locked.".to_owned()));
+        assert_eq!(door.send_event(Event::Give(item("Iron key"))),
+                   Event::Give(item("Iron key")));
+        assert_eq!(door.send_event(Event::Open), Event::Open);
+    }
+}
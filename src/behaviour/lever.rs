@@ -0,0 +1,84 @@
+use entity::EntityState;
+use event::{Event, EventKind};
+use super::{Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour};
+
+/// A lever switching another entity, like a gate, on and off
+///
+/// Pushing or pulling the lever toggles the flag `on` of the entity and sends `Event::Open` or
+/// `Event::Close` to the linked entities, which are delivered by `World2d::interact`. Responds
+/// with the event that was sent.
+#[derive(Clone)]
+pub struct Lever {
+    target: String,
+}
+
+impl_serialize_struct!(Lever { target });
+
+impl Lever {
+    /// Creates a new instance of `Lever` linked to the entities with the given name
+    pub fn new(target: &str) -> Lever {
+        Lever { target: target.to_owned() }
+    }
+
+    /// Returns `true` if the lever is switched on
+    pub fn is_on(state: &EntityState) -> bool {
+        state.get_bool("on").unwrap_or(false)
+    }
+}
+
+impl Behaviour for Lever {
+    fn handle_event(&self, _: &Event, state: &mut EntityState) -> BehaviourResult {
+        let event = if state.toggle("on") {
+            Event::Open
+        } else {
+            Event::Close
+        };
+
+        state.send(&self.target, event.clone());
+        BehaviourResult::Handled(event)
+    }
+
+    fn handles(&self, kind: EventKind) -> bool {
+        kind == EventKind::Push || kind == EventKind::Pull
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        SavedBehaviour::new(self)
+    }
+}
+
+impl SaveableBehaviour for Lever {
+    const TAG: &'static str = "lever";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use behaviour::Door;
+    use entity::Entity;
+    use world::two_dimensional::{Field, FieldType, World2d};
+
+    #[test]
+    fn toggle_linked_entity() {
+        let mut lever = Entity::new("Lever");
+        lever.append_behaviour(Lever::new("Gate"));
+
+        let mut gate = Entity::new("Gate");
+        gate.append_behaviour(Door::new());
+
+        let mut world = World2d::new("Castle", (3, 1));
+        world.set_field(Field::new(FieldType::Stone).entity(lever), (1, 0));
+        world.set_field(Field::new(FieldType::Stone).entity(gate), (2, 0));
+
+        let is_gate_open = |world: &World2d| {
+            let gate = world.get_field((2, 0)).unwrap().entity.as_ref().unwrap();
+            Door::is_open(gate.state())
+        };
+
+        assert_eq!(world.interact((1, 0), Event::Pull), Some(Event::Open));
+        assert!(is_gate_open(&world));
+        assert_eq!(world.interact((1, 0), Event::Pull), Some(Event::Close));
+        assert!(!is_gate_open(&world));
+        assert_eq!(world.interact((1, 0), Event::Open), Some(Event::Nothing));
+    }
+}
@@ -0,0 +1,153 @@
+use entity::EntityState;
+use event::{Event, EventKind};
+use item::Item;
+use types::Gold;
+use super::{Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour};
+
+/// An item a merchant sells, together with its price
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ware {
+    /// The item that is sold
+    pub item: Item,
+    /// The price of the item in gold
+    pub price: Gold,
+}
+
+impl_serialize_struct!(Ware { item, price });
+
+/// A merchant buying and selling wares
+///
+/// The trade works with a credit stored in the entity under `credit`:
+///
+/// * Telling the merchant the name of a ware buys it. If the credit covers the price, the price
+///   is deducted and the merchant responds with an `Event::Give` of the ware. Otherwise the
+///   event falls through to the next behaviour.
+/// * Telling the merchant anything else responds with an `Event::Options` of the names of all
+///   wares as `Event::Tell`.
+/// * Giving the merchant an item it sells adds half of its price to the credit. Other items are
+///   handed back.
+///
+/// The game moves the gold of the player into the credit with `Merchant::deposit`.
+#[derive(Clone)]
+pub struct Merchant {
+    wares: Vec<Ware>,
+}
+
+impl_serialize_struct!(Merchant { wares });
+
+impl Merchant {
+    /// Creates a new instance of `Merchant` selling the given wares
+    pub fn new(wares: Vec<Ware>) -> Merchant {
+        Merchant { wares }
+    }
+
+    /// Returns the credit of the player at the merchant
+    pub fn credit(state: &EntityState) -> Gold {
+        state.get_int("credit").unwrap_or(0) as Gold
+    }
+
+    /// Adds the given amount of gold to the credit of the player
+    pub fn deposit(state: &mut EntityState, gold: Gold) {
+        let credit = Merchant::credit(state) + gold;
+        state.set("credit", credit as i64);
+    }
+
+    fn ware(&self, name: &str) -> Option<&Ware> {
+        self.wares.iter().find(|ware| ware.item.name == name)
+    }
+}
+
+impl Behaviour for Merchant {
+    fn handle_event(&self, event: &Event, state: &mut EntityState) -> BehaviourResult {
+        match *event {
+            Event::Tell(ref name) => {
+                match self.ware(name) {
+                    Some(ware) if Merchant::credit(state) >= ware.price => {
+                        let credit = Merchant::credit(state) - ware.price;
+                        state.set("credit", credit as i64);
+                        BehaviourResult::Handled(Event::Give(ware.item.clone()))
+                    }
+                    Some(_) => BehaviourResult::Pass,
+                    None => {
+                        let wares = self.wares
+                            .iter()
+                            .map(|ware| Event::Tell(ware.item.name.clone()))
+                            .collect();
+                        BehaviourResult::Handled(Event::Options(wares))
+                    }
+                }
+            }
+            Event::Give(ref item) => {
+                match self.ware(&item.name) {
+                    Some(ware) => {
+                        Merchant::deposit(state, ware.price / 2);
+                        BehaviourResult::Handled(Event::Nothing)
+                    }
+                    None => BehaviourResult::Handled(Event::Give(item.clone())),
+                }
+            }
+            _ => BehaviourResult::Pass,
+        }
+    }
+
+    fn handles(&self, kind: EventKind) -> bool {
+        kind == EventKind::Tell || kind == EventKind::Give
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        SavedBehaviour::new(self)
+    }
+}
+
+impl SaveableBehaviour for Merchant {
+    const TAG: &'static str = "merchant";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use behaviour::DefaultResponse;
+    use entity::Entity;
+    use item::{ItemRarity, ItemType};
+
+    fn item(name: &str) -> Item {
+        Item {
+            name: name.to_owned(),
+            item_type: ItemType::ConsumablePotion,
+            influence: None,
+            stack_size: 5,
+            rarity: ItemRarity::Common,
+        }
+    }
+
+    fn tell(text: &str) -> Event {
+        Event::Tell(text.to_owned())
+    }
+
+    #[test]
+    fn trade() {
+        let mut merchant = Entity::new("Merchant");
+        merchant.append_behaviour(Merchant::new(vec![Ware {
+                                                         item: item("Potion"),
+                                                         price: 10,
+                                                     },
+                                                     Ware {
+                                                         item: item("Elixir"),
+                                                         price: 50,
+                                                     }]));
+        merchant.append_behaviour(DefaultResponse::new("You can't afford that."));
+
+        assert_eq!(merchant.send_event(tell("Hello")),
+                   Event::Options(vec![tell("Potion"), tell("Elixir")]));
+        assert_eq!(merchant.send_event(tell("Potion")), tell("You can't afford that."));
+
+        Merchant::deposit(merchant.state_mut(), 12);
+
+        assert_eq!(merchant.send_event(tell("Potion")), Event::Give(item("Potion")));
+        assert_eq!(Merchant::credit(merchant.state()), 2);
+
+        assert_eq!(merchant.send_event(Event::Give(item("Elixir"))), Event::Nothing);
+        assert_eq!(merchant.send_event(Event::Give(item("Rock"))), Event::Give(item("Rock")));
+        assert_eq!(Merchant::credit(merchant.state()), 27);
+    }
+}
@@ -1,5 +1,26 @@
 pub use self::behaviour::*;
+pub use self::behaviour_tree::*;
+pub use self::container::*;
 pub use self::default_response::*;
+pub use self::dialogue::*;
+pub use self::dialogue_script::*;
+pub use self::door::*;
+pub use self::lever::*;
+pub use self::merchant::*;
+pub use self::pushable::*;
+pub use self::registry::*;
+pub use self::state_machine::*;
 
+#[allow(clippy::module_inception)]
 mod behaviour;
+mod behaviour_tree;
+mod container;
 mod default_response;
+mod dialogue;
+mod dialogue_script;
+mod door;
+mod lever;
+mod merchant;
+mod pushable;
+mod registry;
+mod state_machine;
@@ -0,0 +1,84 @@
+use entity::EntityState;
+use event::{Event, EventKind};
+use super::{Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour};
+
+/// An object like a crate or a boulder that can be moved around
+///
+/// Responds to `Event::Push` and `Event::Pull` with the same event, which `World2d::interact`
+/// turns into moving the entity one field away from or towards the player.
+#[derive(Clone)]
+pub struct Pushable {
+    pullable: bool,
+}
+
+impl_serialize_struct!(Pushable { pullable });
+
+impl Pushable {
+    /// Creates a new instance of `Pushable` that can be pushed and pulled
+    pub fn new() -> Pushable {
+        Pushable { pullable: true }
+    }
+
+    /// Creates a new instance of `Pushable` that can only be pushed
+    pub fn push_only() -> Pushable {
+        Pushable { pullable: false }
+    }
+}
+
+impl Default for Pushable {
+    fn default() -> Pushable {
+        Pushable::new()
+    }
+}
+
+impl Behaviour for Pushable {
+    fn handle_event(&self, event: &Event, _: &mut EntityState) -> BehaviourResult {
+        match *event {
+            Event::Push => BehaviourResult::Handled(Event::Push),
+            Event::Pull if self.pullable => BehaviourResult::Handled(Event::Pull),
+            _ => BehaviourResult::Pass,
+        }
+    }
+
+    fn handles(&self, kind: EventKind) -> bool {
+        kind == EventKind::Push || kind == EventKind::Pull
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        SavedBehaviour::new(self)
+    }
+}
+
+impl SaveableBehaviour for Pushable {
+    const TAG: &'static str = "pushable";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::Entity;
+    use world::World;
+    use world::two_dimensional::{Field, FieldType, World2d};
+
+    #[test]
+    fn push_across_world() {
+        let mut boulder = Entity::new("Boulder");
+        boulder.append_behaviour(Pushable::push_only());
+
+        let mut world = World2d::new("Cave", (5, 1));
+        world.set_field(Field::new(FieldType::Stone).entity(boulder), (1, 0));
+        world.set_field(Field::new(FieldType::StoneWall).height(3), (4, 0));
+
+        assert_eq!(world.interact((1, 0), Event::Pull), Some(Event::Nothing));
+        assert_eq!(world.interact((1, 0), Event::Push), Some(Event::Push));
+        world.move_to((1, 0));
+        assert_eq!(world.interact((2, 0), Event::Push), Some(Event::Push));
+        world.move_to((1, 0));
+
+        // The wall is higher than the field the boulder is on
+        assert_eq!(world.interact((3, 0), Event::Push), Some(Event::Nothing));
+
+        assert!(world.get_field((3, 0)).unwrap().entity.is_some());
+        assert!(world.get_field((1, 0)).unwrap().entity.is_none());
+    }
+}
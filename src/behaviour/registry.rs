@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+
+use rustc_serialize::json;
+use rustc_serialize::{Decodable, Encodable};
+
+use super::{Behaviour, BehaviourTree, Container, DefaultResponse, Dialogue, Door, Lever, Merchant,
+            Pushable, StateMachineBehaviour};
+
+/// A behaviour that can be saved together with its entity and restored by a
+/// `BehaviourRegistry`
+pub trait SaveableBehaviour: Behaviour + Encodable + Decodable + 'static {
+    /// The unique tag the behaviour is registered with
+    const TAG: &'static str;
+}
+
+/// A saved behaviour. Contains the tag of the behaviour and its data encoded as JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedBehaviour {
+    /// The tag of the behaviour
+    pub tag: String,
+    /// The data of the behaviour
+    pub data: String,
+}
+
+impl_serialize_struct!(SavedBehaviour { tag, data });
+
+impl SavedBehaviour {
+    /// Saves the given behaviour. Returns `None` if it can't be encoded as JSON.
+    pub fn new<T: SaveableBehaviour>(behaviour: &T) -> Option<SavedBehaviour> {
+        json::encode(behaviour).ok().map(|data| {
+            SavedBehaviour {
+                tag: T::TAG.to_owned(),
+                data,
+            }
+        })
+    }
+}
+
+/// An error while restoring a saved behaviour
+#[derive(Debug)]
+pub enum BehaviourError {
+    /// No behaviour was registered with the given tag
+    UnknownTag(String),
+    /// The data of the behaviour is invalid
+    Decode(json::DecoderError),
+}
+
+impl fmt::Display for BehaviourError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BehaviourError::UnknownTag(ref tag) => write!(f, "unknown behaviour {:?}", tag),
+            BehaviourError::Decode(ref error) => write!(f, "invalid behaviour: {}", error),
+        }
+    }
+}
+
+impl Error for BehaviourError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            BehaviourError::Decode(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+type Loader = fn(&str) -> Result<Box<dyn Behaviour>, json::DecoderError>;
+
+fn load_with(loader: Option<Loader>,
+             saved: &SavedBehaviour)
+             -> Result<Box<dyn Behaviour>, BehaviourError> {
+    match loader {
+        Some(loader) => loader(&saved.data).map_err(BehaviourError::Decode),
+        None => Err(BehaviourError::UnknownTag(saved.tag.clone())),
+    }
+}
+
+fn load<T: SaveableBehaviour>(data: &str) -> Result<Box<dyn Behaviour>, json::DecoderError> {
+    let behaviour: T = json::decode(data)?;
+    Ok(Box::new(behaviour))
+}
+
+/// A map from the tags of behaviours to the functions restoring them
+#[derive(Clone)]
+pub struct BehaviourRegistry {
+    loaders: HashMap<String, Loader>,
+}
+
+impl BehaviourRegistry {
+    /// Creates a new instance of `BehaviourRegistry` without any behaviours
+    pub fn new() -> BehaviourRegistry {
+        BehaviourRegistry { loaders: HashMap::new() }
+    }
+
+    /// Registers the given behaviour, replacing a previous one with the same tag
+    pub fn register<T: SaveableBehaviour>(&mut self) {
+        self.loaders.insert(T::TAG.to_owned(), load::<T>);
+    }
+
+    /// Returns `true` if a behaviour is registered with the given tag
+    pub fn contains(&self, tag: &str) -> bool {
+        self.loaders.contains_key(tag)
+    }
+
+    /// Restores a saved behaviour
+    pub fn load(&self, saved: &SavedBehaviour) -> Result<Box<dyn Behaviour>, BehaviourError> {
+        load_with(self.loaders.get(&saved.tag).cloned(), saved)
+    }
+}
+
+impl Default for BehaviourRegistry {
+    /// Registers all behaviours of this crate
+    fn default() -> BehaviourRegistry {
+        let mut registry = BehaviourRegistry::new();
+        registry.register::<BehaviourTree>();
+        registry.register::<Container>();
+        registry.register::<DefaultResponse>();
+        registry.register::<Dialogue>();
+        registry.register::<Door>();
+        registry.register::<Lever>();
+        registry.register::<Merchant>();
+        registry.register::<Pushable>();
+        registry.register::<StateMachineBehaviour>();
+        registry
+    }
+}
+
+impl fmt::Debug for BehaviourRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut tags: Vec<&String> = self.loaders.keys().collect();
+        tags.sort();
+        f.debug_struct("BehaviourRegistry").field("tags", &tags).finish()
+    }
+}
+
+// Decoding an entity can't be passed a registry, so the registry used for it is global
+static REGISTRY: Mutex<Option<BehaviourRegistry>> = Mutex::new(None);
+
+fn with_registry<T, F: FnOnce(&mut BehaviourRegistry) -> T>(f: F) -> T {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|error| error.into_inner());
+    f(registry.get_or_insert_with(BehaviourRegistry::default))
+}
+
+/// Registers the given behaviour in the registry used to load entities. The behaviours of this
+/// crate are registered by default.
+pub fn register_behaviour<T: SaveableBehaviour>() {
+    with_registry(|registry| registry.register::<T>());
+}
+
+/// Restores a saved behaviour using the registry used to load entities
+pub fn load_behaviour(saved: &SavedBehaviour) -> Result<Box<dyn Behaviour>, BehaviourError> {
+    // The registry is unlocked while loading, behaviours may contain other behaviours
+    let loader = with_registry(|registry| registry.loaders.get(&saved.tag).cloned());
+    load_with(loader, saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use behaviour::BehaviourResult;
+    use entity::EntityState;
+    use event::Event;
+
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Behaviour for Echo {
+        fn handle_event(&self, event: &Event, _: &mut EntityState) -> BehaviourResult {
+            BehaviourResult::Transform(event.clone())
+        }
+    }
+
+    impl Encodable for Echo {
+        fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+            e.emit_nil()
+        }
+    }
+
+    impl Decodable for Echo {
+        fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<Echo, D::Error> {
+            d.read_nil().map(|_| Echo)
+        }
+    }
+
+    impl SaveableBehaviour for Echo {
+        const TAG: &'static str = "echo";
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = BehaviourRegistry::default();
+        let saved = SavedBehaviour::new(&Echo).unwrap();
+
+        assert!(registry.contains(DefaultResponse::TAG));
+        match registry.load(&saved) {
+            Err(BehaviourError::UnknownTag(tag)) => assert_eq!(tag, "echo"),
+            _ => panic!("an unknown behaviour was loaded"),
+        }
+
+        registry.register::<Echo>();
+        let behaviour = registry.load(&saved).unwrap();
+
+        assert_eq!(behaviour.handle_event(&Event::Push, &mut EntityState::new()),
+                   BehaviourResult::Transform(Event::Push));
+    }
+}
@@ -0,0 +1,327 @@
+use std::rc::Rc;
+
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use entity::EntityState;
+use event::{Event, EventKind};
+use super::{load_behaviour, Behaviour, BehaviourResult, SaveableBehaviour, SavedBehaviour};
+
+/// The name of the state matching every state as the source of a transition
+pub const ANY_STATE: &str = "*";
+
+/// A condition of a transition, checked with the event and the state of the entity
+pub type Condition = Rc<dyn Fn(&Event, &EntityState) -> bool>;
+
+/// What triggers a transition of a `StateMachineBehaviour`
+#[derive(Clone)]
+pub enum Trigger {
+    /// An event of the given kind
+    Event(EventKind),
+    /// Any event for which the condition holds
+    Condition(Condition),
+}
+
+impl Trigger {
+    fn is_triggered(&self, event: &Event, state: &EntityState) -> bool {
+        match *self {
+            Trigger::Event(kind) => event.kind() == kind,
+            Trigger::Condition(ref condition) => condition(event, state),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Transition {
+    from: String,
+    to: String,
+    trigger: Trigger,
+}
+
+/// A finite state machine, like an NPC switching between being idle, alert and hostile
+///
+/// Every state has a behaviour handling the events while the machine is in it. When an event
+/// arrives, the first transition from the current state that is triggered by it is taken, then
+/// the event is handled by the behaviour of the new state. States without a behaviour pass
+/// every event on. The current state is stored in the entity under the key of the machine.
+///
+/// Only machines without conditions and with saveable behaviours can be saved.
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::behaviour::{DefaultResponse, StateMachineBehaviour};
+/// # use rpg::entity::Entity;
+/// # use rpg::event::{Event, EventKind};
+/// let guard = StateMachineBehaviour::new("idle")
+///     .state("idle", DefaultResponse::new("Move along."))
+///     .state("hostile", DefaultResponse::new("Halt!"))
+///     .transition("idle", "hostile", EventKind::Push);
+///
+/// let mut entity = Entity::new("Guard");
+/// entity.append_behaviour(guard);
+///
+/// assert_eq!(entity.send_event(Event::Nothing), Event::Tell("Move along.".to_owned()));
+/// assert_eq!(entity.send_event(Event::Push), Event::Tell("Halt!".to_owned()));
+/// assert_eq!(entity.send_event(Event::Nothing), Event::Tell("Halt!".to_owned()));
+/// ```
+#[derive(Clone)]
+pub struct StateMachineBehaviour {
+    key: String,
+    initial: String,
+    states: Vec<(String, Box<dyn Behaviour>)>,
+    transitions: Vec<Transition>,
+}
+
+impl StateMachineBehaviour {
+    /// Creates a new instance of `StateMachineBehaviour` starting in the given state
+    pub fn new(initial: &str) -> StateMachineBehaviour {
+        StateMachineBehaviour {
+            key: "state_machine".to_owned(),
+            initial: initial.to_owned(),
+            states: Vec::new(),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// A builder method for setting the key the current state is stored under. Needed if an
+    /// entity has more than one machine.
+    pub fn key(mut self, key: &str) -> StateMachineBehaviour {
+        self.key = key.to_owned();
+        self
+    }
+
+    /// A builder method for setting the behaviour of a state
+    pub fn state<T: Behaviour + 'static>(mut self,
+                                         name: &str,
+                                         behaviour: T)
+                                         -> StateMachineBehaviour {
+        self.states.retain(|(state, _)| state != name);
+        self.states.push((name.to_owned(), Box::new(behaviour)));
+        self
+    }
+
+    /// A builder method for adding a transition taken on events of the given kind. `from` can
+    /// be `ANY_STATE`.
+    pub fn transition(self, from: &str, to: &str, kind: EventKind) -> StateMachineBehaviour {
+        self.add_transition(from, to, Trigger::Event(kind))
+    }
+
+    /// A builder method for adding a transition taken when the condition holds. `from` can be
+    /// `ANY_STATE`.
+    pub fn transition_if<F>(self, from: &str, to: &str, condition: F) -> StateMachineBehaviour
+        where F: Fn(&Event, &EntityState) -> bool + 'static
+    {
+        self.add_transition(from, to, Trigger::Condition(Rc::new(condition)))
+    }
+
+    /// Returns the current state of the machine
+    pub fn current(&self, state: &EntityState) -> String {
+        state.get_text(&self.key).unwrap_or(&self.initial).to_owned()
+    }
+
+    fn add_transition(mut self, from: &str, to: &str, trigger: Trigger) -> StateMachineBehaviour {
+        self.transitions.push(Transition {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            trigger,
+        });
+        self
+    }
+}
+
+impl Behaviour for StateMachineBehaviour {
+    fn handle_event(&self, event: &Event, state: &mut EntityState) -> BehaviourResult {
+        let mut current = self.current(state);
+
+        let next = self.transitions.iter().find(|transition| {
+            (transition.from == current || transition.from == ANY_STATE) &&
+            transition.trigger.is_triggered(event, state)
+        });
+        if let Some(transition) = next {
+            current = transition.to.clone();
+            state.set(&self.key, current.as_str());
+        }
+
+        match self.states.iter().find(|(name, _)| *name == current) {
+            Some((_, behaviour)) if behaviour.handles(event.kind()) => {
+                behaviour.handle_event(event, state)
+            }
+            _ => BehaviourResult::Pass,
+        }
+    }
+
+    fn save(&self) -> Option<SavedBehaviour> {
+        let saveable = self.transitions.iter().all(|transition| match transition.trigger {
+            Trigger::Event(_) => true,
+            Trigger::Condition(_) => false,
+        }) && self.states.iter().all(|(_, behaviour)| behaviour.save().is_some());
+
+        if saveable {
+            SavedBehaviour::new(self)
+        } else {
+            None
+        }
+    }
+}
+
+impl SaveableBehaviour for StateMachineBehaviour {
+    const TAG: &'static str = "state_machine";
+}
+
+struct SavedState {
+    name: String,
+    behaviour: SavedBehaviour,
+}
+
+impl_serialize_struct!(SavedState { name, behaviour });
+
+struct SavedTransition {
+    from: String,
+    to: String,
+    kind: EventKind,
+}
+
+impl_serialize_struct!(SavedTransition { from, to, kind });
+
+/// Conditions and states with behaviours that can't be saved are skipped
+impl Encodable for StateMachineBehaviour {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        let states: Vec<SavedState> = self.states
+            .iter()
+            .filter_map(|(name, behaviour)| {
+                behaviour.save().map(|behaviour| {
+                    SavedState {
+                        name: name.clone(),
+                        behaviour,
+                    }
+                })
+            })
+            .collect();
+        let transitions: Vec<SavedTransition> = self.transitions
+            .iter()
+            .filter_map(|transition| match transition.trigger {
+                Trigger::Event(kind) => {
+                    Some(SavedTransition {
+                        from: transition.from.clone(),
+                        to: transition.to.clone(),
+                        kind,
+                    })
+                }
+                Trigger::Condition(_) => None,
+            })
+            .collect();
+
+        e.emit_struct("StateMachineBehaviour", 4, |e| {
+            e.emit_struct_field("key", 0, |e| self.key.encode(e))?;
+            e.emit_struct_field("initial", 1, |e| self.initial.encode(e))?;
+            e.emit_struct_field("states", 2, |e| states.encode(e))?;
+            e.emit_struct_field("transitions", 3, |e| transitions.encode(e))
+        })
+    }
+}
+
+impl Decodable for StateMachineBehaviour {
+    fn decode<D: Decoder>(d: &mut D) -> Result<StateMachineBehaviour, D::Error> {
+        d.read_struct("StateMachineBehaviour", 4, |d| {
+            let key: String = d.read_struct_field("key", 0, Decodable::decode)?;
+            let initial: String = d.read_struct_field("initial", 1, Decodable::decode)?;
+            let states: Vec<SavedState> = d.read_struct_field("states", 2, Decodable::decode)?;
+            let transitions: Vec<SavedTransition> =
+                d.read_struct_field("transitions", 3, Decodable::decode)?;
+
+            let mut machine = StateMachineBehaviour::new(&initial).key(&key);
+            for state in states {
+                let behaviour = load_behaviour(&state.behaviour)
+                    .map_err(|error| d.error(&error.to_string()))?;
+                machine.states.push((state.name, behaviour));
+            }
+
+            for transition in transitions {
+                machine = machine.transition(&transition.from, &transition.to, transition.kind);
+            }
+
+            Ok(machine)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use behaviour::{DefaultResponse, Merchant, Ware};
+    use entity::Entity;
+    use item::{Item, ItemRarity, ItemType};
+    use rustc_serialize::json;
+
+    fn tell(text: &str) -> Event {
+        Event::Tell(text.to_owned())
+    }
+
+    fn shopkeeper() -> StateMachineBehaviour {
+        let potion = Item {
+            name: "Potion".to_owned(),
+            item_type: ItemType::ConsumablePotion,
+            influence: None,
+            stack_size: 5,
+            rarity: ItemRarity::Common,
+        };
+
+        StateMachineBehaviour::new("idle")
+            .state("idle", DefaultResponse::new("Welcome!"))
+            .state("talking",
+                   Merchant::new(vec![Ware {
+                                          item: potion,
+                                          price: 10,
+                                      }]))
+            .state("hostile", DefaultResponse::new("Get out!"))
+            .transition("idle", "talking", EventKind::Tell)
+            .transition("talking", "idle", EventKind::Close)
+            .transition(ANY_STATE, "hostile", EventKind::Push)
+    }
+
+    #[test]
+    fn transitions() {
+        let mut shop = Entity::new("Shopkeeper");
+        shop.append_behaviour(shopkeeper());
+
+        assert_eq!(shop.send_event(Event::Nothing), tell("Welcome!"));
+        assert_eq!(shop.send_event(tell("Hi")), Event::Options(vec![tell("Potion")]));
+        assert_eq!(shop.state().get_text("state_machine"), Some("talking"));
+
+        assert_eq!(shop.send_event(Event::Close), tell("Welcome!"));
+        assert_eq!(shop.state().get_text("state_machine"), Some("idle"));
+        assert_eq!(shop.send_event(Event::Push), tell("Get out!"));
+        assert_eq!(shop.send_event(tell("Sorry")), tell("Get out!"));
+    }
+
+    #[test]
+    fn conditions() {
+        let guard = StateMachineBehaviour::new("idle")
+            .key("mood")
+            .state("alert", DefaultResponse::new("Who's there?"))
+            .transition_if("idle", "alert", |_, state| state.get_int("noise").unwrap_or(0) > 2);
+
+        let mut entity = Entity::new("Guard");
+        entity.append_behaviour(guard.clone());
+
+        assert_eq!(entity.send_event(Event::Nothing), Event::Nothing);
+
+        entity.state_mut().set("noise", 3);
+
+        assert_eq!(entity.send_event(Event::Nothing), tell("Who's there?"));
+        assert_eq!(guard.current(entity.state()), "alert");
+        assert!(guard.save().is_none());
+    }
+
+    #[test]
+    fn save_machine() {
+        let mut shop = Entity::new("Shopkeeper");
+        shop.append_behaviour(shopkeeper());
+        shop.send_event(tell("Hi"));
+
+        let mut loaded: Entity = json::decode(&json::encode(&shop).unwrap()).unwrap();
+
+        assert_eq!(loaded.send_event(tell("Potion")), Event::Nothing);
+        assert_eq!(loaded.send_event(Event::Push), tell("Get out!"));
+    }
+}
@@ -1,12 +1,23 @@
-use item::{Item, ItemType, ItemInfluence};
+use item::{Item, ItemType};
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 use inventory::Inventory;
-use types::{Health, AttributeValue};
+use progression::{AllocationError, LevelUp, Progression};
+use resource::{Ability, ResourcePool};
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+use stats::{Effect, Modifier, ModifierSource, StatBreakdown};
+use types::{Health, AttributeValue, Experience, ResourceAmount};
 
 /// The influence the `Attribute::Dexterity` has on the attack_damage of the character
 const DEXTERITY_INFLUENCE: f64 = 0.2;
 
+/// A function called when the character dies or is revived
+pub type VitalityHook = Rc<dyn Fn(&mut Character)>;
+
 /// The character the player is impersonating
+///
+/// The hooks of a character are not saved and have to be registered again after loading.
 pub struct Character {
     name: String,
     health: Health,
@@ -18,13 +29,24 @@ pub struct Character {
     weapon_slot_left: Option<Item>,
     weapon_slot_right: Option<Item>,
     inventory: Inventory,
+    regeneration: Health,
+    resources: Vec<ResourcePool>,
+    experience: Experience,
+    level: u32,
+    attribute_points: u32,
+    progression: Progression,
+    effects: Vec<Effect>,
+    death_hooks: Vec<VitalityHook>,
+    revive_hooks: Vec<VitalityHook>,
 }
 
 impl Character {
     /// Constructs a new `Character`.
     ///
     /// By default, the character has an attribute set given by `Character::default_attributes()`.
-    /// The characters default inventory size is `30`slots.
+    /// The characters default inventory size is `30`slots. It starts with full
+    /// `ResourcePool::mana()` and `ResourcePool::stamina()` at level 1 of the default
+    /// `Progression`.
     ///
     /// # Examples
     ///
@@ -34,7 +56,7 @@ impl Character {
     /// ```
     pub fn new(name: &str) -> Character {
         let attribute_map = Self::default_attributes();
-        Character {
+        let mut character = Character {
             name: name.to_owned(),
             health: (&attribute_map)[&Attribute::Constitution] as Health,
             attributes: attribute_map,
@@ -45,58 +67,487 @@ impl Character {
             weapon_slot_left: None,
             weapon_slot_right: None,
             inventory: Inventory::new(30),
+            regeneration: 0,
+            resources: Vec::new(),
+            experience: 0,
+            level: 1,
+            attribute_points: 0,
+            progression: Progression::default(),
+            effects: Vec::new(),
+            death_hooks: Vec::new(),
+            revive_hooks: Vec::new(),
+        };
+
+        character.add_resource(ResourcePool::mana());
+        character.add_resource(ResourcePool::stamina());
+        character
+    }
+
+    /// Returns the name of the character
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the current health of the character. It never exceeds the maximum health.
+    pub fn health(&self) -> Health {
+        self.health
+    }
+
+    /// Returns the maximum health of the character, its effective `Attribute::Constitution`. The
+    /// health is lowered when the maximum drops below it, so a character dies if it drops to `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rpg::character::{Attribute, Character};
+    /// # use rpg::item_generator::ItemGenerator;
+    /// # use rpg::item::{ItemInfluence, ItemType};
+    /// let mut character = Character::new("Michael");
+    /// let helmet = ItemGenerator::new()
+    ///     .item_type(ItemType::ArmorHead)
+    ///     .influence(Some(ItemInfluence::new(Attribute::Constitution, 5)))
+    ///     .gen();
+    ///
+    /// character.set_armor_slot_head(Some(helmet));
+    ///
+    /// assert_eq!(character.max_health(), 35);
+    /// ```
+    pub fn max_health(&self) -> Health {
+        self.get_attribute_value(&Attribute::Constitution).max(0) as Health
+    }
+
+    /// Returns `true` if the character has health left
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+
+    /// Reduces the health of the character by the given damage, but not below zero. Returns the
+    /// damage that was actually taken. Runs the death hooks if the character dies.
+    pub fn take_damage(&mut self, damage: Health) -> Health {
+        if !self.is_alive() {
+            return 0;
+        }
+
+        let taken = damage.min(self.health);
+        self.health -= taken;
+
+        if !self.is_alive() {
+            self.run_hooks(self.death_hooks.clone());
+        }
+
+        taken
+    }
+
+    /// Increases the health of the character by the given amount, but not above the maximum
+    /// health. Returns the amount that was actually healed. Dead characters can't be healed,
+    /// they have to be revived.
+    pub fn heal(&mut self, amount: Health) -> Health {
+        if !self.is_alive() {
+            return 0;
+        }
+
+        let health = self.health.saturating_add(amount).min(self.max_health()).max(self.health);
+        let healed = health - self.health;
+        self.health = health;
+        healed
+    }
+
+    /// Brings a dead character back to life with the given health, at least `1` and at most the
+    /// maximum health. Runs the revive hooks and returns `true` if the character was dead.
+    /// Characters with a maximum health of `0` can't be revived.
+    pub fn revive(&mut self, health: Health) -> bool {
+        if self.is_alive() || self.max_health() == 0 {
+            return false;
+        }
+
+        self.health = health.min(self.max_health()).max(1);
+        self.run_hooks(self.revive_hooks.clone());
+        true
+    }
+
+    /// Registers a function called whenever the character dies
+    pub fn on_death<F: Fn(&mut Character) + 'static>(&mut self, hook: F) {
+        self.death_hooks.push(Rc::new(hook));
+    }
+
+    /// Registers a function called whenever the character is revived
+    pub fn on_revive<F: Fn(&mut Character) + 'static>(&mut self, hook: F) {
+        self.revive_hooks.push(Rc::new(hook));
+    }
+
+    /// Returns the health the character regenerates per tick
+    pub fn regeneration(&self) -> Health {
+        self.regeneration
+    }
+
+    /// Sets the health the character regenerates per tick. `0` turns regeneration off, which is
+    /// the default.
+    pub fn set_regeneration(&mut self, regeneration: Health) {
+        self.regeneration = regeneration;
+    }
+
+    /// Heals the character by its regeneration, regenerates its resources and returns the
+    /// health that was healed. Meant to be called once per tick of the engine, like in an update
+    /// function or a system. Dead characters don't regenerate.
+    pub fn regenerate(&mut self) -> Health {
+        if !self.is_alive() {
+            return 0;
         }
+
+        let attributes = self.effective_attributes();
+        for pool in &mut self.resources {
+            pool.regenerate(attribute_values(&attributes));
+        }
+
+        let regeneration = self.regeneration;
+        self.heal(regeneration)
+    }
+
+    /// Returns the resource pools of the character
+    pub fn resources(&self) -> &[ResourcePool] {
+        &self.resources
+    }
+
+    /// Adds a resource pool to the character and fills it. A pool with the same name is
+    /// replaced.
+    pub fn add_resource(&mut self, mut pool: ResourcePool) {
+        pool.fill(attribute_values(&self.effective_attributes()));
+        self.remove_resource(pool.name());
+        self.resources.push(pool);
+    }
+
+    /// Removes the resource pool with the given name and returns it
+    pub fn remove_resource(&mut self, name: &str) -> Option<ResourcePool> {
+        let index = self.resources.iter().position(|pool| pool.name() == name)?;
+        Some(self.resources.remove(index))
+    }
+
+    /// Returns the current amount of the given resource, or `None` if the character doesn't
+    /// have it
+    pub fn resource(&self, name: &str) -> Option<ResourceAmount> {
+        self.resources.iter().find(|pool| pool.name() == name).map(ResourcePool::current)
+    }
+
+    /// Returns the maximum amount of the given resource, or `None` if the character doesn't
+    /// have it
+    pub fn max_resource(&self, name: &str) -> Option<ResourceAmount> {
+        self.resources
+            .iter()
+            .find(|pool| pool.name() == name)
+            .map(|pool| pool.maximum(attribute_values(&self.effective_attributes())))
     }
 
-    /// Updates the given attribute
+    /// Spends the given amount of a resource. If there is not enough of it, nothing is spent
+    /// and `false` is returned.
+    pub fn spend_resource(&mut self, name: &str, amount: ResourceAmount) -> bool {
+        self.resources
+            .iter_mut()
+            .find(|pool| pool.name() == name)
+            .is_some_and(|pool| pool.spend(amount))
+    }
+
+    /// Restores the given amount of a resource, but not above its maximum. Returns the amount
+    /// that was actually restored.
+    pub fn restore_resource(&mut self, name: &str, amount: ResourceAmount) -> ResourceAmount {
+        let attributes = self.effective_attributes();
+        self.resources
+            .iter_mut()
+            .find(|pool| pool.name() == name)
+            .map_or(0, |pool| pool.restore(amount, attribute_values(&attributes)))
+    }
+
+    /// Returns `true` if the character is alive and has enough resources to use the ability
+    pub fn can_use(&self, ability: &Ability) -> bool {
+        self.is_alive() &&
+        ability.costs.iter().all(|(name, amount)| self.resource(name).is_some_and(|r| r >= *amount))
+    }
+
+    /// Spends the costs of the ability. If the character can't use it, nothing is spent and
+    /// `false` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rpg::character::Character;
+    /// # use rpg::resource::Ability;
+    /// let mut character = Character::new("Michael");
+    /// let fireball = Ability::new("Fireball").cost("mana", 20);
+    ///
+    /// assert!(character.use_ability(&fireball));
+    /// assert!(!character.use_ability(&fireball));
+    /// assert_eq!(character.resource("mana"), Some(5));
+    /// ```
+    pub fn use_ability(&mut self, ability: &Ability) -> bool {
+        if !self.can_use(ability) {
+            return false;
+        }
+
+        for (name, amount) in &ability.costs {
+            self.spend_resource(name, *amount);
+        }
+        true
+    }
+
+    /// Consumes a potion or food with the given name from the inventory and restores the
+    /// resources restored by its type. Returns `false` if there is no such item.
+    pub fn consume(&mut self, name: &str) -> bool {
+        let item_type = match self.inventory.get_item(name) {
+            Some(item) if item.is_consumable() => item.item_type.clone(),
+            _ => return false,
+        };
+
+        self.inventory.remove_item(name);
+        let attributes = self.effective_attributes();
+        for pool in &mut self.resources {
+            let amount = pool.restoration(&item_type);
+            pool.restore(amount, attribute_values(&attributes));
+        }
+        true
+    }
+
+    /// Returns the level of the character, starting at `1`
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// Returns the total experience the character gained
+    pub fn experience(&self) -> Experience {
+        self.experience
+    }
+
+    /// Returns the total experience needed for the next level, or `None` if the character
+    /// reached the highest level
+    pub fn next_level_experience(&self) -> Option<Experience> {
+        self.level.checked_add(1).and_then(|level| self.progression.experience(level))
+    }
+
+    /// Returns the attribute points the character has not allocated yet
+    pub fn attribute_points(&self) -> u32 {
+        self.attribute_points
+    }
+
+    /// Returns the rules for the progression of the character
+    pub fn progression(&self) -> &Progression {
+        &self.progression
+    }
+
+    /// Sets the rules for the progression of the character. The level and experience are kept,
+    /// the new rules apply to the next levels.
+    pub fn set_progression(&mut self, progression: Progression) {
+        self.progression = progression;
+    }
+
+    /// Adds the given experience and levels the character up as often as it reaches the next
+    /// level. Every level grants attribute points and lets attributes grow according to the
+    /// progression. The growth is a modifier of the attributes, see `attribute_breakdown`.
+    /// Returns the levels that were reached, oldest first.
+    pub fn gain_experience(&mut self, experience: Experience) -> Vec<LevelUp> {
+        self.experience = self.experience.saturating_add(experience);
+
+        let mut level_ups = Vec::new();
+        while self.next_level_experience().is_some_and(|needed| self.experience >= needed) {
+            self.level += 1;
+
+            let attribute_points = self.progression.attribute_points();
+            self.attribute_points = self.attribute_points.saturating_add(attribute_points);
+
+            level_ups.push(LevelUp {
+                level: self.level,
+                attribute_points,
+                growth: self.progression.growth_at(self.level),
+            });
+        }
+
+        level_ups
+    }
+
+    /// Spends unspent attribute points on the base value of the given attribute, one point per
+    /// attribute value
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rpg::character::{Attribute, Character};
+    /// # use rpg::progression::AllocationError;
+    /// let mut character = Character::new("Michael");
+    /// character.gain_experience(100);
+    ///
+    /// assert_eq!(character.allocate_points(&Attribute::Luck, 5),
+    ///            Err(AllocationError::NotEnoughPoints(3)));
+    /// assert_eq!(character.allocate_points(&Attribute::Luck, 2), Ok(()));
+    /// assert_eq!(character.get_attribute_value(&Attribute::Luck), 2);
+    /// assert_eq!(character.attribute_points(), 1);
+    /// ```
+    pub fn allocate_points(&mut self,
+                           attribute: &Attribute,
+                           points: u32)
+                           -> Result<(), AllocationError> {
+        if points == 0 {
+            return Err(AllocationError::NoPoints);
+        }
+
+        if points > self.attribute_points {
+            return Err(AllocationError::NotEnoughPoints(self.attribute_points));
+        }
+
+        self.attribute_points -= points;
+        let value = self.base_attribute_value(attribute);
+        self.update_attribute(attribute, value + AttributeValue::from(points));
+        Ok(())
+    }
+
+    /// Returns the inventory of the character
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    /// Returns the inventory of the character mutably
+    pub fn inventory_mut(&mut self) -> &mut Inventory {
+        &mut self.inventory
+    }
+
+    /// Updates the base value of the given attribute
     pub fn update_attribute(&mut self, attribute: &Attribute, value: AttributeValue) {
         *self.attributes.get_mut(attribute).unwrap() = value;
+        self.limit_vitals();
     }
 
-    /// Calculates and returns the current attack damage of the character based on the attibutes
+    /// Calculates and returns the current attack damage of the character: its effective strength
+    /// plus a fifth of its effective dexterity plus the influences of the weapons on any other
+    /// attribute
+    ///
+    /// The effective values include the weapons, so they still add their strength and a fifth of
+    /// their dexterity, but also the armor and the active effects.
     pub fn attack_damage(&self) -> AttributeValue {
-        let base_dexterity = self.attributes
-            .get(&Attribute::Dexterity)
-            .expect("Unable to find attribute: Attribute::Dexterity");
-
-        let base_dexterity = ((*base_dexterity as f64) * DEXTERITY_INFLUENCE) as AttributeValue;
-
-        let base_strength = self.attributes
-            .get(&Attribute::Strength)
-            .expect("Unable to find attribute: Attribute::Strength");
-
-        let mut additional_damage: i64 = 0;
-        if let Some(ref inner_item) = self.weapon_slot_left {
-            if let Some(ItemInfluence { ref attribute, ref amount }) = inner_item.influence {
-                let influence = if attribute == &Attribute::Dexterity {
-                    DEXTERITY_INFLUENCE
-                } else {
-                    1_f64
-                };
-
-                additional_damage += ((*amount as f64) * influence) as i64;
-            }
-        }
+        let dexterity = self.get_attribute_value(&Attribute::Dexterity) as f64;
+        let dexterity = (dexterity * DEXTERITY_INFLUENCE) as AttributeValue;
+
+        let weapons: AttributeValue = [&self.weapon_slot_left, &self.weapon_slot_right]
+            .iter()
+            .filter_map(|slot| slot.as_ref().and_then(|item| item.influence.as_ref()))
+            .filter(|influence| {
+                influence.attribute != Attribute::Strength &&
+                influence.attribute != Attribute::Dexterity
+            })
+            .map(|influence| influence.amount)
+            .sum();
+
+        self.get_attribute_value(&Attribute::Strength) + dexterity + weapons
+    }
 
-        if let Some(ref inner_item) = self.weapon_slot_right {
-            if let Some(ItemInfluence { ref attribute, ref amount }) = inner_item.influence {
-                let influence = if attribute == &Attribute::Dexterity {
-                    DEXTERITY_INFLUENCE
-                } else {
-                    1_f64
-                };
+    /// Returns the armor of the character, the sum of the `Attribute::Defense` influences of the
+    /// equipped armor. They are part of the effective defense as `ModifierSource::Equipment`
+    /// contributions, see `Character::attribute_breakdown`.
+    pub fn armor(&self) -> AttributeValue {
+        let slots = [&self.armor_slot_head,
+                     &self.armor_slot_chest,
+                     &self.armor_slot_legs,
+                     &self.armor_slot_feet];
+
+        slots.iter()
+            .filter_map(|slot| slot.as_ref().and_then(|item| item.influence.as_ref()))
+            .filter(|influence| influence.attribute == Attribute::Defense)
+            .map(|influence| influence.amount)
+            .sum()
+    }
 
-                additional_damage += ((*amount as f64) * influence) as i64;
+    /// Returns the effective value of the specified attribute, including all modifiers
+    pub fn get_attribute_value(&self, attribute: &Attribute) -> AttributeValue {
+        self.attribute_breakdown(attribute).total()
+    }
+
+    /// Returns the value of the specified attribute without any modifiers
+    pub fn base_attribute_value(&self, attribute: &Attribute) -> AttributeValue {
+        *self.attributes.get(attribute).unwrap()
+    }
+
+    /// Returns the effective value of the specified attribute together with where each point
+    /// of it comes from: the influences of the equipped items, the active effects and the
+    /// growth with the level
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rpg::character::{Attribute, Character};
+    /// # use rpg::item::{Item, ItemInfluence, ItemRarity, ItemType};
+    /// # use rpg::stats::Effect;
+    /// let mut character = Character::new("Michael");
+    /// character.set_weapon_slot_right(Some(Item {
+    ///     name: "Sword".to_owned(),
+    ///     item_type: ItemType::WeaponSword,
+    ///     influence: Some(ItemInfluence::new(Attribute::Strength, 10)),
+    ///     stack_size: 1,
+    ///     rarity: ItemRarity::Common,
+    /// }));
+    /// character.add_effect(Effect::new("Rage").percent(Attribute::Strength, 20));
+    ///
+    /// let breakdown = character.attribute_breakdown(&Attribute::Strength);
+    ///
+    /// assert_eq!(breakdown.total(), 36);
+    /// assert_eq!(breakdown.to_string(), "Strength 36 = 20 base + 10 Sword + 20% Rage");
+    /// ```
+    pub fn attribute_breakdown(&self, attribute: &Attribute) -> StatBreakdown {
+        let mut breakdown = StatBreakdown::new(attribute.clone(),
+                                               self.base_attribute_value(attribute));
+
+        for item in self.equipment() {
+            if let Some(ref influence) = item.influence {
+                let source = ModifierSource::Equipment(item.name.clone());
+                let modifier = Modifier::flat(influence.attribute.clone(), influence.amount);
+                breakdown.add(&source, &modifier);
             }
+        }
 
+        for effect in &self.effects {
+            let source = ModifierSource::Effect(effect.name().to_owned());
+            for modifier in effect.modifiers() {
+                breakdown.add(&source, modifier);
+            }
         }
 
-        base_strength + base_dexterity + additional_damage
+        let growth = self.progression.growth_until(attribute, self.level);
+        breakdown.add(&ModifierSource::Level, &Modifier::flat(attribute.clone(), growth));
+
+        breakdown
     }
 
-    /// Returns the value of the specified attribute
-    pub fn get_attribute_value(&self, attribute: &Attribute) -> AttributeValue {
-        *self.attributes.get(attribute).unwrap()
+    /// Returns the active effects of the character
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Adds an effect to the character. An effect with the same name is replaced.
+    pub fn add_effect(&mut self, effect: Effect) {
+        self.effects.retain(|other| other.name() != effect.name());
+        self.effects.push(effect);
+        self.limit_vitals();
+    }
+
+    /// Removes the effect with the given name and returns it
+    pub fn remove_effect(&mut self, name: &str) -> Option<Effect> {
+        let index = self.effects.iter().position(|effect| effect.name() == name)?;
+        let effect = self.effects.remove(index);
+        self.limit_vitals();
+        Some(effect)
+    }
+
+    /// Counts down the durations of the effects and removes the ones that ended, which are
+    /// returned. Meant to be called once per tick of the engine, like `regenerate`.
+    pub fn tick_effects(&mut self) -> Vec<Effect> {
+        let mut ended = Vec::new();
+        let mut index = 0;
+        while index < self.effects.len() {
+            if self.effects[index].tick() {
+                ended.push(self.effects.remove(index));
+            } else {
+                index += 1;
+            }
+        }
+
+        if !ended.is_empty() {
+            self.limit_vitals();
+        }
+        ended
     }
 
     /// A setter method for the head armor slot.
@@ -110,6 +561,7 @@ impl Character {
         }
 
         self.armor_slot_head = item;
+        self.limit_vitals();
     }
 
     /// A setter method for the chest armor slot.
@@ -123,6 +575,7 @@ impl Character {
         }
 
         self.armor_slot_chest = item;
+        self.limit_vitals();
     }
 
     /// A setter method for the legs armor slot.
@@ -137,6 +590,7 @@ impl Character {
         }
 
         self.armor_slot_legs = item;
+        self.limit_vitals();
     }
 
     /// A setter method for the feet armor slot.
@@ -151,16 +605,19 @@ impl Character {
         }
 
         self.armor_slot_feet = item;
+        self.limit_vitals();
     }
 
     /// A setter method for the right weapon slot
     pub fn set_weapon_slot_right(&mut self, item: Option<Item>) {
         self.weapon_slot_right = item;
+        self.limit_vitals();
     }
 
     /// A setter method for the left weapon slot
     pub fn set_weapon_slot_left(&mut self, item: Option<Item>) {
         self.weapon_slot_left = item;
+        self.limit_vitals();
     }
 
     /// Returns the default attributes for a character
@@ -180,10 +637,138 @@ impl Character {
 
         attribute_map
     }
+
+    fn equipment(&self) -> Vec<&Item> {
+        [&self.armor_slot_head,
+         &self.armor_slot_chest,
+         &self.armor_slot_legs,
+         &self.armor_slot_feet,
+         &self.weapon_slot_left,
+         &self.weapon_slot_right]
+            .iter()
+            .filter_map(|slot| slot.as_ref())
+            .collect()
+    }
+
+    /// Returns the effective values of all attributes
+    fn effective_attributes(&self) -> HashMap<Attribute, AttributeValue> {
+        self.attributes
+            .keys()
+            .map(|attribute| (attribute.clone(), self.get_attribute_value(attribute)))
+            .collect()
+    }
+
+    /// Keeps the health of a living character and its resources within their maxima after the
+    /// attributes changed. The character dies if its maximum health dropped to `0`.
+    fn limit_vitals(&mut self) {
+        if self.is_alive() {
+            self.health = self.health.min(self.max_health());
+            if !self.is_alive() {
+                self.run_hooks(self.death_hooks.clone());
+            }
+        }
+
+        let attributes = self.effective_attributes();
+        for pool in &mut self.resources {
+            pool.limit(attribute_values(&attributes));
+        }
+    }
+
+    fn run_hooks(&mut self, hooks: Vec<VitalityHook>) {
+        for hook in hooks {
+            hook(self);
+        }
+    }
+}
+
+/// The hooks are skipped
+impl Encodable for Character {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("Character", 17, |e| {
+            e.emit_struct_field("name", 0, |e| self.name.encode(e))?;
+            e.emit_struct_field("health", 1, |e| self.health.encode(e))?;
+            e.emit_struct_field("attributes", 2, |e| self.attributes.encode(e))?;
+            e.emit_struct_field("armor_slot_head", 3, |e| self.armor_slot_head.encode(e))?;
+            e.emit_struct_field("armor_slot_chest", 4, |e| self.armor_slot_chest.encode(e))?;
+            e.emit_struct_field("armor_slot_legs", 5, |e| self.armor_slot_legs.encode(e))?;
+            e.emit_struct_field("armor_slot_feet", 6, |e| self.armor_slot_feet.encode(e))?;
+            e.emit_struct_field("weapon_slot_left", 7, |e| self.weapon_slot_left.encode(e))?;
+            e.emit_struct_field("weapon_slot_right", 8, |e| self.weapon_slot_right.encode(e))?;
+            e.emit_struct_field("inventory", 9, |e| self.inventory.encode(e))?;
+            e.emit_struct_field("regeneration", 10, |e| self.regeneration.encode(e))?;
+            e.emit_struct_field("resources", 11, |e| self.resources.encode(e))?;
+            e.emit_struct_field("experience", 12, |e| self.experience.encode(e))?;
+            e.emit_struct_field("level", 13, |e| self.level.encode(e))?;
+            e.emit_struct_field("attribute_points", 14, |e| self.attribute_points.encode(e))?;
+            e.emit_struct_field("progression", 15, |e| self.progression.encode(e))?;
+            e.emit_struct_field("effects", 16, |e| self.effects.encode(e))
+        })
+    }
+}
+
+impl Decodable for Character {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Character, D::Error> {
+        d.read_struct("Character", 17, |d| {
+            Ok(Character {
+                name: d.read_struct_field("name", 0, Decodable::decode)?,
+                health: d.read_struct_field("health", 1, Decodable::decode)?,
+                attributes: d.read_struct_field("attributes", 2, Decodable::decode)?,
+                armor_slot_head: d.read_struct_field("armor_slot_head", 3, Decodable::decode)?,
+                armor_slot_chest: d.read_struct_field("armor_slot_chest", 4, Decodable::decode)?,
+                armor_slot_legs: d.read_struct_field("armor_slot_legs", 5, Decodable::decode)?,
+                armor_slot_feet: d.read_struct_field("armor_slot_feet", 6, Decodable::decode)?,
+                weapon_slot_left: d.read_struct_field("weapon_slot_left", 7, Decodable::decode)?,
+                weapon_slot_right: d.read_struct_field("weapon_slot_right",
+                                                       8,
+                                                       Decodable::decode)?,
+                inventory: d.read_struct_field("inventory", 9, Decodable::decode)?,
+                regeneration: d.read_struct_field("regeneration", 10, Decodable::decode)?,
+                resources: d.read_struct_field("resources", 11, Decodable::decode)?,
+                experience: d.read_struct_field("experience", 12, Decodable::decode)?,
+                level: d.read_struct_field("level", 13, Decodable::decode)?,
+                attribute_points: d.read_struct_field("attribute_points", 14, Decodable::decode)?,
+                progression: d.read_struct_field("progression", 15, Decodable::decode)?,
+                effects: d.read_struct_field("effects", 16, Decodable::decode)?,
+                death_hooks: Vec::new(),
+                revive_hooks: Vec::new(),
+            })
+        })
+    }
+}
+
+impl Hash for Character {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut attributes: Vec<_> = self.attributes.iter().collect();
+        attributes.sort();
+
+        self.name.hash(state);
+        self.health.hash(state);
+        attributes.hash(state);
+        self.armor_slot_head.hash(state);
+        self.armor_slot_chest.hash(state);
+        self.armor_slot_legs.hash(state);
+        self.armor_slot_feet.hash(state);
+        self.weapon_slot_left.hash(state);
+        self.weapon_slot_right.hash(state);
+        self.inventory.hash(state);
+        self.regeneration.hash(state);
+        self.resources.hash(state);
+        self.experience.hash(state);
+        self.level.hash(state);
+        self.attribute_points.hash(state);
+        self.progression.hash(state);
+        self.effects.hash(state);
+    }
+}
+
+/// Returns a function looking up the given attributes, missing ones count as `0`
+fn attribute_values(attributes: &HashMap<Attribute, AttributeValue>)
+                    -> impl Fn(&Attribute) -> AttributeValue + '_ {
+    move |attribute| attributes.get(attribute).cloned().unwrap_or(0)
 }
 
 /// A list of all possible attributes
-#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum Attribute {
     /// The charisma of a character
     Charisma,
@@ -207,13 +792,65 @@ pub enum Attribute {
     Wisdom,
 }
 
+impl_serialize_enum!(Attribute {
+    Charisma,
+    Constitution,
+    Defense,
+    Dexterity,
+    Intelligence,
+    Luck,
+    Perception,
+    Strength,
+    Willpower,
+    Wisdom,
+});
+
+impl Attribute {
+    /// Returns all attributes
+    pub fn all() -> [Attribute; 10] {
+        [Attribute::Charisma,
+         Attribute::Constitution,
+         Attribute::Defense,
+         Attribute::Dexterity,
+         Attribute::Intelligence,
+         Attribute::Luck,
+         Attribute::Perception,
+         Attribute::Strength,
+         Attribute::Willpower,
+         Attribute::Wisdom]
+    }
+
+    /// Returns the name of the attribute in lowercase, like `"strength"`
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Attribute::Charisma => "charisma",
+            Attribute::Constitution => "constitution",
+            Attribute::Defense => "defense",
+            Attribute::Dexterity => "dexterity",
+            Attribute::Intelligence => "intelligence",
+            Attribute::Luck => "luck",
+            Attribute::Perception => "perception",
+            Attribute::Strength => "strength",
+            Attribute::Willpower => "willpower",
+            Attribute::Wisdom => "wisdom",
+        }
+    }
+
+    /// Returns the attribute with the given lowercase name
+    pub fn from_name(name: &str) -> Option<Attribute> {
+        Attribute::all().iter().find(|attribute| attribute.name() == name).cloned()
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use item_generator;
-    use item::{ItemType, ItemInfluence};
+    use item::{ItemType, ItemInfluence, ItemRarity};
+    use progression::{DEFAULT_MAX_LEVEL, LevelCurve};
+    use resource::Formula;
 
     #[test]
     fn set_armor_slot_head() {
@@ -331,4 +968,360 @@ mod tests {
 
         assert_eq!(character.attack_damage(), 42);
     }
+
+    #[test]
+    fn attack_damage_with_other_influences() {
+        let mut character = Character::new("Wil Wheaton");
+
+        let rapier = item_generator::ItemGenerator::new()
+            .item_type(ItemType::WeaponSword)
+            .influence(Some(ItemInfluence::new(Attribute::Dexterity, 10)))
+            .gen();
+        let wand = item_generator::ItemGenerator::new()
+            .item_type(ItemType::WeaponWand)
+            .influence(Some(ItemInfluence::new(Attribute::Intelligence, 5)))
+            .gen();
+        let robe = item_generator::ItemGenerator::new()
+            .item_type(ItemType::ArmorChest)
+            .influence(Some(ItemInfluence::new(Attribute::Intelligence, 5)))
+            .gen();
+
+        character.set_weapon_slot_left(Some(rapier));
+        character.set_weapon_slot_right(Some(wand));
+        character.set_armor_slot_chest(Some(robe));
+
+        // A fifth of the dexterity, but all of the intelligence of the wand only
+        assert_eq!(character.attack_damage(), 22 + 2 + 5);
+    }
+
+    #[test]
+    fn armor_and_damage() {
+        let mut character = Character::new("Wil Wheaton");
+
+        let helmet = item_generator::ItemGenerator::new()
+            .item_type(ItemType::ArmorHead)
+            .influence(Some(ItemInfluence::new(Attribute::Defense, 4)))
+            .gen();
+        let boots = item_generator::ItemGenerator::new()
+            .item_type(ItemType::ArmorFeet)
+            .influence(Some(ItemInfluence::new(Attribute::Luck, 2)))
+            .gen();
+
+        character.set_armor_slot_head(Some(helmet));
+        character.set_armor_slot_feet(Some(boots));
+
+        assert_eq!(character.armor(), 4);
+        assert_eq!(character.get_attribute_value(&Attribute::Defense), 19);
+        assert_eq!(character.get_attribute_value(&Attribute::Luck), 2);
+        assert_eq!(character.take_damage(12), 12);
+        assert_eq!(character.health(), 18);
+        assert_eq!(character.take_damage(100), 18);
+        assert_eq!(character.health(), 0);
+    }
+
+    #[test]
+    fn heal_up_to_max_health() {
+        let mut character = Character::new("Wil Wheaton");
+
+        assert_eq!(character.take_damage(10), 10);
+        assert_eq!(character.heal(usize::MAX), 10);
+        assert_eq!(character.health(), 30);
+    }
+
+    #[test]
+    fn regenerate_health() {
+        let mut character = Character::new("Wil Wheaton");
+        character.set_regeneration(4);
+
+        character.take_damage(10);
+        assert_eq!(character.regenerate(), 4);
+        assert_eq!(character.health(), 24);
+    }
+
+    #[test]
+    fn death_hooks() {
+        use std::cell::Cell;
+
+        let deaths = Rc::new(Cell::new(0));
+        let mut character = Character::new("Wil Wheaton");
+        let counter = deaths.clone();
+        character.on_death(move |character| {
+            counter.set(counter.get() + 1);
+            character.inventory_mut().add_gold(1);
+        });
+
+        assert_eq!(character.take_damage(usize::MAX), 30);
+        assert_eq!(character.take_damage(5), 0);
+        assert!(!character.is_alive());
+        assert_eq!(deaths.get(), 1);
+        assert_eq!(character.inventory().gold(), 1);
+    }
+
+    #[test]
+    fn dead_characters_dont_heal() {
+        let mut character = Character::new("Wil Wheaton");
+        character.set_regeneration(4);
+
+        character.take_damage(usize::MAX);
+        assert_eq!(character.heal(10), 0);
+        assert_eq!(character.regenerate(), 0);
+        assert_eq!(character.health(), 0);
+    }
+
+    #[test]
+    fn revive_hooks() {
+        let mut character = Character::new("Wil Wheaton");
+        character.on_revive(|character| character.set_regeneration(0));
+        character.set_regeneration(4);
+
+        assert!(!character.revive(100));
+        character.take_damage(usize::MAX);
+        assert!(character.revive(100));
+        assert!(!character.revive(100));
+        assert_eq!(character.health(), 30);
+        assert_eq!(character.regeneration(), 0);
+    }
+
+    #[test]
+    fn max_health_with_equipment() {
+        let mut character = Character::new("Wil Wheaton");
+        let armor = item_generator::ItemGenerator::new()
+            .item_type(ItemType::ArmorChest)
+            .influence(Some(ItemInfluence::new(Attribute::Constitution, 10)))
+            .gen();
+
+        character.set_armor_slot_chest(Some(armor));
+        assert_eq!(character.max_health(), 40);
+        character.heal(20);
+        assert_eq!(character.health(), 40);
+
+        character.set_armor_slot_chest(None);
+        assert_eq!(character.health(), 30);
+    }
+
+    #[test]
+    fn die_without_max_health() {
+        use std::cell::Cell;
+
+        let deaths = Rc::new(Cell::new(0));
+        let mut character = Character::new("Wil Wheaton");
+        let counter = deaths.clone();
+        character.on_death(move |_| counter.set(counter.get() + 1));
+
+        character.update_attribute(&Attribute::Constitution, 0);
+        assert_eq!(character.health(), 0);
+        assert!(!character.is_alive());
+        assert_eq!(deaths.get(), 1);
+        assert!(!character.revive(10));
+
+        character.update_attribute(&Attribute::Constitution, 5);
+        assert!(character.revive(10));
+        assert_eq!(character.health(), 5);
+    }
+
+    #[test]
+    fn default_resources() {
+        let character = Character::new("Wil Wheaton");
+
+        assert_eq!(character.max_resource("mana"), Some(25));
+        assert_eq!(character.max_resource("stamina"), Some(50));
+        assert_eq!(character.resource("stamina"), Some(50));
+    }
+
+    #[test]
+    fn use_abilities() {
+        let mut character = Character::new("Wil Wheaton");
+        let dash = Ability::new("Dash").cost("stamina", 30).cost("mana", 5);
+        let focus = Ability::new("Focus").cost("focus", 1);
+
+        assert!(!character.can_use(&focus));
+        assert!(character.use_ability(&dash));
+        assert!(!character.use_ability(&dash));
+        assert_eq!(character.resource("stamina"), Some(20));
+        assert_eq!(character.resource("mana"), Some(20));
+    }
+
+    #[test]
+    fn restore_resources_with_consumables() {
+        let mut character = Character::new("Wil Wheaton");
+        character.use_ability(&Ability::new("Dash").cost("stamina", 30));
+
+        let food = item_generator::ItemGenerator::new().item_type(ItemType::ConsumableFood).gen();
+        let name = food.name.clone();
+        character.inventory_mut().add_item(food).unwrap();
+        assert!(character.consume(&name));
+        assert!(!character.consume(&name));
+        assert_eq!(character.resource("stamina"), Some(35));
+    }
+
+    #[test]
+    fn regenerate_resources() {
+        let mut character = Character::new("Wil Wheaton");
+        character.use_ability(&Ability::new("Dash").cost("stamina", 30).cost("mana", 5));
+
+        assert_eq!(character.regenerate(), 0);
+        assert_eq!(character.resource("stamina"), Some(24));
+        assert_eq!(character.resource("mana"), Some(21));
+        assert_eq!(character.restore_resource("mana", 100), 4);
+    }
+
+    #[test]
+    fn limit_resources_to_their_maximum() {
+        let mut character = Character::new("Wil Wheaton");
+
+        character.update_attribute(&Attribute::Willpower, 0);
+        assert_eq!(character.resource("stamina"), Some(20));
+    }
+
+    #[test]
+    fn add_and_remove_resources() {
+        let mut character = Character::new("Wil Wheaton");
+        let focus = Ability::new("Focus").cost("focus", 1);
+
+        character.add_resource(ResourcePool::new("focus", Formula::new(3)));
+        assert!(character.use_ability(&focus));
+        assert_eq!(character.remove_resource("focus").map(|pool| pool.current()), Some(2));
+        assert_eq!(character.resource("focus"), None);
+    }
+
+    #[test]
+    fn levelling() {
+        let mut character = Character::new("Wil Wheaton");
+
+        assert_eq!(character.level(), 1);
+        assert_eq!(character.next_level_experience(), Some(100));
+        assert!(character.gain_experience(99).is_empty());
+
+        let level_ups = character.gain_experience(301);
+        assert_eq!(level_ups,
+                   vec![LevelUp {
+                            level: 2,
+                            attribute_points: 3,
+                            growth: vec![(Attribute::Constitution, 2)],
+                        },
+                        LevelUp {
+                            level: 3,
+                            attribute_points: 3,
+                            growth: vec![(Attribute::Constitution, 2)],
+                        }]);
+        assert_eq!(character.experience(), 400);
+        assert_eq!(character.attribute_points(), 6);
+        assert_eq!(character.max_health(), 34);
+
+        assert_eq!(character.allocate_points(&Attribute::Strength, 0),
+                   Err(AllocationError::NoPoints));
+        assert_eq!(character.allocate_points(&Attribute::Strength, 7),
+                   Err(AllocationError::NotEnoughPoints(6)));
+        assert_eq!(character.allocate_points(&Attribute::Strength, 6), Ok(()));
+        assert_eq!(character.get_attribute_value(&Attribute::Strength), 26);
+        assert_eq!(character.attribute_points(), 0);
+
+        character.set_progression(Progression::default().max_level(4));
+        assert_eq!(character.gain_experience(u64::MAX).len(), 1);
+        assert_eq!(character.level(), 4);
+        assert_eq!(character.next_level_experience(), None);
+    }
+
+    #[test]
+    fn level_up_to_default_max_level() {
+        let mut character = Character::new("Wil Wheaton");
+        character.set_progression(Progression::new(LevelCurve::Polynomial(100, 2)));
+
+        assert_eq!(character.gain_experience(u64::MAX).len() as u32, DEFAULT_MAX_LEVEL - 1);
+        assert_eq!(character.level(), DEFAULT_MAX_LEVEL);
+    }
+
+    #[test]
+    fn flat_curves_end_levelling() {
+        let mut character = Character::new("Wil Wheaton");
+        character.set_progression(Progression::new(LevelCurve::Polynomial(0, 2)));
+        assert!(character.gain_experience(u64::MAX).is_empty());
+
+        let mut character = Character::new("Wil Wheaton");
+        character.set_progression(Progression::new(LevelCurve::Table(vec![10, 20, 20, 30])));
+        assert_eq!(character.gain_experience(u64::MAX).len(), 2);
+        assert_eq!(character.level(), 3);
+    }
+
+    #[test]
+    fn levelling_ends_at_the_last_level() {
+        let mut character = Character::new("Wil Wheaton");
+        character.set_progression(Progression::new(LevelCurve::Polynomial(1, 1))
+            .max_level(u32::MAX));
+        character.level = u32::MAX;
+
+        assert_eq!(character.next_level_experience(), None);
+        assert!(character.gain_experience(1).is_empty());
+    }
+
+    fn plagued() -> Character {
+        let mut character = Character::new("Wil Wheaton");
+        let ring = Item {
+            name: "Ring".to_owned(),
+            item_type: ItemType::ArmorHead,
+            influence: Some(ItemInfluence::new(Attribute::Constitution, 10)),
+            stack_size: 1,
+            rarity: ItemRarity::Rare,
+        };
+
+        character.set_armor_slot_head(Some(ring));
+        character.gain_experience(100);
+        character.add_effect(Effect::new("Plague")
+            .percent(Attribute::Constitution, -50)
+            .duration(1));
+        character
+    }
+
+    #[test]
+    fn attribute_breakdown() {
+        let character = plagued();
+        let breakdown = character.attribute_breakdown(&Attribute::Constitution);
+
+        assert_eq!(breakdown.base, 30);
+        assert_eq!(breakdown.flat(), 12);
+        assert_eq!(breakdown.percent(), -50);
+        assert_eq!(breakdown.to_string(),
+                   "Constitution 21 = 30 base + 10 Ring - 50% Plague + 2 level");
+        assert_eq!(character.base_attribute_value(&Attribute::Constitution), 30);
+    }
+
+    #[test]
+    fn effects_change_vitals() {
+        let character = plagued();
+
+        assert_eq!(character.max_health(), 21);
+        assert_eq!(character.health(), 21);
+        assert_eq!(character.max_resource("stamina"), Some(50));
+    }
+
+    #[test]
+    fn effects_end() {
+        let mut character = plagued();
+        let ended = character.tick_effects();
+
+        assert_eq!(ended.len(), 1);
+        assert_eq!(ended[0].name(), "Plague");
+        assert!(character.effects().is_empty());
+        assert_eq!(character.max_health(), 42);
+    }
+
+    #[test]
+    fn remove_effects() {
+        let mut character = Character::new("Wil Wheaton");
+
+        character.add_effect(Effect::new("Weakness").flat(Attribute::Willpower, -10));
+        assert_eq!(character.resource("stamina"), Some(30));
+        assert!(character.remove_effect("Weakness").is_some());
+        assert_eq!(character.max_resource("stamina"), Some(50));
+        assert_eq!(character.remove_effect("Weakness"), None);
+    }
+
+    #[test]
+    fn attribute_names() {
+        for attribute in &Attribute::all() {
+            assert_eq!(Attribute::from_name(attribute.name()).as_ref(), Some(attribute));
+        }
+
+        assert_eq!(Attribute::from_name("Strength"), None);
+    }
 }
@@ -0,0 +1,519 @@
+use rand::Rng;
+
+use character::{Attribute, Character};
+use entity::Entity;
+use types::{AttributeValue, Health};
+
+/// The chance to hit an equally skilled defender
+const BASE_HIT_CHANCE: f64 = 0.75;
+/// The change of the hit chance per point of `Attribute::Dexterity` the attacker has over the
+/// defender
+const DEXTERITY_HIT_INFLUENCE: f64 = 0.01;
+/// The change of the hit chance per point of `Attribute::Perception` the attacker has over the
+/// defender
+const PERCEPTION_HIT_INFLUENCE: f64 = 0.005;
+/// The lowest and highest possible chance to hit
+const HIT_CHANCE_BOUNDS: (f64, f64) = (0.05, 0.95);
+/// The chance of a hit to be critical without any `Attribute::Luck`
+const BASE_CRITICAL_CHANCE: f64 = 0.05;
+/// The change of the critical chance per point of `Attribute::Luck`
+const LUCK_CRITICAL_INFLUENCE: f64 = 0.01;
+/// The change of the critical chance per point of `Attribute::Perception`
+const PERCEPTION_CRITICAL_INFLUENCE: f64 = 0.002;
+/// The highest possible chance of a hit to be critical
+const MAX_CRITICAL_CHANCE: f64 = 0.5;
+/// The factor applied to the damage of a critical hit
+const CRITICAL_MULTIPLIER: f64 = 1.5;
+/// The amount of defense absorbing half of the damage
+const HALF_MITIGATION_DEFENSE: f64 = 100.0;
+/// The highest random bonus added to the initiative at the start of a combat
+const INITIATIVE_ROLL: AttributeValue = 10;
+
+/// Anything that can fight, like the character of the player or a hostile entity
+pub trait Combatant {
+    /// Returns the name shown in the combat log
+    fn combat_name(&self) -> &str;
+
+    /// Returns the value of the given attribute
+    fn attribute(&self, attribute: &Attribute) -> AttributeValue;
+
+    /// Returns the damage of an attack before it is mitigated
+    fn attack_damage(&self) -> AttributeValue;
+
+    /// Returns the armor mitigating damage in addition to `Attribute::Defense`
+    fn armor(&self) -> AttributeValue {
+        0
+    }
+
+    /// Returns the current health
+    fn health(&self) -> Health;
+
+    /// Reduces the health by the given damage and returns the damage that was actually taken
+    fn take_damage(&mut self, damage: Health) -> Health;
+
+    /// Returns `true` if the combatant can still fight
+    fn is_alive(&self) -> bool {
+        self.health() > 0
+    }
+}
+
+/// Characters fight with their effective attributes. The armor is reported separately, so it is
+/// left out of their `Attribute::Defense` to be counted only once.
+impl Combatant for Character {
+    fn combat_name(&self) -> &str {
+        self.name()
+    }
+
+    fn attribute(&self, attribute: &Attribute) -> AttributeValue {
+        match *attribute {
+            Attribute::Defense => self.get_attribute_value(attribute) - self.armor(),
+            _ => self.get_attribute_value(attribute),
+        }
+    }
+
+    fn attack_damage(&self) -> AttributeValue {
+        Character::attack_damage(self)
+    }
+
+    fn armor(&self) -> AttributeValue {
+        Character::armor(self)
+    }
+
+    fn health(&self) -> Health {
+        Character::health(self)
+    }
+
+    fn take_damage(&mut self, damage: Health) -> Health {
+        Character::take_damage(self, damage)
+    }
+}
+
+/// Entities fight with the numbers in their state: `"health"`, `"attack"`, `"armor"` and the
+/// attributes by their name, like `"dexterity"`. Missing numbers count as `0`, except for the
+/// attack, which defaults to the strength of the entity.
+impl Combatant for Entity {
+    fn combat_name(&self) -> &str {
+        self.name()
+    }
+
+    fn attribute(&self, attribute: &Attribute) -> AttributeValue {
+        self.state().get_int(attribute.name()).unwrap_or(0)
+    }
+
+    fn attack_damage(&self) -> AttributeValue {
+        self.state()
+            .get_int("attack")
+            .unwrap_or_else(|| self.attribute(&Attribute::Strength))
+    }
+
+    fn armor(&self) -> AttributeValue {
+        self.state().get_int("armor").unwrap_or(0)
+    }
+
+    fn health(&self) -> Health {
+        self.state().get_int("health").unwrap_or(0).max(0) as Health
+    }
+
+    fn take_damage(&mut self, damage: Health) -> Health {
+        let health = self.health();
+        let taken = damage.min(health);
+        self.state_mut().set("health", (health - taken) as i64);
+        taken
+    }
+}
+
+/// The outcome of an attack
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// The attack missed
+    Miss,
+    /// The attack hit
+    Hit,
+    /// The attack hit critically
+    Critical,
+}
+
+impl_serialize_enum!(Outcome { Miss, Hit, Critical });
+
+/// An entry of the combat log
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AttackResult {
+    /// The round of the combat the attack happened in, starting at `1`
+    pub round: u32,
+    /// The name of the attacker
+    pub attacker: String,
+    /// The name of the defender
+    pub defender: String,
+    /// Whether the attack hit
+    pub outcome: Outcome,
+    /// The damage the defender took
+    pub damage: Health,
+    /// The damage absorbed by the defense and armor of the defender
+    pub mitigated: Health,
+    /// Whether the defender was defeated by the attack
+    pub defeated: bool,
+}
+
+impl_serialize_struct!(AttackResult {
+    round,
+    attacker,
+    defender,
+    outcome,
+    damage,
+    mitigated,
+    defeated,
+});
+
+/// Returns the chance of the attacker to hit the defender. Dexterity and perception increase
+/// the chance of the attacker and decrease the chance of anyone attacking them.
+pub fn hit_chance(attacker: &dyn Combatant, defender: &dyn Combatant) -> f64 {
+    let difference = |attribute| attacker.attribute(&attribute) - defender.attribute(&attribute);
+    let chance = BASE_HIT_CHANCE +
+                 difference(Attribute::Dexterity) as f64 * DEXTERITY_HIT_INFLUENCE +
+                 difference(Attribute::Perception) as f64 * PERCEPTION_HIT_INFLUENCE;
+
+    chance.clamp(HIT_CHANCE_BOUNDS.0, HIT_CHANCE_BOUNDS.1)
+}
+
+/// Returns the chance of a hit of the attacker to be critical, based on their luck and
+/// perception
+pub fn critical_chance(attacker: &dyn Combatant) -> f64 {
+    let chance = BASE_CRITICAL_CHANCE +
+                 attacker.attribute(&Attribute::Luck) as f64 * LUCK_CRITICAL_INFLUENCE +
+                 attacker.attribute(&Attribute::Perception) as f64 * PERCEPTION_CRITICAL_INFLUENCE;
+
+    chance.clamp(0.0, MAX_CRITICAL_CHANCE)
+}
+
+/// Reduces the damage by the defense and armor of the defender and returns the damage that
+/// gets through. Every point of defense absorbs a bit less than the one before, so the damage
+/// never drops to zero.
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::character::Character;
+/// # use rpg::combat;
+/// // 15 defense by default
+/// let defender = Character::new("Michael");
+///
+/// assert_eq!(combat::mitigate(23, &defender), 20);
+/// ```
+pub fn mitigate(damage: AttributeValue, defender: &dyn Combatant) -> Health {
+    if damage <= 0 {
+        return 0;
+    }
+
+    let defense = (defender.attribute(&Attribute::Defense) + defender.armor()).max(0) as f64;
+    let mitigated = damage as f64 * HALF_MITIGATION_DEFENSE / (HALF_MITIGATION_DEFENSE + defense);
+
+    (mitigated.round() as Health).max(1)
+}
+
+/// Returns the initiative of the combatant without the random bonus. Combatants with a higher
+/// initiative act first.
+pub fn initiative(combatant: &dyn Combatant) -> AttributeValue {
+    combatant.attribute(&Attribute::Dexterity) + combatant.attribute(&Attribute::Perception) / 2
+}
+
+/// Resolves a single attack and applies the damage to the defender. The round of the result
+/// is `0`, use `Combat::attack` for attacks that are part of a combat.
+pub fn attack<R: Rng>(attacker: &dyn Combatant,
+                      defender: &mut dyn Combatant,
+                      rng: &mut R)
+                      -> AttackResult {
+    let outcome = if rng.gen::<f64>() >= hit_chance(attacker, defender) {
+        Outcome::Miss
+    } else if rng.gen::<f64>() < critical_chance(attacker) {
+        Outcome::Critical
+    } else {
+        Outcome::Hit
+    };
+
+    let raw = match outcome {
+        Outcome::Miss => 0,
+        Outcome::Hit => attacker.attack_damage().max(0),
+        Outcome::Critical => (attacker.attack_damage().max(0) as f64 * CRITICAL_MULTIPLIER) as i64,
+    };
+    let dealt = mitigate(raw, defender);
+    let damage = defender.take_damage(dealt);
+
+    AttackResult {
+        round: 0,
+        attacker: attacker.combat_name().to_owned(),
+        defender: defender.combat_name().to_owned(),
+        outcome,
+        damage,
+        mitigated: raw as Health - dealt,
+        defeated: damage > 0 && !defender.is_alive(),
+    }
+}
+
+/// A turn-based combat
+///
+/// The combat doesn't own the combatants, it only knows them by their index in the list it was
+/// started with, so it can be kept in the state of the game between updates and saved with it.
+/// The game passes the combatants to every call in the same order.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate rand;
+/// # extern crate rpg;
+/// # use rand::{SeedableRng, XorShiftRng};
+/// # use rpg::character::Character;
+/// # use rpg::combat::{Combat, Combatant};
+/// # use rpg::entity::Entity;
+/// # fn main() {
+/// let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+/// let mut character = Character::new("Michael");
+/// let mut wolf = Entity::new("Wolf");
+/// wolf.state_mut().set("health", 40);
+/// wolf.state_mut().set("attack", 8);
+/// wolf.state_mut().set("dexterity", 15);
+///
+/// let mut combat = Combat::new(&[&character, &wolf], &mut rng);
+/// while character.is_alive() && wolf.is_alive() {
+///     if combat.current() == 0 {
+///         combat.attack(&character, &mut wolf, &mut rng);
+///     } else {
+///         combat.attack(&wolf, &mut character, &mut rng);
+///     }
+///     combat.next_turn(&[&character, &wolf]);
+/// }
+///
+/// assert!(combat.log().last().unwrap().defeated);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Combat {
+    order: Vec<usize>,
+    turn: usize,
+    round: u32,
+    log: Vec<AttackResult>,
+}
+
+impl_serialize_struct!(Combat { order, turn, round, log });
+
+impl Combat {
+    /// Starts a new combat between the given combatants. The turn order is decided by their
+    /// initiative plus a random bonus, ties are broken by the order of the list.
+    pub fn new<R: Rng>(combatants: &[&dyn Combatant], rng: &mut R) -> Combat {
+        let mut initiatives: Vec<(usize, AttributeValue)> = combatants.iter()
+            .enumerate()
+            .map(|(index, combatant)| {
+                (index, initiative(*combatant) + rng.gen_range(0, INITIATIVE_ROLL + 1))
+            })
+            .collect();
+        initiatives.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        Combat {
+            order: initiatives.into_iter().map(|(index, _)| index).collect(),
+            turn: 0,
+            round: 1,
+            log: Vec::new(),
+        }
+    }
+
+    /// Returns the indices of the combatants in the order they act
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Returns the current round, starting at `1`
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    /// Returns the index of the combatant whose turn it is
+    ///
+    /// # Panics
+    ///
+    /// **Panics** if the combat was started without combatants
+    pub fn current(&self) -> usize {
+        self.order[self.turn]
+    }
+
+    /// Resolves an attack, applies the damage to the defender and adds the result to the log
+    pub fn attack<R: Rng>(&mut self,
+                          attacker: &dyn Combatant,
+                          defender: &mut dyn Combatant,
+                          rng: &mut R)
+                          -> &AttackResult {
+        let mut result = attack(attacker, defender, rng);
+        result.round = self.round;
+        self.log.push(result);
+        &self.log[self.log.len() - 1]
+    }
+
+    /// Ends the current turn and returns the index of the next combatant that is still alive,
+    /// skipping defeated ones. Returns `None` if nobody is left.
+    pub fn next_turn(&mut self, combatants: &[&dyn Combatant]) -> Option<usize> {
+        for _ in 0..self.order.len() {
+            self.turn += 1;
+            if self.turn == self.order.len() {
+                self.turn = 0;
+                self.round += 1;
+            }
+
+            if combatants.get(self.current()).is_some_and(|combatant| combatant.is_alive()) {
+                return Some(self.current());
+            }
+        }
+
+        None
+    }
+
+    /// Returns the results of all attacks so far
+    pub fn log(&self) -> &[AttackResult] {
+        &self.log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, XorShiftRng};
+    use rustc_serialize::json;
+    use item::{Item, ItemInfluence, ItemRarity, ItemType};
+
+    fn wolf(health: i64) -> Entity {
+        let mut wolf = Entity::new("Wolf");
+        wolf.state_mut().set("health", health);
+        wolf.state_mut().set("attack", 8);
+        wolf.state_mut().set("dexterity", 15);
+        wolf
+    }
+
+    #[test]
+    fn chances() {
+        let mut character = Character::new("Michael");
+        let mut wolf = wolf(20);
+
+        // The wolf is more agile, but can't see as well
+        assert!((hit_chance(&character, &wolf) - 0.75).abs() < 1e-9);
+        assert!((hit_chance(&wolf, &character) - 0.75).abs() < 1e-9);
+        wolf.state_mut().set("perception", 20);
+        assert!((hit_chance(&character, &wolf) - 0.65).abs() < 1e-9);
+        assert!((critical_chance(&character) - 0.07).abs() < 1e-9);
+
+        character.update_attribute(&Attribute::Dexterity, 200);
+        character.update_attribute(&Attribute::Luck, 100);
+        assert!((hit_chance(&character, &wolf) - 0.95).abs() < 1e-9);
+        assert!((hit_chance(&wolf, &character) - 0.05).abs() < 1e-9);
+        assert!((critical_chance(&character) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mitigation() {
+        let mut wolf = wolf(20);
+
+        assert_eq!(mitigate(10, &wolf), 10);
+        assert_eq!(mitigate(0, &wolf), 0);
+
+        wolf.state_mut().set("defense", 50);
+        wolf.state_mut().set("armor", 50);
+        assert_eq!(mitigate(10, &wolf), 5);
+        assert_eq!(mitigate(1, &wolf), 1);
+
+        assert_eq!(wolf.take_damage(15), 15);
+        assert_eq!(wolf.take_damage(15), 5);
+        assert!(!wolf.is_alive());
+    }
+
+    #[test]
+    fn character_armor() {
+        let mut character = Character::new("Michael");
+        character.set_armor_slot_head(Some(Item {
+            name: "Helmet".to_owned(),
+            item_type: ItemType::ArmorHead,
+            influence: Some(ItemInfluence::new(Attribute::Defense, 4)),
+            stack_size: 1,
+            rarity: ItemRarity::Common,
+        }));
+
+        // The armor is part of the defense, but only counted once
+        assert_eq!(Combatant::armor(&character), 4);
+        assert_eq!(Combatant::attribute(&character, &Attribute::Defense), 15);
+        assert_eq!(character.get_attribute_value(&Attribute::Defense), 19);
+        assert_eq!(character.attribute_breakdown(&Attribute::Defense).to_string(),
+                   "Defense 19 = 15 base + 4 Helmet");
+    }
+
+    #[test]
+    fn attacks() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let character = Character::new("Michael");
+        let mut wolf = wolf(10000);
+
+        let results: Vec<AttackResult> =
+            (0..200).map(|_| attack(&character, &mut wolf, &mut rng)).collect();
+        let count = |outcome| results.iter().filter(|result| result.outcome == outcome).count();
+
+        assert!(count(Outcome::Miss) > 0);
+        assert!(count(Outcome::Hit) > count(Outcome::Miss));
+        assert!(count(Outcome::Critical) > 0);
+
+        for result in &results {
+            let damage = match result.outcome {
+                Outcome::Miss => 0,
+                Outcome::Hit => 22,
+                Outcome::Critical => 33,
+            };
+            assert_eq!(result.damage + result.mitigated, damage);
+        }
+
+        let total: Health = results.iter().map(|result| result.damage).sum();
+        assert_eq!(wolf.health(), 10000 - total);
+    }
+
+    #[test]
+    fn turn_order() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let character = Character::new("Michael");
+        let mut fast = wolf(10);
+        fast.state_mut().set("dexterity", 50);
+        let mut slow = wolf(10);
+        slow.state_mut().set("dexterity", 0);
+        let mut dead = wolf(0);
+        dead.state_mut().set("dexterity", 0);
+
+        let combatants: [&dyn Combatant; 4] = [&character, &fast, &slow, &dead];
+        let mut combat = Combat::new(&combatants, &mut rng);
+
+        assert_eq!(&combat.order()[..2], &[1, 0]);
+        assert_eq!(combat.current(), 1);
+        assert_eq!(combat.next_turn(&combatants), Some(0));
+        assert_eq!(combat.next_turn(&combatants), Some(2));
+        assert_eq!(combat.next_turn(&combatants), Some(1));
+        assert_eq!(combat.round(), 2);
+
+        let nobody: [&dyn Combatant; 4] = [&dead, &dead, &dead, &dead];
+        assert_eq!(combat.next_turn(&nobody), None);
+    }
+
+    #[test]
+    fn fight() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut character = Character::new("Michael");
+        let mut wolf = wolf(60);
+
+        let mut combat = Combat::new(&[&character, &wolf], &mut rng);
+        while character.is_alive() && wolf.is_alive() {
+            if combat.current() == 0 {
+                combat.attack(&character, &mut wolf, &mut rng);
+            } else {
+                combat.attack(&wolf, &mut character, &mut rng);
+            }
+            combat.next_turn(&[&character, &wolf]);
+        }
+
+        let last = combat.log().last().unwrap();
+        assert!(last.defeated);
+        assert_eq!(last.round, combat.log().iter().map(|result| result.round).max().unwrap());
+        assert!(combat.log().iter().all(|result| !result.defeated || result == last));
+
+        let loaded: Combat = json::decode(&json::encode(&combat).unwrap()).unwrap();
+        assert_eq!(loaded, combat);
+    }
+}
@@ -1,118 +1,934 @@
-/// The state of the whole game
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::{self, Rng, SeedableRng, XorShiftRng};
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use event::{EventKind, Message};
+use event_bus::{EventBus, EventQueue};
+use input::{Input, InputSource};
+use scene::{Scene, SceneStack};
+use system::{System, Systems};
+
+/// The default amount of updates per second
+pub const DEFAULT_TICKS_PER_SECOND: u32 = 60;
+
+/// The default maximum amount of updates that are run to catch up before a frame is drawn
+pub const DEFAULT_MAX_CATCH_UP: u32 = 5;
+
+/// The state of the whole game. `S` is the type of the game specific state, like characters,
+/// worlds and campaigns.
 #[allow(missing_docs)]
-pub struct EngineContext {
-    pub maps: Vec<u32>,
+pub struct EngineContext<S = ()> {
+    /// The state of the game itself
+    pub state: S,
     pub running: bool,
     pub invalid: bool,
+    /// The number of updates that have been run since the engine was started
+    pub tick: u64,
+    /// The fixed amount of seconds simulated by a single update
+    pub delta: f64,
+    /// How far the time has progressed between the last update and the next one, from `0.0` to
+    /// `1.0`. Used to interpolate the output while drawing
+    pub alpha: f64,
+    /// The input of the player collected for the current update
+    pub input: Input,
+    /// The names of the systems that are currently skipped
+    pub disabled_systems: HashSet<String>,
+    /// The events published during the current update
+    pub events: EventQueue,
+    /// The seed `rng` was created with
+    pub seed: u64,
+    /// The random number generator of the game. Use it for all randomness to keep the game
+    /// reproducible
+    pub rng: XorShiftRng,
 }
 
-impl EngineContext {
-    /// Create a new EngineContext
-    pub fn new() -> EngineContext {
+impl<S> EngineContext<S> {
+    /// Create a new EngineContext holding the given game state
+    pub fn new(state: S) -> EngineContext<S> {
         EngineContext {
-            maps: Vec::new(),
+            state,
             running: false,
             invalid: false,
+            tick: 0,
+            delta: 1_f64 / DEFAULT_TICKS_PER_SECOND as f64,
+            alpha: 0_f64,
+            input: Input::default(),
+            disabled_systems: HashSet::new(),
+            events: EventQueue::new(),
+            seed: 0,
+            rng: seeded_rng(0),
+        }
+    }
+
+    /// Replaces the random number generator with a new one created from the given seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = seeded_rng(seed);
+    }
+
+    /// Replaces the random number generator with a new one seeded from its next number. The
+    /// state of a generator can't be saved directly, so this is the generator a context decoded
+    /// from a save of this one continues with. Call it right after encoding the context to keep
+    /// both in sync.
+    pub fn split_rng(&mut self) {
+        self.rng = seeded_rng(self.rng.gen());
+    }
+
+    /// Enables the system with the given name again
+    pub fn enable_system(&mut self, name: &str) {
+        self.disabled_systems.remove(name);
+    }
+
+    /// Disables the system with the given name. It is skipped until it is enabled again.
+    pub fn disable_system(&mut self, name: &str) {
+        self.disabled_systems.insert(name.to_owned());
+    }
+
+    /// Returns `true` unless the system with the given name was disabled
+    pub fn is_system_enabled(&self, name: &str) -> bool {
+        !self.disabled_systems.contains(name)
+    }
+
+    /// Wraps the given error into an `EngineError::Aborted`. Return it from a behaviour to stop
+    /// the engine.
+    pub fn abort<E: Into<Box<dyn Error>>>(self, error: E) -> EngineError<S> {
+        EngineError::Aborted(Box::new(self), error.into())
+    }
+}
+
+impl<S: Default> Default for EngineContext<S> {
+    fn default() -> EngineContext<S> {
+        EngineContext::new(S::default())
+    }
+}
+
+impl<S> fmt::Debug for EngineContext<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EngineContext")
+            .field("running", &self.running)
+            .field("invalid", &self.invalid)
+            .field("tick", &self.tick)
+            .field("delta", &self.delta)
+            .field("alpha", &self.alpha)
+            .field("input", &self.input)
+            .field("disabled_systems", &self.disabled_systems)
+            .field("events", &self.events)
+            .field("seed", &self.seed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Only the persistent parts of the context are saved: the state, the tick, the seed, the
+/// disabled systems and the random number generator. The input and the pending events belong to
+/// a single update and are dropped.
+impl<S: Encodable> Encodable for EngineContext<S> {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        let rng: u64 = self.rng.clone().gen();
+        let mut disabled_systems: Vec<&String> = self.disabled_systems.iter().collect();
+        disabled_systems.sort();
+
+        e.emit_struct("EngineContext", 5, |e| {
+            e.emit_struct_field("state", 0, |e| self.state.encode(e))?;
+            e.emit_struct_field("tick", 1, |e| self.tick.encode(e))?;
+            e.emit_struct_field("seed", 2, |e| self.seed.encode(e))?;
+            e.emit_struct_field("disabled_systems", 3, |e| disabled_systems.encode(e))?;
+            e.emit_struct_field("rng", 4, |e| rng.encode(e))
+        })
+    }
+}
+
+impl<S: Decodable> Decodable for EngineContext<S> {
+    fn decode<D: Decoder>(d: &mut D) -> Result<EngineContext<S>, D::Error> {
+        d.read_struct("EngineContext", 5, |d| {
+            let mut context = EngineContext::new(d.read_struct_field("state", 0, S::decode)?);
+            context.tick = d.read_struct_field("tick", 1, Decodable::decode)?;
+            context.seed = d.read_struct_field("seed", 2, Decodable::decode)?;
+            context.disabled_systems =
+                d.read_struct_field("disabled_systems", 3, Decodable::decode)?;
+            context.rng = seeded_rng(d.read_struct_field("rng", 4, Decodable::decode)?);
+            Ok(context)
+        })
+    }
+}
+
+/// Creates a random number generator that produces the same numbers on every platform for the
+/// same seed
+fn seeded_rng(seed: u64) -> XorShiftRng {
+    // A xorshift generator must not be seeded with zeros only
+    XorShiftRng::from_seed([seed as u32, (seed >> 32) as u32, 0x9e37_79b9, 0x7f4a_7c15])
+}
+
+/// The result of running a part of the engine
+pub type EngineResult<S = ()> = Result<EngineContext<S>, EngineError<S>>;
+
+type Callback<S> = Box<dyn Fn(EngineContext<S>) -> EngineResult<S>>;
+
+/// An error that prevented the engine from being built or kept it from running
+pub enum EngineError<S = ()> {
+    /// Neither an update behaviour, a scene nor a system was defined
+    MissingUpdate,
+    /// The amount of updates per second has to be greater than `0`
+    InvalidTicksPerSecond(u32),
+    /// The maximum amount of catch up updates has to be greater than `0`
+    InvalidMaxCatchUp(u32),
+    /// Two systems were registered with the same name
+    DuplicateSystem(String),
+    /// The ordering constraints of the given systems contradict each other
+    SystemCycle(Vec<String>),
+    /// A behaviour stopped the engine. Contains the context at the time of the failure
+    Aborted(Box<EngineContext<S>>, Box<dyn Error>),
+}
+
+impl<S> fmt::Debug for EngineError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EngineError::MissingUpdate => write!(f, "MissingUpdate"),
+            EngineError::InvalidTicksPerSecond(ticks) => {
+                f.debug_tuple("InvalidTicksPerSecond").field(&ticks).finish()
+            }
+            EngineError::InvalidMaxCatchUp(max) => {
+                f.debug_tuple("InvalidMaxCatchUp").field(&max).finish()
+            }
+            EngineError::DuplicateSystem(ref name) => {
+                f.debug_tuple("DuplicateSystem").field(name).finish()
+            }
+            EngineError::SystemCycle(ref names) => {
+                f.debug_tuple("SystemCycle").field(names).finish()
+            }
+            EngineError::Aborted(ref context, ref error) => {
+                f.debug_tuple("Aborted").field(context).field(error).finish()
+            }
+        }
+    }
+}
+
+impl<S> fmt::Display for EngineError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EngineError::MissingUpdate => {
+                write!(f, "neither an update behaviour, a scene nor a system was defined")
+            }
+            EngineError::InvalidTicksPerSecond(ticks) => {
+                write!(f, "invalid amount of ticks per second: {}", ticks)
+            }
+            EngineError::InvalidMaxCatchUp(max) => {
+                write!(f, "invalid maximum amount of catch up updates: {}", max)
+            }
+            EngineError::DuplicateSystem(ref name) => {
+                write!(f, "the system {} was registered twice", name)
+            }
+            EngineError::SystemCycle(ref names) => {
+                write!(f, "cyclic ordering constraints between the systems {}", names.join(", "))
+            }
+            EngineError::Aborted(_, ref error) => write!(f, "the engine was aborted: {}", error),
+        }
+    }
+}
+
+impl<S> Error for EngineError<S> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            EngineError::Aborted(_, ref error) => Some(error.as_ref()),
+            _ => None,
         }
     }
 }
 
-/// The engine to run the game
-pub struct Engine {
-    setup: Option<Box<Fn(EngineContext) -> EngineContext>>,
-    update: Option<Box<Fn(EngineContext) -> EngineContext>>,
-    draw: Option<Box<Fn(EngineContext) -> EngineContext>>,
+/// A builder for the `Engine`. Only an update behaviour, an initial scene or a system is
+/// required, everything else has a default.
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::engine::{EngineBuilder, EngineContext};
+/// let engine = EngineBuilder::with_state(0)
+///     .setup(|context| Ok(EngineContext { state: 42, ..context }))
+///     .ticks_per_second(30)
+///     .update(|context| Ok(EngineContext { running: false, ..context }))
+///     .build()
+///     .unwrap();
+///
+/// let context = engine.start().unwrap();
+/// assert_eq!(context.tick, 1);
+/// assert_eq!(context.state, 42);
+/// ```
+pub struct EngineBuilder<S = ()> {
+    context: EngineContext<S>,
+    resumed: bool,
+    setup: Option<Callback<S>>,
+    update: Option<Callback<S>>,
+    draw: Option<Callback<S>>,
+    input: Option<Box<dyn InputSource>>,
+    scene: Option<Box<dyn Scene<S>>>,
+    systems: Vec<Box<dyn System<S>>>,
+    bus: EventBus<S>,
+    ticks_per_second: u32,
+    max_catch_up: u32,
+    seed: Option<u64>,
 }
 
-impl Engine {
-    /// Creates a new engine
-    pub fn new() -> Engine {
-        Engine {
+impl EngineBuilder {
+    /// Creates a new `EngineBuilder` for a game without any state
+    pub fn new() -> EngineBuilder {
+        EngineBuilder::with_state(())
+    }
+}
+
+impl<S> EngineBuilder<S> {
+    /// Creates a new `EngineBuilder` for a game starting with the given state
+    pub fn with_state(state: S) -> EngineBuilder<S> {
+        EngineBuilder::from_context(EngineContext::new(state), false)
+    }
+
+    fn from_context(context: EngineContext<S>, resumed: bool) -> EngineBuilder<S> {
+        EngineBuilder {
+            context,
+            resumed,
             setup: None,
             update: None,
             draw: None,
+            input: None,
+            scene: None,
+            systems: Vec::new(),
+            bus: EventBus::new(),
+            ticks_per_second: DEFAULT_TICKS_PER_SECOND,
+            max_catch_up: DEFAULT_MAX_CATCH_UP,
+            seed: None,
         }
     }
 
-    /// A method to define the setup behavior
-    pub fn setup<T: 'static>(&mut self, setup: T)
-        where T: Fn(EngineContext) -> EngineContext
+    /// Creates a new `EngineBuilder` continuing a game from the given context, usually one
+    /// loaded from a save. The context keeps its tick and random number generator, the setup
+    /// behaviour is run again.
+    pub fn resume(context: EngineContext<S>) -> EngineBuilder<S> {
+        EngineBuilder::from_context(context, true)
+    }
+
+    /// Sets the setup behaviour, which is run once before the first update
+    pub fn setup<T>(mut self, setup: T) -> EngineBuilder<S>
+        where T: Fn(EngineContext<S>) -> EngineResult<S> + 'static
     {
         self.setup = Some(Box::new(setup));
+        self
     }
 
-    /// A method to define the update behavior
-    pub fn update<T: 'static>(&mut self, update: T)
-        where T: Fn(EngineContext) -> EngineContext
+    /// Sets the update behaviour, which is run `ticks_per_second` times per second
+    pub fn update<T>(mut self, update: T) -> EngineBuilder<S>
+        where T: Fn(EngineContext<S>) -> EngineResult<S> + 'static
     {
         self.update = Some(Box::new(update));
+        self
     }
 
-    /// A method to define the draw behavior
-    pub fn draw<T: 'static>(&mut self, draw: T)
-        where T: Fn(EngineContext) -> EngineContext
+    /// Sets the draw behaviour, which is run once per frame if the context was invalidated
+    pub fn draw<T>(mut self, draw: T) -> EngineBuilder<S>
+        where T: Fn(EngineContext<S>) -> EngineResult<S> + 'static
     {
         self.draw = Some(Box::new(draw));
+        self
+    }
+
+    /// Sets the source the input of the player is read from. It is polled once before every
+    /// update and the events are available as `context.input`.
+    pub fn input<T: InputSource + 'static>(mut self, input: T) -> EngineBuilder<S> {
+        self.input = Some(Box::new(input));
+        self
+    }
+
+    /// Sets the scene the game starts with. It is entered right after the setup behaviour ran.
+    ///
+    /// Every update, the update behaviour runs first and the active scene afterwards. While
+    /// drawing, the visible scenes are drawn before the draw behaviour. The engine stops once
+    /// the last scene was removed from the stack.
+    pub fn scene<T: Scene<S> + 'static>(mut self, scene: T) -> EngineBuilder<S> {
+        self.scene = Some(Box::new(scene));
+        self
+    }
+
+    /// Registers a system. The systems run in every phase before the scenes and the behaviours,
+    /// in the order given by their constraints.
+    pub fn system<T: System<S> + 'static>(mut self, system: T) -> EngineBuilder<S> {
+        self.systems.push(Box::new(system));
+        self
+    }
+
+    /// Subscribes to all published events of the given kind. The events are dispatched at the end
+    /// of every update.
+    pub fn subscribe<T>(mut self, kind: EventKind, subscriber: T) -> EngineBuilder<S>
+        where T: Fn(&Message, EngineContext<S>) -> EngineResult<S> + 'static
+    {
+        self.bus.subscribe(kind, subscriber);
+        self
+    }
+
+    /// Subscribes to every published event. The events are dispatched at the end of every update.
+    pub fn subscribe_all<T>(mut self, subscriber: T) -> EngineBuilder<S>
+        where T: Fn(&Message, EngineContext<S>) -> EngineResult<S> + 'static
+    {
+        self.bus.subscribe_all(subscriber);
+        self
+    }
+
+    /// Sets the amount of updates run per second. Defaults to `DEFAULT_TICKS_PER_SECOND`.
+    pub fn ticks_per_second(mut self, ticks_per_second: u32) -> EngineBuilder<S> {
+        self.ticks_per_second = ticks_per_second;
+        self
+    }
+
+    /// Sets the maximum amount of updates run in a row to catch up with the clock before a frame
+    /// is drawn. If an update takes longer than its time slice, the remaining backlog is dropped
+    /// instead of piling up. Defaults to `DEFAULT_MAX_CATCH_UP`.
+    pub fn max_catch_up(mut self, max_catch_up: u32) -> EngineBuilder<S> {
+        self.max_catch_up = max_catch_up;
+        self
+    }
+
+    /// Sets the seed of the random number generator in the context. A random seed is chosen if
+    /// none is given. Ignored when resuming a game, which keeps the generator of its context.
+    pub fn seed(mut self, seed: u64) -> EngineBuilder<S> {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Validates the configuration and builds the `Engine`
+    pub fn build(self) -> Result<Engine<S>, EngineError<S>> {
+        if self.update.is_none() && self.scene.is_none() && self.systems.is_empty() {
+            return Err(EngineError::MissingUpdate);
+        }
+
+        if self.ticks_per_second == 0 {
+            return Err(EngineError::InvalidTicksPerSecond(self.ticks_per_second));
+        }
+
+        if self.max_catch_up == 0 {
+            return Err(EngineError::InvalidMaxCatchUp(self.max_catch_up));
+        }
+
+        let systems = Systems::new(self.systems)?;
+        let seed = match self.seed {
+            _ if self.resumed => self.context.seed,
+            Some(seed) => seed,
+            None => rand::thread_rng().gen(),
+        };
+
+        Ok(Engine {
+            context: Some(self.context),
+            resumed: self.resumed,
+            setup: self.setup,
+            update: self.update,
+            draw: self.draw,
+            input: self.input,
+            scene: self.scene,
+            scenes: SceneStack::new(),
+            systems,
+            bus: self.bus,
+            ticks_per_second: self.ticks_per_second,
+            max_catch_up: self.max_catch_up,
+            seed,
+        })
+    }
+}
+
+impl<S: Default> Default for EngineBuilder<S> {
+    fn default() -> EngineBuilder<S> {
+        EngineBuilder::with_state(S::default())
+    }
+}
+
+/// The engine to run the game. Use the `EngineBuilder` to create one.
+pub struct Engine<S = ()> {
+    context: Option<EngineContext<S>>,
+    resumed: bool,
+    setup: Option<Callback<S>>,
+    update: Option<Callback<S>>,
+    draw: Option<Callback<S>>,
+    input: Option<Box<dyn InputSource>>,
+    scene: Option<Box<dyn Scene<S>>>,
+    scenes: SceneStack<S>,
+    systems: Systems<S>,
+    bus: EventBus<S>,
+    ticks_per_second: u32,
+    max_catch_up: u32,
+    seed: u64,
+}
+
+impl<S> Engine<S> {
+    /// Returns the seed of the random number generator in the context
+    pub fn seed(&self) -> u64 {
+        self.seed
     }
 
     /// Start the engine
-    pub fn start(&self) {
-        let setup = self.setup.as_ref().unwrap();
-        let update = self.update.as_ref().unwrap();
-        let draw = self.draw.as_ref().unwrap();
+    ///
+    /// The updates are run at a fixed rate of `ticks_per_second`, while the drawing happens once
+    /// per frame whenever the context was invalidated. Between frames the engine sleeps until the
+    /// next update is due.
+    ///
+    /// Returns the final context once `running` was set to `false`, or the error of the behaviour
+    /// that aborted the engine.
+    pub fn start(mut self) -> EngineResult<S> {
+        let step = Duration::from_secs(1) / self.ticks_per_second;
 
-        let mut context = EngineContext::new();
-        context.running = true;
-        context = setup(context);
+        let mut context = self.begin()?;
+
+        let mut previous = Instant::now();
+        let mut lag = Duration::new(0, 0);
+
+        while context.running {
+            let now = Instant::now();
+            lag += now - previous;
+            previous = now;
+
+            let mut updates = 0;
+            while lag >= step && context.running {
+                if updates == self.max_catch_up {
+                    // Drop the backlog to avoid spiraling into ever longer catch up phases
+                    let remainder = lag.as_nanos() % step.as_nanos();
+                    lag = Duration::from_nanos(remainder as u64);
+                    break;
+                }
+
+                context = self.tick(context)?;
+                lag -= step;
+                updates += 1;
+            }
 
-        loop {
             if !context.running {
                 break;
             }
 
-            context = update(context);
+            context.alpha = lag.as_secs_f64() / step.as_secs_f64();
 
             if context.invalid {
                 context.invalid = false;
-                context = draw(context);
+                context = self.render(context)?;
+            }
+
+            let elapsed = lag + previous.elapsed();
+            if elapsed < step {
+                thread::sleep(step - elapsed);
+            }
+        }
+
+        Ok(context)
+    }
+
+    /// Runs the engine without a clock and without drawing. The setup is followed by at most
+    /// `ticks` updates, fewer if `running` is set to `false` earlier.
+    ///
+    /// Together with a `ScriptedSource` as input and a fixed seed, every run produces the same
+    /// final context, which makes it possible to test a game from start to finish.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rpg::engine::EngineBuilder;
+    /// # use rpg::input::{InputEvent, Key, ScriptedSource};
+    /// let engine = EngineBuilder::with_state(0)
+    ///     .seed(42)
+    ///     .input(ScriptedSource::new().idle(2).tick(vec![InputEvent::Key(Key::Enter)]))
+    ///     .update(|mut context| {
+    ///         context.state += context.input.events.len();
+    ///         Ok(context)
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let context = engine.run_headless(10).unwrap();
+    /// assert_eq!(context.tick, 10);
+    /// assert_eq!(context.state, 1);
+    /// ```
+    pub fn run_headless(mut self, ticks: u64) -> EngineResult<S> {
+        let mut context = self.begin()?;
+
+        while context.running && context.tick < ticks {
+            context = self.tick(context)?;
+        }
+
+        Ok(context)
+    }
+
+    /// Creates the context and runs the setup
+    fn begin(&mut self) -> EngineResult<S> {
+        let mut context = self.context.take().expect("The engine was already started");
+        context.running = true;
+        context.delta = 1_f64 / self.ticks_per_second as f64;
+        if !self.resumed {
+            context.reseed(self.seed);
+        }
+
+        if let Some(ref setup) = self.setup {
+            context = setup(context)?;
+        }
+
+        context = self.systems.setup(context)?;
+
+        if let Some(scene) = self.scene.take() {
+            context = self.scenes.push(scene, context)?;
+        }
+
+        self.bus.dispatch(context)
+    }
+
+    /// Runs a single update
+    fn tick(&mut self, mut context: EngineContext<S>) -> EngineResult<S> {
+        context.input.events = match self.input {
+            Some(ref mut input) => input.poll(),
+            None => Vec::new(),
+        };
+
+        context = self.systems.update(context)?;
+
+        if let Some(ref update) = self.update {
+            context = update(context)?;
+        }
+
+        if !self.scenes.is_empty() {
+            context = self.scenes.update(context)?;
+            if self.scenes.is_empty() {
+                context.running = false;
             }
         }
+
+        context = self.bus.dispatch(context)?;
+        context.tick += 1;
+
+        Ok(context)
+    }
+
+    /// Draws a single frame
+    fn render(&mut self, mut context: EngineContext<S>) -> EngineResult<S> {
+        context = self.systems.draw(context)?;
+        context = self.scenes.draw(context)?;
+
+        match self.draw {
+            Some(ref draw) => draw(context),
+            None => Ok(context),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use character::Character;
-
+    use character::{Attribute, Character};
+    use event::{Event, EventKind};
+    use input::{Action, InputEvent, Key, ScriptedSource};
+    use item::Item;
+    use item_generator::ItemGenerator;
+    use scene::{Scene, SceneResult, Transition};
+    use system::System;
+    use world::World;
+    use world::campaign::Campaign;
+    use world::two_dimensional::World2d;
 
     #[test]
     fn workflow() {
-        let mut engine = Engine::new();
+        let engine = EngineBuilder::new()
+            .setup(|context| {
+                // Setup your game
+                let _character = Character::new("Thomas");
 
-        engine.setup(|context| {
-            // Setup your game
-            let character = Character::new("Thomas");
+                // Return the altered/non-altered context
+                Ok(context)
+            })
+            .update(|context| {
+                // Implement your update mechanics
 
-            // Return the altered/non-altered context
-            context
-        });
+                // Return the altered/non-altered context
+                Ok(EngineContext { invalid: true, ..context })
+            })
+            .draw(|context| {
+                // Implement your output
 
-        engine.update(|context| {
-            // Implement your update mechanics
+                // Return the altered/non-altered context
+                Ok(EngineContext { running: false, ..context })
+            })
+            .build()
+            .unwrap();
 
-            // Return the altered/non-altered context
-            EngineContext { invalid: true, ..context }
-        });
+        // Start the engine => run the game
+        let context = engine.start().unwrap();
 
-        engine.draw(|context| {
-            // Implement your output
+        assert!(!context.running);
+    }
 
-            // Return the altered/non-altered context
-            EngineContext { running: false, ..context }
-        });
+    #[test]
+    fn fixed_timestep() {
+        let engine = EngineBuilder::new()
+            .ticks_per_second(100)
+            .update(|context| {
+                let running = context.tick < 9;
+                assert_eq!(context.delta, 0.01);
+                Ok(EngineContext { running, ..context })
+            })
+            .build()
+            .unwrap();
 
-        // Start the engine => run the game
-        engine.start();
+        let start = Instant::now();
+        let context = engine.start().unwrap();
+
+        assert_eq!(context.tick, 10);
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn invalid_configuration() {
+        match EngineBuilder::new().build() {
+            Err(EngineError::MissingUpdate) => {}
+            _ => panic!("an engine without update behaviour was built"),
+        }
+
+        match EngineBuilder::new().update(Ok).ticks_per_second(0).build() {
+            Err(EngineError::InvalidTicksPerSecond(0)) => {}
+            _ => panic!("an engine with 0 ticks per second was built"),
+        }
+
+        match EngineBuilder::new().update(Ok).max_catch_up(0).build() {
+            Err(EngineError::InvalidMaxCatchUp(0)) => {}
+            _ => panic!("an engine with a maximum catch up of 0 was built"),
+        }
+    }
+
+    #[test]
+    fn aborted_update() {
+        let engine = EngineBuilder::new()
+            .update(|context| {
+                if context.tick == 3 {
+                    Err(context.abort("Out of cheese"))
+                } else {
+                    Ok(context)
+                }
+            })
+            .build()
+            .unwrap();
+
+        match engine.start() {
+            Err(EngineError::Aborted(context, error)) => {
+                assert_eq!(context.tick, 3);
+                assert_eq!(error.to_string(), "Out of cheese");
+            }
+            _ => panic!("the engine was not aborted"),
+        }
+    }
+
+    struct Game {
+        hero: Character,
+        campaign: Campaign<World2d>,
+        world: World2d,
+    }
+
+    #[test]
+    fn typed_state() {
+        let game = Game {
+            hero: Character::new("Thomas"),
+            campaign: Campaign::new("Adventure Time!"),
+            world: World2d::new("Hunger Game", (10, 10)).end_point((3, 0)),
+        };
+
+        let engine = EngineBuilder::with_state(game)
+            .setup(|mut context| {
+                context.state.hero.update_attribute(&Attribute::Luck, 7);
+                Ok(context)
+            })
+            .update(|mut context| {
+                context.state.world.move_to((1, 0));
+                context.running = !context.state.world.is_finished();
+                Ok(context)
+            })
+            .build()
+            .unwrap();
+
+        let mut context = engine.start().unwrap();
+
+        assert_eq!(context.tick, 3);
+        assert_eq!(context.state.hero.get_attribute_value(&Attribute::Luck), 7);
+        assert_eq!(context.state.world.get_position(), (3, 0));
+
+        context.state.campaign.add_world(context.state.world);
+    }
+
+    #[test]
+    fn scripted_input() {
+        let input = ScriptedSource::new()
+            .tick(vec![InputEvent::Key(Key::Char('d'))])
+            .tick(vec![InputEvent::Key(Key::Char('s')), InputEvent::Key(Key::Right)])
+            .idle(1)
+            .tick(vec![InputEvent::Command("quit".to_owned())]);
+
+        let engine = EngineBuilder::with_state(World2d::new("Hunger Game", (10, 10)))
+            .input(input)
+            .ticks_per_second(1000)
+            .update(|mut context| {
+                for action in context.input.actions() {
+                    if let Action::Move(movement) = action {
+                        context.state.move_to(movement);
+                    }
+                }
+
+                context.running = !context.input.commands().contains(&"quit");
+                Ok(context)
+            })
+            .build()
+            .unwrap();
+
+        let context = engine.start().unwrap();
+
+        assert_eq!(context.tick, 4);
+        assert_eq!(context.state.get_position(), (2, 1));
+    }
+
+    struct Title;
+
+    impl Scene<Vec<&'static str>> for Title {
+        fn update(&mut self, mut context: EngineContext<Vec<&'static str>>)
+                  -> SceneResult<Vec<&'static str>> {
+            context.state.push("title");
+            Ok((context, Transition::Replace(Box::new(Gameplay))))
+        }
+    }
+
+    struct Gameplay;
+
+    impl Scene<Vec<&'static str>> for Gameplay {
+        fn update(&mut self, mut context: EngineContext<Vec<&'static str>>)
+                  -> SceneResult<Vec<&'static str>> {
+            context.state.push("gameplay");
+            Ok((context, Transition::Pop))
+        }
+    }
+
+    #[test]
+    fn scenes() {
+        let engine = EngineBuilder::with_state(Vec::new())
+            .ticks_per_second(1000)
+            .scene(Title)
+            .build()
+            .unwrap();
+
+        let context = engine.start().unwrap();
+
+        assert!(!context.running);
+        assert_eq!(context.tick, 2);
+        assert_eq!(context.state, vec!["title", "gameplay"]);
+    }
+
+    struct Counter;
+
+    impl System<u32> for Counter {
+        fn name(&self) -> &str {
+            "counter"
+        }
+
+        fn update(&mut self, mut context: EngineContext<u32>) -> EngineResult<u32> {
+            context.state += 1;
+            Ok(context)
+        }
+    }
+
+    struct Referee;
+
+    impl System<u32> for Referee {
+        fn name(&self) -> &str {
+            "referee"
+        }
+
+        fn after(&self) -> Vec<&str> {
+            vec!["counter"]
+        }
+
+        fn update(&mut self, mut context: EngineContext<u32>) -> EngineResult<u32> {
+            if context.state == 2 {
+                context.disable_system("counter");
+            }
+
+            context.running = context.tick < 4;
+            Ok(context)
+        }
+    }
+
+    #[test]
+    fn systems() {
+        let engine = EngineBuilder::with_state(0)
+            .ticks_per_second(1000)
+            .system(Referee)
+            .system(Counter)
+            .build()
+            .unwrap();
+
+        let context = engine.start().unwrap();
+
+        assert_eq!(context.tick, 5);
+        assert_eq!(context.state, 2);
+    }
+
+    #[test]
+    fn event_bus() {
+        let engine = EngineBuilder::with_state(Vec::new())
+            .ticks_per_second(1000)
+            .setup(|mut context| {
+                context.events.publish(Event::Open);
+                Ok(context)
+            })
+            .update(|mut context| {
+                context.state.push(format!("update {}", context.tick));
+                if context.tick == 1 {
+                    context.events.publish(Event::Push);
+                }
+                Ok(context)
+            })
+            .subscribe(EventKind::Open, |_, mut context| {
+                context.state.push(format!("open {}", context.tick));
+                Ok(context)
+            })
+            .subscribe(EventKind::Push, |_, mut context| {
+                context.state.push(format!("push {}", context.tick));
+                context.running = false;
+                Ok(context)
+            })
+            .build()
+            .unwrap();
+
+        let context = engine.start().unwrap();
+
+        assert_eq!(context.state, vec!["open 0", "update 0", "update 1", "push 1"]);
+    }
+
+    fn loot_run(seed: u64) -> EngineContext<Vec<Item>> {
+        let input = ScriptedSource::new()
+            .tick(vec![InputEvent::Key(Key::Char('l'))])
+            .idle(3)
+            .tick(vec![InputEvent::Key(Key::Char('l'))]);
+
+        EngineBuilder::with_state(Vec::new())
+            .seed(seed)
+            .input(input)
+            .update(|mut context| {
+                for _ in context.input.events.clone() {
+                    let item = ItemGenerator::new().gen_with(&mut context.rng);
+                    context.state.push(item);
+                }
+                Ok(context)
+            })
+            .build()
+            .unwrap()
+            .run_headless(100)
+            .unwrap()
+    }
+
+    #[test]
+    fn headless_determinism() {
+        let first = loot_run(1337);
+        let second = loot_run(1337);
+
+        assert_eq!(first.tick, 100);
+        assert_eq!(first.state.len(), 2);
+        assert_eq!(first.state, second.state);
+        assert!(first.state != loot_run(7331).state);
     }
 }
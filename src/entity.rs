@@ -1,11 +1,242 @@
-use behaviour::Behaviour;
-use event::Event;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use behaviour::{self, Behaviour, BehaviourResult, SavedBehaviour, TickContext};
+use character::Character;
+use event::{Actor, Event, Message};
+use item::Item;
+
+/// A value in the state of an entity
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StateValue {
+    /// A flag, like whether a door is open
+    Bool(bool),
+    /// A number, like the amount of times an NPC was talked to
+    Int(i64),
+    /// A text, like the name of the last visitor
+    Text(String),
+    /// A list of items, like the contents of a chest
+    Items(Vec<Item>),
+}
+
+impl Encodable for StateValue {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_enum("StateValue", |e| {
+            match *self {
+                StateValue::Bool(value) => {
+                    e.emit_enum_variant("Bool", 0, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| value.encode(e))
+                    })
+                }
+                StateValue::Int(value) => {
+                    e.emit_enum_variant("Int", 1, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| value.encode(e))
+                    })
+                }
+                StateValue::Text(ref value) => {
+                    e.emit_enum_variant("Text", 2, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| value.encode(e))
+                    })
+                }
+                StateValue::Items(ref value) => {
+                    e.emit_enum_variant("Items", 3, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| value.encode(e))
+                    })
+                }
+            }
+        })
+    }
+}
+
+impl Decodable for StateValue {
+    fn decode<D: Decoder>(d: &mut D) -> Result<StateValue, D::Error> {
+        d.read_enum("StateValue", |d| {
+            d.read_enum_variant(&["Bool", "Int", "Text", "Items"], |d, index| {
+                match index {
+                    0 => d.read_enum_variant_arg(0, Decodable::decode).map(StateValue::Bool),
+                    1 => d.read_enum_variant_arg(0, Decodable::decode).map(StateValue::Int),
+                    2 => d.read_enum_variant_arg(0, Decodable::decode).map(StateValue::Text),
+                    _ => d.read_enum_variant_arg(0, Decodable::decode).map(StateValue::Items),
+                }
+            })
+        })
+    }
+}
+
+impl From<bool> for StateValue {
+    fn from(value: bool) -> StateValue {
+        StateValue::Bool(value)
+    }
+}
+
+impl From<i64> for StateValue {
+    fn from(value: i64) -> StateValue {
+        StateValue::Int(value)
+    }
+}
+
+impl<'a> From<&'a str> for StateValue {
+    fn from(value: &'a str) -> StateValue {
+        StateValue::Text(value.to_owned())
+    }
+}
+
+impl From<String> for StateValue {
+    fn from(value: String) -> StateValue {
+        StateValue::Text(value)
+    }
+}
+
+impl From<Vec<Item>> for StateValue {
+    fn from(value: Vec<Item>) -> StateValue {
+        StateValue::Items(value)
+    }
+}
+
+/// The mutable state of an entity, shared by all of its behaviours. A behaviour can remember
+/// things in it, like a door being open, and later events see the change.
+///
+/// Behaviours can also leave messages for others in it, which are delivered by the world the
+/// entity lives in. Only the values are saved and compared, not the undelivered messages.
+#[derive(Clone, Debug, Default)]
+pub struct EntityState {
+    values: BTreeMap<String, StateValue>,
+    messages: Vec<Message>,
+}
+
+impl Encodable for EntityState {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("EntityState", 1, |e| {
+            e.emit_struct_field("values", 0, |e| self.values.encode(e))
+        })
+    }
+}
+
+impl Decodable for EntityState {
+    fn decode<D: Decoder>(d: &mut D) -> Result<EntityState, D::Error> {
+        d.read_struct("EntityState", 1, |d| {
+            Ok(EntityState {
+                values: d.read_struct_field("values", 0, Decodable::decode)?,
+                messages: Vec::new(),
+            })
+        })
+    }
+}
+
+impl PartialEq for EntityState {
+    fn eq(&self, other: &EntityState) -> bool {
+        self.values == other.values
+    }
+}
+
+impl Eq for EntityState {}
+
+impl Hash for EntityState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.values.hash(state);
+    }
+}
+
+impl EntityState {
+    /// Creates a new, empty instance of `EntityState`
+    pub fn new() -> EntityState {
+        EntityState {
+            values: BTreeMap::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    /// Returns the value stored under the given key
+    pub fn get(&self, key: &str) -> Option<&StateValue> {
+        self.values.get(key)
+    }
+
+    /// Returns the flag stored under the given key, or `None` if it isn't a flag
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.values.get(key) {
+            Some(&StateValue::Bool(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the number stored under the given key, or `None` if it isn't a number
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.values.get(key) {
+            Some(&StateValue::Int(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the text stored under the given key, or `None` if it isn't a text
+    pub fn get_text(&self, key: &str) -> Option<&str> {
+        match self.values.get(key) {
+            Some(StateValue::Text(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the items stored under the given key, or `None` if they aren't items
+    pub fn get_items(&self, key: &str) -> Option<&[Item]> {
+        match self.values.get(key) {
+            Some(StateValue::Items(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Stores the value under the given key, replacing the previous one
+    pub fn set<T: Into<StateValue>>(&mut self, key: &str, value: T) {
+        self.values.insert(key.to_owned(), value.into());
+    }
+
+    /// Removes the value stored under the given key and returns it
+    pub fn remove(&mut self, key: &str) -> Option<StateValue> {
+        self.values.remove(key)
+    }
+
+    /// Returns `true` if a value is stored under the given key
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// Inverts the flag stored under the given key and returns the new value. A missing flag
+    /// counts as `false`.
+    pub fn toggle(&mut self, key: &str) -> bool {
+        let value = !self.get_bool(key).unwrap_or(false);
+        self.set(key, value);
+        value
+    }
+
+    /// Leaves an event for the entities with the given name
+    pub fn send(&mut self, target: &str, event: Event) {
+        self.send_to(Actor::Entity(target.to_owned()), event);
+    }
+
+    /// Leaves an event for the given actor, like the player
+    pub fn send_to(&mut self, target: Actor, event: Event) {
+        self.messages.push(Message::new(Actor::Game, target, event));
+    }
+
+    /// Leaves an event for everyone
+    pub fn broadcast(&mut self, event: Event) {
+        self.messages.push(Message::broadcast(Actor::Game, event));
+    }
+
+    /// Removes the messages left for others and returns them. Their source is `Actor::Game`,
+    /// use `Entity::take_messages` to get them with the entity as their source.
+    pub fn take_messages(&mut self) -> Vec<Message> {
+        self.messages.drain(..).collect()
+    }
+}
 
 /// Any non-character element
 #[derive(Clone)]
 pub struct Entity {
     name: String,
-    behaviour: Vec<Box<Behaviour>>,
+    behaviour: Vec<Box<dyn Behaviour>>,
+    state: EntityState,
 }
 
 impl Entity {
@@ -14,35 +245,170 @@ impl Entity {
         Entity {
             name: name.to_owned(),
             behaviour: Vec::new(),
+            state: EntityState::new(),
         }
     }
 
+    /// Returns the name of the entity
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the state of the entity
+    pub fn state(&self) -> &EntityState {
+        &self.state
+    }
+
+    /// Returns the state of the entity mutably
+    pub fn state_mut(&mut self) -> &mut EntityState {
+        &mut self.state
+    }
+
+    /// Removes the messages the behaviours left for others and returns them with the entity as
+    /// their source
+    pub fn take_messages(&mut self) -> Vec<Message> {
+        let mut messages = self.state.take_messages();
+        for message in &mut messages {
+            message.source = Actor::Entity(self.name.clone());
+        }
+        messages
+    }
+
     /// Adds a behaviour ot the behaviour chain of the entity
     pub fn append_behaviour<T: Behaviour + 'static>(&mut self, behaviour: T) {
         self.behaviour.push(Box::new(behaviour));
     }
 
-    /// Sends and event to the entity
-    pub fn send_event(&self, event: Event) -> Event {
-        self.handle_event(event)
+    /// Returns `true` if every behaviour of the entity can be saved, see `Behaviour::save`
+    pub fn can_be_saved(&self) -> bool {
+        self.behaviour.iter().all(|behaviour| behaviour.save().is_some())
     }
-}
 
-impl Behaviour for Entity {
-    fn handle_event(&self, event: Event) -> Event {
-        let mut last_event = Event::Nothing;
-        let mut first_run = true;
+    /// Runs the behaviour chain of the entity. The first behaviour handling or stopping the
+    /// event ends the chain. If the event was only transformed, the transformed event is
+    /// returned.
+    pub fn handle_event(&mut self, event: &Event) -> BehaviourResult {
+        self.run_chain(event, |behaviour, event, state| behaviour.handle_event(event, state))
+    }
 
-        for ref behaviour in &self.behaviour {
-            if first_run {
-                first_run = false;
-                last_event = behaviour.handle_event(event.clone());
-            } else {
-                last_event = behaviour.handle_event(last_event);
+    /// Runs the behaviour chain of the entity for an event sent by the player. Works like
+    /// `handle_event`, but the behaviours get access to the character of the player.
+    pub fn handle_player_event(&mut self,
+                               event: &Event,
+                               character: &mut Character)
+                               -> BehaviourResult {
+        self.run_chain(event, |behaviour, event, state| {
+            behaviour.handle_player_event(event, state, character)
+        })
+    }
+
+    fn run_chain<F>(&mut self, event: &Event, mut handle: F) -> BehaviourResult
+        where F: FnMut(&dyn Behaviour, &Event, &mut EntityState) -> BehaviourResult
+    {
+        let mut current: Option<Event> = None;
+
+        for behaviour in &self.behaviour {
+            let event = current.as_ref().unwrap_or(event);
+            if !behaviour.handles(event.kind()) {
+                continue;
             }
+
+            match handle(behaviour.as_ref(), event, &mut self.state) {
+                BehaviourResult::Pass => {}
+                BehaviourResult::Transform(transformed) => current = Some(transformed),
+                result => return result,
+            }
+        }
+
+        match current {
+            Some(transformed) => BehaviourResult::Transform(transformed),
+            None => BehaviourResult::Pass,
+        }
+    }
+
+    /// Ticks all behaviours of the entity
+    pub fn tick(&mut self, context: &mut TickContext) {
+        for behaviour in &self.behaviour {
+            behaviour.tick(context, &mut self.state);
+        }
+    }
+
+    /// Sends and event to the entity and returns its response. Returns `Event::Nothing` if no
+    /// behaviour handled the event.
+    pub fn send_event(&mut self, event: Event) -> Event {
+        match self.handle_event(&event) {
+            BehaviourResult::Handled(response) => response,
+            _ => Event::Nothing,
+        }
+    }
+
+    /// Sends an event of the player to the entity and returns its response. Returns
+    /// `Event::Nothing` if no behaviour handled the event.
+    pub fn send_player_event(&mut self, event: Event, character: &mut Character) -> Event {
+        match self.handle_player_event(&event, character) {
+            BehaviourResult::Handled(response) => response,
+            _ => Event::Nothing,
         }
+    }
+}
+
+thread_local! {
+    // Encoders can't be given an error of our own, so the entities encoded without some of
+    // their behaviours are collected instead and `save::encode` refuses to save them
+    static UNSAVEABLE: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Removes the names of the entities that were encoded without some of their behaviours on this
+/// thread and returns them
+pub(crate) fn take_unsaveable() -> Vec<String> {
+    UNSAVEABLE.with(|unsaveable| unsaveable.borrow_mut().split_off(0))
+}
+
+/// The behaviours are saved by their tag and restored using the behaviours registered with
+/// `behaviour::register_behaviour`. Behaviours that can't be saved are left out, which is why
+/// `save::encode` refuses to save entities having one, see `Entity::can_be_saved`.
+impl Encodable for Entity {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        let behaviours: Vec<SavedBehaviour> =
+            self.behaviour.iter().filter_map(|behaviour| behaviour.save()).collect();
+
+        if behaviours.len() < self.behaviour.len() {
+            UNSAVEABLE.with(|unsaveable| unsaveable.borrow_mut().push(self.name.clone()));
+        }
+
+        e.emit_struct("Entity", 3, |e| {
+            e.emit_struct_field("name", 0, |e| self.name.encode(e))?;
+            e.emit_struct_field("behaviours", 1, |e| behaviours.encode(e))?;
+            e.emit_struct_field("state", 2, |e| self.state.encode(e))
+        })
+    }
+}
+
+impl Decodable for Entity {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Entity, D::Error> {
+        d.read_struct("Entity", 3, |d| {
+            let name: String = d.read_struct_field("name", 0, Decodable::decode)?;
+            let behaviours: Vec<SavedBehaviour> =
+                d.read_struct_field("behaviours", 1, Decodable::decode)?;
 
-        last_event
+            let mut entity = Entity::new(&name);
+            entity.state = d.read_struct_field("state", 2, Decodable::decode)?;
+            for saved in behaviours {
+                let behaviour = behaviour::load_behaviour(&saved)
+                    .map_err(|error| d.error(&error.to_string()))?;
+                entity.behaviour.push(behaviour);
+            }
+
+            Ok(entity)
+        })
+    }
+}
+
+/// Only the name and the state of an entity are hashed, its behaviours are skipped
+impl Hash for Entity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.state.hash(state);
     }
 }
 
@@ -50,7 +416,9 @@ impl Behaviour for Entity {
 mod tests {
     use super::*;
     use behaviour::DefaultResponse;
-    use event::Event;
+    use event::{Event, EventKind};
+    use item::{Item, ItemRarity, ItemType};
+    use rustc_serialize::json;
 
     #[test]
     fn append_behaviour() {
@@ -86,6 +454,184 @@ mod tests {
 
         let res = entity.send_event(Event::Nothing);
 
-        assert_eq!(res, Event::Tell("Response 2!".to_owned()));
+        assert_eq!(res, Event::Tell("Response 1!".to_owned()));
+    }
+
+    /// Opens when given the key, stays shut otherwise
+    #[derive(Clone)]
+    struct Lock {
+        key: String,
+    }
+
+    impl Behaviour for Lock {
+        fn handle_event(&self, event: &Event, _: &mut EntityState) -> BehaviourResult {
+            match *event {
+                Event::Give(ref item) if item.name == self.key => {
+                    BehaviourResult::Transform(Event::Open)
+                }
+                Event::Give(_) => BehaviourResult::Stop,
+                _ => BehaviourResult::Handled(Event::Tell("It's locked.".to_owned())),
+            }
+        }
+
+        fn handles(&self, kind: EventKind) -> bool {
+            kind == EventKind::Open || kind == EventKind::Give
+        }
+    }
+
+    #[derive(Clone)]
+    struct Chest;
+
+    impl Behaviour for Chest {
+        fn handle_event(&self, event: &Event, _: &mut EntityState) -> BehaviourResult {
+            match *event {
+                Event::Open => BehaviourResult::Handled(Event::Tell("The chest opens.".to_owned())),
+                _ => BehaviourResult::Pass,
+            }
+        }
+    }
+
+    #[test]
+    fn locked_chest() {
+        let key = Item {
+            name: "Rusty key".to_owned(),
+            item_type: ItemType::Usable,
+            influence: None,
+            stack_size: 1,
+            rarity: ItemRarity::Common,
+        };
+        let stone = Item { name: "Stone".to_owned(), ..key.clone() };
+
+        let mut chest = Entity::new("Chest");
+        chest.append_behaviour(Lock { key: key.name.clone() });
+        chest.append_behaviour(Chest);
+        chest.append_behaviour(DefaultResponse::new("A wooden chest."));
+
+        assert_eq!(chest.send_event(Event::Open), Event::Tell("It's locked.".to_owned()));
+        assert_eq!(chest.send_event(Event::Give(stone)), Event::Nothing);
+        assert_eq!(chest.send_event(Event::Give(key)),
+                   Event::Tell("The chest opens.".to_owned()));
+        assert_eq!(chest.send_event(Event::Push), Event::Tell("A wooden chest.".to_owned()));
+    }
+
+    #[test]
+    fn unhandled_events() {
+        let mut entity = Entity::new("TestSubject");
+
+        assert_eq!(entity.handle_event(&Event::Push), BehaviourResult::Pass);
+
+        entity.append_behaviour(Chest);
+        entity.append_behaviour(Lock { key: "Key".to_owned() });
+
+        assert_eq!(entity.handle_event(&Event::Push), BehaviourResult::Pass);
+    }
+
+    /// Remembers whether it was opened
+    #[derive(Clone)]
+    struct Hatch;
+
+    impl Behaviour for Hatch {
+        fn handle_event(&self, event: &Event, state: &mut EntityState) -> BehaviourResult {
+            let open = state.get_bool("open").unwrap_or(false);
+            let response = match *event {
+                Event::Open if open => "The hatch is already open.",
+                Event::Close if !open => "The hatch is already closed.",
+                _ => {
+                    state.toggle("open");
+                    "Done."
+                }
+            };
+
+            BehaviourResult::Handled(Event::Tell(response.to_owned()))
+        }
+
+        fn handles(&self, kind: EventKind) -> bool {
+            kind == EventKind::Open || kind == EventKind::Close
+        }
+    }
+
+    #[test]
+    fn stateful_behaviour() {
+        let mut hatch = Entity::new("Hatch");
+        hatch.append_behaviour(Hatch);
+
+        assert_eq!(hatch.send_event(Event::Open), Event::Tell("Done.".to_owned()));
+        assert_eq!(hatch.state().get_bool("open"), Some(true));
+        assert_eq!(hatch.send_event(Event::Open),
+                   Event::Tell("The hatch is already open.".to_owned()));
+        assert_eq!(hatch.send_event(Event::Close), Event::Tell("Done.".to_owned()));
+        assert_eq!(hatch.send_event(Event::Close),
+                   Event::Tell("The hatch is already closed.".to_owned()));
+    }
+
+    #[test]
+    fn entity_state() {
+        let mut state = EntityState::new();
+
+        state.set("name", "Bob");
+        state.set("gold", 12);
+
+        assert_eq!(state.get_text("name"), Some("Bob"));
+        assert_eq!(state.get_int("gold"), Some(12));
+        assert_eq!(state.get_bool("gold"), None);
+        assert!(state.toggle("angry"));
+        assert!(!state.toggle("angry"));
+        assert_eq!(state.remove("gold"), Some(StateValue::Int(12)));
+        assert!(!state.contains("gold"));
+
+        state.send("Gate", Event::Open);
+
+        assert_eq!(state.take_messages(),
+                   vec![Message::new(Actor::Game, Actor::Entity("Gate".to_owned()), Event::Open)]);
+        assert!(state.take_messages().is_empty());
+    }
+
+    #[derive(Clone)]
+    struct Silent;
+
+    impl Behaviour for Silent {
+        fn handle_event(&self, _: &Event, _: &mut EntityState) -> BehaviourResult {
+            BehaviourResult::Stop
+        }
+    }
+
+    #[test]
+    fn save_behaviours() {
+        let mut entity = Entity::new("TestSubject");
+
+        entity.append_behaviour(DefaultResponse::new("Response 1!"));
+        entity.append_behaviour(DefaultResponse::new("Response 2!"));
+
+        entity.state_mut().set("visits", 3);
+
+        let mut loaded: Entity = json::decode(&json::encode(&entity).unwrap()).unwrap();
+
+        assert!(entity.can_be_saved());
+        assert_eq!(loaded.name, "TestSubject");
+        assert_eq!(loaded.behaviour.len(), 2);
+        assert_eq!(loaded.state().get_int("visits"), Some(3));
+        assert_eq!(loaded.send_event(Event::Nothing), Event::Tell("Response 1!".to_owned()));
+
+        let unknown = r#"{"name":"TestSubject","behaviours":[{"tag":"unknown","data":"null"}],
+                          "state":{"values":{}}}"#;
+        match json::decode::<Entity>(unknown) {
+            Err(json::DecoderError::ApplicationError(error)) => {
+                assert_eq!(error, "unknown behaviour \"unknown\"")
+            }
+            _ => panic!("an entity with an unknown behaviour was loaded"),
+        }
+    }
+
+    #[test]
+    fn save_unsaveable_behaviour() {
+        let mut entity = Entity::new("TestSubject");
+
+        entity.append_behaviour(DefaultResponse::new("Response 1!"));
+        entity.append_behaviour(Silent);
+
+        assert!(!entity.can_be_saved());
+        assert!(json::encode(&entity).is_ok());
+        assert_eq!(take_unsaveable(), vec!["TestSubject"]);
+        assert!(take_unsaveable().is_empty());
     }
 }
@@ -1,7 +1,8 @@
 use item::Item;
+use types::Health;
 
 /// A primitive event type
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Event {
     /// Talk to an entity
     Tell(String),
@@ -17,6 +18,157 @@ pub enum Event {
     Open,
     /// Close an entity
     Close,
+    /// Attack an entity
+    Attack,
+    /// Use an item on an entity
+    Use(Item),
+    /// Take the item with the given name from an entity
+    Take(String),
+    /// Look at an entity
+    Look,
+    /// The player entered the field at the given position
+    Enter((usize, usize)),
+    /// The player left the field at the given position
+    Leave((usize, usize)),
+    /// Deal the given amount of damage
+    Damage(Health),
+    /// Heal the given amount of health
+    Heal(Health),
+    /// An event defined by the game, consisting of a name and arbitrary data
+    Custom(String, String),
     /// Do nothing
     Nothing,
 }
+
+impl Event {
+    /// Returns the kind of the event
+    pub fn kind(&self) -> EventKind {
+        match *self {
+            Event::Tell(_) => EventKind::Tell,
+            Event::Give(_) => EventKind::Give,
+            Event::Options(_) => EventKind::Options,
+            Event::Push => EventKind::Push,
+            Event::Pull => EventKind::Pull,
+            Event::Open => EventKind::Open,
+            Event::Close => EventKind::Close,
+            Event::Attack => EventKind::Attack,
+            Event::Use(_) => EventKind::Use,
+            Event::Take(_) => EventKind::Take,
+            Event::Look => EventKind::Look,
+            Event::Enter(_) => EventKind::Enter,
+            Event::Leave(_) => EventKind::Leave,
+            Event::Damage(_) => EventKind::Damage,
+            Event::Heal(_) => EventKind::Heal,
+            Event::Custom(..) => EventKind::Custom,
+            Event::Nothing => EventKind::Nothing,
+        }
+    }
+}
+
+/// The kind of an event without its payload. Used to subscribe to certain events
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Tell,
+    Give,
+    Options,
+    Push,
+    Pull,
+    Open,
+    Close,
+    Attack,
+    Use,
+    Take,
+    Look,
+    Enter,
+    Leave,
+    Damage,
+    Heal,
+    Custom,
+    Nothing,
+}
+
+impl_serialize_enum!(EventKind {
+    Tell,
+    Give,
+    Options,
+    Push,
+    Pull,
+    Open,
+    Close,
+    Attack,
+    Use,
+    Take,
+    Look,
+    Enter,
+    Leave,
+    Damage,
+    Heal,
+    Custom,
+    Nothing,
+});
+
+/// Someone sending or receiving events
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Actor {
+    /// The game itself, like a system or a scene
+    Game,
+    /// The character of the player
+    Player,
+    /// The entities with the given name
+    Entity(String),
+    /// The world with the given name
+    World(String),
+}
+
+/// An event together with the one who sent it and the one who should receive it
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Message {
+    /// The sender of the event
+    pub source: Actor,
+    /// The receiver of the event, or `None` if it is meant for everyone
+    pub target: Option<Actor>,
+    /// The event itself
+    pub event: Event,
+}
+
+impl Message {
+    /// Creates a new instance of `Message` sent to the given target
+    pub fn new(source: Actor, target: Actor, event: Event) -> Message {
+        Message {
+            source,
+            target: Some(target),
+            event,
+        }
+    }
+
+    /// Creates a new instance of `Message` meant for everyone
+    pub fn broadcast(source: Actor, event: Event) -> Message {
+        Message {
+            source,
+            target: None,
+            event,
+        }
+    }
+
+    /// Returns `true` if the message is meant for the given actor
+    pub fn is_for(&self, actor: &Actor) -> bool {
+        self.target.as_ref().is_none_or(|target| target == actor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_targets() {
+        let attack = Message::new(Actor::Entity("Wolf".to_owned()), Actor::Player, Event::Attack);
+        let cry = Message::broadcast(Actor::Entity("Wolf".to_owned()), Event::Tell("Awoo".into()));
+
+        assert!(attack.is_for(&Actor::Player));
+        assert!(!attack.is_for(&Actor::Entity("Sheep".to_owned())));
+        assert!(cry.is_for(&Actor::Entity("Sheep".to_owned())));
+        assert_eq!(attack.event.kind(), EventKind::Attack);
+    }
+}
@@ -0,0 +1,158 @@
+use std::mem;
+
+use engine::{EngineContext, EngineResult};
+use event::{Actor, Event, EventKind, Message};
+
+/// The messages published during the current update. They are dispatched to the subscribers
+/// of the `EventBus` at the end of the update.
+#[derive(Clone, Debug, Default)]
+pub struct EventQueue {
+    events: Vec<Message>,
+}
+
+impl EventQueue {
+    /// Creates a new, empty instance of `EventQueue`
+    pub fn new() -> EventQueue {
+        EventQueue { events: Vec::new() }
+    }
+
+    /// Queues the given event as a message of the game meant for everyone
+    pub fn publish(&mut self, event: Event) {
+        self.events.push(Message::broadcast(Actor::Game, event));
+    }
+
+    /// Queues the given message
+    pub fn send(&mut self, message: Message) {
+        self.events.push(message);
+    }
+
+    /// Returns the amount of queued messages
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns `true` if no messages are queued
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Removes all queued messages and returns them in the order they were published
+    pub fn take(&mut self) -> Vec<Message> {
+        mem::take(&mut self.events)
+    }
+}
+
+/// A function reacting to published messages
+pub type Subscriber<S = ()> = Box<dyn Fn(&Message, EngineContext<S>) -> EngineResult<S>>;
+
+/// Dispatches the messages published on `context.events` to everyone who subscribed to their
+/// events
+pub struct EventBus<S = ()> {
+    subscribers: Vec<(Option<EventKind>, Subscriber<S>)>,
+}
+
+impl<S> EventBus<S> {
+    /// Creates a new instance of `EventBus` without any subscribers
+    pub fn new() -> EventBus<S> {
+        EventBus { subscribers: Vec::new() }
+    }
+
+    /// Subscribes to all events of the given kind
+    pub fn subscribe<T>(&mut self, kind: EventKind, subscriber: T)
+        where T: Fn(&Message, EngineContext<S>) -> EngineResult<S> + 'static
+    {
+        self.subscribers.push((Some(kind), Box::new(subscriber)));
+    }
+
+    /// Subscribes to every event
+    pub fn subscribe_all<T>(&mut self, subscriber: T)
+        where T: Fn(&Message, EngineContext<S>) -> EngineResult<S> + 'static
+    {
+        self.subscribers.push((None, Box::new(subscriber)));
+    }
+
+    /// Dispatches all queued messages in the order they were published. Every message is passed
+    /// to its subscribers in the order they subscribed. Messages published by the subscribers
+    /// are queued for the next dispatch.
+    pub fn dispatch(&self, mut context: EngineContext<S>) -> EngineResult<S> {
+        for message in context.events.take() {
+            let kind = message.event.kind();
+            for (filter, subscriber) in &self.subscribers {
+                if filter.is_none_or(|filter| filter == kind) {
+                    context = subscriber(&message, context)?;
+                }
+            }
+        }
+
+        Ok(context)
+    }
+}
+
+impl<S> Default for EventBus<S> {
+    fn default() -> EventBus<S> {
+        EventBus::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use behaviour::DefaultResponse;
+    use engine::EngineContext;
+    use entity::Entity;
+    use event::{Actor, Event, EventKind, Message};
+
+    #[test]
+    fn dispatch_by_kind() {
+        let mut bus = EventBus::new();
+
+        bus.subscribe(EventKind::Open, |_, mut context: EngineContext<Vec<String>>| {
+            context.state.push("door".to_owned());
+            context.events.publish(Event::Close);
+            Ok(context)
+        });
+        bus.subscribe_all(|message, mut context: EngineContext<Vec<String>>| {
+            context.state.push(format!("log {:?}", message.event.kind()));
+            Ok(context)
+        });
+
+        let mut context = EngineContext::new(Vec::new());
+        context.events.publish(Event::Open);
+        context.events.publish(Event::Push);
+        context = bus.dispatch(context).unwrap();
+
+        assert_eq!(context.state, vec!["door", "log Open", "log Push"]);
+        assert_eq!(context.events.len(), 1);
+
+        context = bus.dispatch(context).unwrap();
+
+        assert_eq!(context.state.last().unwrap(), "log Close");
+        assert!(context.events.is_empty());
+    }
+
+    #[test]
+    fn entity_subscriber() {
+        let mut guard = Entity::new("Guard");
+        guard.append_behaviour(DefaultResponse::new("Halt!"));
+
+        let mut bus = EventBus::new();
+        bus.subscribe(EventKind::Tell,
+                      |message, mut context: EngineContext<(Entity, Vec<Event>)>| {
+            if message.is_for(&Actor::Entity(context.state.0.name().to_owned())) {
+                let response = context.state.0.send_event(message.event.clone());
+                context.state.1.push(response);
+            }
+            Ok(context)
+        });
+
+        let mut context = EngineContext::new((guard, Vec::new()));
+        context.events.publish(Event::Tell("Hello".to_owned()));
+        context.events.publish(Event::Open);
+        context.events.send(Message::new(Actor::Player,
+                                         Actor::Entity("Merchant".to_owned()),
+                                         Event::Tell("Hi".to_owned())));
+        context = bus.dispatch(context).unwrap();
+
+        assert_eq!(context.state.1, vec![Event::Tell("Halt!".to_owned())]);
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, Read};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// A key pressed by the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// A printable character
+    Char(char),
+    /// The arrow key pointing up
+    Up,
+    /// The arrow key pointing down
+    Down,
+    /// The arrow key pointing left
+    Left,
+    /// The arrow key pointing right
+    Right,
+    /// The enter or return key
+    Enter,
+    /// The escape key
+    Escape,
+    /// The backspace key
+    Backspace,
+    /// The tab key
+    Tab,
+}
+
+/// A single piece of input of the player
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    /// A single key was pressed
+    Key(Key),
+    /// A whole command was entered
+    Command(String),
+}
+
+/// An action in the game a key can be bound to
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Move the player by the given delta. Can be passed to `World::move_to` of a `World2d`
+    Move((i64, i64)),
+    /// Confirm a choice
+    Confirm,
+    /// Cancel a choice or leave a screen
+    Cancel,
+    /// A game specific action
+    Custom(String),
+}
+
+/// A source of input events. The engine polls it once per update.
+pub trait InputSource {
+    /// Returns all events that arrived since the last poll. Must not block.
+    fn poll(&mut self) -> Vec<InputEvent>;
+}
+
+/// Reads whole lines from the standard input. Every line is turned into an
+/// `InputEvent::Command`.
+pub struct StdinLineSource {
+    receiver: Receiver<String>,
+}
+
+impl StdinLineSource {
+    /// Creates a new instance of `StdinLineSource` and starts reading from the standard input
+    pub fn new() -> StdinLineSource {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            for line in stdin.lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+
+                if sender.send(line.trim().to_owned()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        StdinLineSource { receiver }
+    }
+}
+
+impl Default for StdinLineSource {
+    fn default() -> StdinLineSource {
+        StdinLineSource::new()
+    }
+}
+
+impl InputSource for StdinLineSource {
+    fn poll(&mut self) -> Vec<InputEvent> {
+        self.receiver.try_iter().map(InputEvent::Command).collect()
+    }
+}
+
+/// Reads single keys from the standard input. Every key is turned into an `InputEvent::Key`.
+///
+/// The terminal has to be switched into raw mode by the game, otherwise the keys only arrive
+/// after the player pressed enter.
+pub struct TerminalKeySource {
+    receiver: Receiver<Key>,
+}
+
+impl TerminalKeySource {
+    /// Creates a new instance of `TerminalKeySource` and starts reading from the standard input
+    pub fn new() -> TerminalKeySource {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            let mut buffer = [0; 64];
+            loop {
+                let read = match stdin.lock().read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => read,
+                };
+
+                for key in decode_keys(&buffer[..read]) {
+                    if sender.send(key).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        TerminalKeySource { receiver }
+    }
+}
+
+impl Default for TerminalKeySource {
+    fn default() -> TerminalKeySource {
+        TerminalKeySource::new()
+    }
+}
+
+impl InputSource for TerminalKeySource {
+    fn poll(&mut self) -> Vec<InputEvent> {
+        self.receiver.try_iter().map(InputEvent::Key).collect()
+    }
+}
+
+/// Decodes the raw bytes sent by a terminal into keys. Escape sequences of the arrow keys are
+/// only recognized if they are contained in `bytes` as a whole.
+pub fn decode_keys(bytes: &[u8]) -> Vec<Key> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    let mut keys = Vec::new();
+
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\u{1b}' => {
+                if chars.peek() == Some(&'[') {
+                    chars.next();
+                    match chars.next() {
+                        Some('A') => Key::Up,
+                        Some('B') => Key::Down,
+                        Some('C') => Key::Right,
+                        Some('D') => Key::Left,
+                        _ => continue,
+                    }
+                } else {
+                    Key::Escape
+                }
+            }
+            '\r' | '\n' => Key::Enter,
+            '\t' => Key::Tab,
+            '\u{7f}' | '\u{8}' => Key::Backspace,
+            c => Key::Char(c),
+        };
+
+        keys.push(key);
+    }
+
+    keys
+}
+
+/// An input source replaying a fixed script. Every poll returns the events of the next update,
+/// once the script is exhausted no more events are returned. Mostly useful for tests.
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::input::{InputEvent, InputSource, Key, ScriptedSource};
+/// let mut source = ScriptedSource::new()
+///     .tick(vec![InputEvent::Key(Key::Char('w'))])
+///     .idle(2)
+///     .tick(vec![InputEvent::Command("quit".to_owned())]);
+///
+/// assert_eq!(source.poll(), vec![InputEvent::Key(Key::Char('w'))]);
+/// assert!(source.poll().is_empty());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedSource {
+    ticks: VecDeque<Vec<InputEvent>>,
+}
+
+impl ScriptedSource {
+    /// Creates a new, empty instance of `ScriptedSource`
+    pub fn new() -> ScriptedSource {
+        ScriptedSource { ticks: VecDeque::new() }
+    }
+
+    /// Appends the events of a single update to the script
+    pub fn tick(mut self, events: Vec<InputEvent>) -> ScriptedSource {
+        self.ticks.push_back(events);
+        self
+    }
+
+    /// Appends the given amount of updates without any events to the script
+    pub fn idle(mut self, ticks: usize) -> ScriptedSource {
+        for _ in 0..ticks {
+            self.ticks.push_back(Vec::new());
+        }
+        self
+    }
+
+    /// Returns `true` if all scripted events have been polled
+    pub fn is_exhausted(&self) -> bool {
+        self.ticks.is_empty()
+    }
+}
+
+impl InputSource for ScriptedSource {
+    fn poll(&mut self) -> Vec<InputEvent> {
+        self.ticks.pop_front().unwrap_or_default()
+    }
+}
+
+/// A map from keys to the actions of the game
+#[derive(Clone, Debug)]
+pub struct KeyBindings {
+    bindings: HashMap<Key, Action>,
+}
+
+impl KeyBindings {
+    /// Creates a new instance of `KeyBindings` without any bindings
+    pub fn new() -> KeyBindings {
+        KeyBindings { bindings: HashMap::new() }
+    }
+
+    /// Binds the given key to an action, replacing the previous binding of the key
+    pub fn bind(&mut self, key: Key, action: Action) {
+        self.bindings.insert(key, action);
+    }
+
+    /// Removes the binding of the given key
+    pub fn unbind(&mut self, key: &Key) {
+        self.bindings.remove(key);
+    }
+
+    /// Returns the action bound to the given key
+    pub fn action(&self, key: &Key) -> Option<&Action> {
+        self.bindings.get(key)
+    }
+}
+
+impl Default for KeyBindings {
+    /// Binds `WASD` and the arrow keys to movement, enter to `Action::Confirm` and escape to
+    /// `Action::Cancel`
+    fn default() -> KeyBindings {
+        let mut bindings = KeyBindings::new();
+
+        bindings.bind(Key::Char('w'), Action::Move((0, -1)));
+        bindings.bind(Key::Char('a'), Action::Move((-1, 0)));
+        bindings.bind(Key::Char('s'), Action::Move((0, 1)));
+        bindings.bind(Key::Char('d'), Action::Move((1, 0)));
+        bindings.bind(Key::Up, Action::Move((0, -1)));
+        bindings.bind(Key::Left, Action::Move((-1, 0)));
+        bindings.bind(Key::Down, Action::Move((0, 1)));
+        bindings.bind(Key::Right, Action::Move((1, 0)));
+        bindings.bind(Key::Enter, Action::Confirm);
+        bindings.bind(Key::Escape, Action::Cancel);
+
+        bindings
+    }
+}
+
+/// The input of the player during a single update
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    /// The events that arrived since the last update
+    pub events: Vec<InputEvent>,
+    /// The key bindings used to turn the events into actions
+    pub bindings: KeyBindings,
+}
+
+impl Input {
+    /// Returns the actions bound to the keys pressed since the last update. Unbound keys are
+    /// skipped.
+    pub fn actions(&self) -> Vec<Action> {
+        self.events
+            .iter()
+            .filter_map(|event| match *event {
+                InputEvent::Key(ref key) => self.bindings.action(key).cloned(),
+                InputEvent::Command(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns the commands entered since the last update
+    pub fn commands(&self) -> Vec<&str> {
+        self.events
+            .iter()
+            .filter_map(|event| match *event {
+                InputEvent::Command(ref command) => Some(command.as_str()),
+                InputEvent::Key(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_source() {
+        let mut source = ScriptedSource::new()
+            .tick(vec![InputEvent::Key(Key::Up), InputEvent::Key(Key::Enter)])
+            .idle(1)
+            .tick(vec![InputEvent::Command("look".to_owned())]);
+
+        assert_eq!(source.poll().len(), 2);
+        assert!(source.poll().is_empty());
+        assert_eq!(source.poll(), vec![InputEvent::Command("look".to_owned())]);
+        assert!(source.is_exhausted());
+        assert!(source.poll().is_empty());
+    }
+
+    #[test]
+    fn decode_terminal_keys() {
+        assert_eq!(decode_keys(b"w\x1b[A\x1b[D\r\x1b"),
+                   vec![Key::Char('w'), Key::Up, Key::Left, Key::Enter, Key::Escape]);
+    }
+
+    #[test]
+    fn key_bindings() {
+        let mut input = Input::default();
+
+        input.bindings.bind(Key::Char('i'), Action::Custom("inventory".to_owned()));
+        input.bindings.unbind(&Key::Char('a'));
+        input.events = vec![InputEvent::Key(Key::Char('d')),
+                            InputEvent::Key(Key::Char('a')),
+                            InputEvent::Command("help".to_owned()),
+                            InputEvent::Key(Key::Char('i'))];
+
+        assert_eq!(input.actions(),
+                   vec![Action::Move((1, 0)), Action::Custom("inventory".to_owned())]);
+        assert_eq!(input.commands(), vec!["help"]);
+    }
+}
@@ -2,26 +2,34 @@ use item::Item;
 use types::Gold;
 
 /// A single slot of the inventory
-#[derive(Debug)]
+#[derive(Debug, Hash)]
 pub struct InventorySlot {
     item: Item,
     amount: usize,
 }
 
+impl_serialize_struct!(InventorySlot { item, amount });
+
 /// An inventory holding items
-#[derive(Debug)]
+#[derive(Debug, Hash)]
 pub struct Inventory {
     contents: Vec<InventorySlot>,
     gold: Gold,
     max_size: usize,
 }
 
+impl_serialize_struct!(Inventory {
+    contents,
+    gold,
+    max_size,
+});
+
 impl Inventory {
     /// Creates a new instance of `Inventory`
     pub fn new(max_size: usize) -> Inventory {
         Inventory {
             contents: Vec::new(),
-            max_size: max_size,
+            max_size,
             gold: 0,
         }
     }
@@ -30,11 +38,9 @@ impl Inventory {
     /// inventory and a `Err(Item)` is returned.
     pub fn add_item(&mut self, new_item: Item) -> Result<(), Item> {
         for slot in &mut self.contents {
-            if slot.item == new_item {
-                if slot.item.stack_size > slot.amount {
-                    slot.amount += 1;
-                    return Ok(());
-                }
+            if slot.item == new_item && slot.item.stack_size > slot.amount {
+                slot.amount += 1;
+                return Ok(());
             }
         }
 
@@ -50,6 +56,48 @@ impl Inventory {
         Ok(())
     }
 
+    /// Returns `true` if the inventory contains an item with the given name
+    pub fn contains(&self, name: &str) -> bool {
+        self.contents.iter().any(|slot| slot.item.name == name)
+    }
+
+    /// Returns the first item with the given name
+    pub fn get_item(&self, name: &str) -> Option<&Item> {
+        self.contents.iter().find(|slot| slot.item.name == name).map(|slot| &slot.item)
+    }
+
+    /// Removes a single item with the given name from the inventory
+    pub fn remove_item(&mut self, name: &str) -> Option<Item> {
+        let index = self.contents.iter().position(|slot| slot.item.name == name)?;
+        self.contents[index].amount -= 1;
+        if self.contents[index].amount == 0 {
+            Some(self.contents.remove(index).item)
+        } else {
+            Some(self.contents[index].item.clone())
+        }
+    }
+
+    /// Returns the amount of gold in the inventory
+    pub fn gold(&self) -> Gold {
+        self.gold
+    }
+
+    /// Adds the given amount of gold to the inventory
+    pub fn add_gold(&mut self, gold: Gold) {
+        self.gold += gold;
+    }
+
+    /// Removes the given amount of gold from the inventory. If there is not enough gold, nothing
+    /// is removed and `false` is returned.
+    pub fn remove_gold(&mut self, gold: Gold) -> bool {
+        if self.gold < gold {
+            return false;
+        }
+
+        self.gold -= gold;
+        true
+    }
+
     /// Returns `true` it the inventory is full
     pub fn is_full(&self) -> bool {
         self.contents.len() == self.max_size
@@ -69,7 +117,7 @@ mod tests {
         for _ in 0..40 {
             let random_item =
                 item_generator::ItemGenerator::new().item_type(ItemType::ArmorHead).gen();
-            if let Err(_) = inv_1.add_item(random_item) {
+            if inv_1.add_item(random_item).is_err() {
                 assert!(inv_1.is_full());
             }
         }
@@ -93,4 +141,24 @@ mod tests {
 
         assert_eq!(inv.contents[1].amount, random_item_1.stack_size / 4);
     }
+
+    #[test]
+    fn remove_items_and_gold() {
+        let mut inv = Inventory::new(30);
+
+        let item = item_generator::ItemGenerator::new().item_type(ItemType::ConsumableFood).gen();
+        let _ = inv.add_item(item.clone());
+        let _ = inv.add_item(item.clone());
+
+        assert!(inv.contains(&item.name));
+        assert_eq!(inv.remove_item(&item.name), Some(item.clone()));
+        assert_eq!(inv.remove_item(&item.name), Some(item.clone()));
+        assert_eq!(inv.remove_item(&item.name), None);
+        assert!(!inv.contains(&item.name));
+
+        inv.add_gold(20);
+        assert!(!inv.remove_gold(30));
+        assert!(inv.remove_gold(15));
+        assert_eq!(inv.gold(), 5);
+    }
 }
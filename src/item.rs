@@ -3,7 +3,7 @@ use rand::{Rand, Rng};
 use types::AttributeValue;
 
 /// An item
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Item {
     /// The name of the item
     pub name: String,
@@ -17,20 +17,33 @@ pub struct Item {
     pub rarity: ItemRarity,
 }
 
+impl_serialize_struct!(Item {
+    name,
+    item_type,
+    influence,
+    stack_size,
+    rarity,
+});
+
 impl Item {
     /// Returns `true` if the item can be equipped
     pub fn can_be_equipped(&self) -> bool {
-        let equipable = vec![ItemType::ArmorHead,
-                             ItemType::ArmorChest,
-                             ItemType::ArmorLegs,
-                             ItemType::ArmorFeet,
-                             ItemType::WeaponSword,
-                             ItemType::WeaponWand,
-                             ItemType::WeaponHammer];
+        let equipable = [ItemType::ArmorHead,
+                         ItemType::ArmorChest,
+                         ItemType::ArmorLegs,
+                         ItemType::ArmorFeet,
+                         ItemType::WeaponSword,
+                         ItemType::WeaponWand,
+                         ItemType::WeaponHammer];
 
         equipable.contains(&self.item_type)
     }
 
+    /// Returns `true` if the item can be consumed
+    pub fn is_consumable(&self) -> bool {
+        self.item_type == ItemType::ConsumablePotion || self.item_type == ItemType::ConsumableFood
+    }
+
     /// Returns `true` if the item can be stacked
     pub fn can_be_stacked(&self) -> bool {
         self.stack_size > 1
@@ -38,7 +51,7 @@ impl Item {
 }
 
 /// The influence an item can have on a certain attribute
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct ItemInfluence {
     /// The attribute that is influenced
     pub attribute: Attribute,
@@ -46,18 +59,20 @@ pub struct ItemInfluence {
     pub amount: AttributeValue,
 }
 
+impl_serialize_struct!(ItemInfluence { attribute, amount });
+
 impl ItemInfluence {
     /// Creates a new `ItemInfluence` object
     pub fn new(attribute: Attribute, amount: AttributeValue) -> ItemInfluence {
         ItemInfluence {
-            attribute: attribute,
-            amount: amount,
+            attribute,
+            amount,
         }
     }
 }
 
 /// The type of an item
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum ItemType {
     /// Armor that can only be put into the `armor_slot_head` of a character
     ArmorHead,
@@ -86,6 +101,20 @@ pub enum ItemType {
     Prop,
 }
 
+impl_serialize_enum!(ItemType {
+    ArmorHead,
+    ArmorChest,
+    ArmorLegs,
+    ArmorFeet,
+    ConsumablePotion,
+    ConsumableFood,
+    WeaponSword,
+    WeaponWand,
+    WeaponHammer,
+    Usable,
+    Prop,
+});
+
 impl ItemType {
     /// A list of attributes an `ItemType` can influence
     pub fn attributes(&self) -> Vec<Attribute> {
@@ -121,7 +150,7 @@ impl ItemType {
 
     /// Returns `true` if the item created using this type should be stackable
     pub fn is_stackable(&self) -> bool {
-        let stackable_types = vec![ItemType::ConsumableFood, ItemType::ConsumablePotion];
+        let stackable_types = [ItemType::ConsumableFood, ItemType::ConsumablePotion];
 
         stackable_types.contains(self)
     }
@@ -129,34 +158,34 @@ impl ItemType {
     /// A helper method to get an ItemType
     pub fn by_num(item_class_num: u32, item_type_num: u32) -> ItemType {
         match item_class_num {
-            0...250 => {
+            0..=250 => {
                 match item_type_num {
-                    0...500 => ItemType::ConsumableFood,
-                    501...1000 => ItemType::ConsumablePotion,
+                    0..=500 => ItemType::ConsumableFood,
+                    501..=1000 => ItemType::ConsumablePotion,
                     _ => ItemType::Prop,
                 }
             }
-            251...500 => {
+            251..=500 => {
                 match item_type_num {
-                    0...250 => ItemType::ArmorHead,
-                    251...500 => ItemType::ArmorChest,
-                    501...750 => ItemType::ArmorLegs,
-                    751...1000 => ItemType::ArmorFeet,
+                    0..=250 => ItemType::ArmorHead,
+                    251..=500 => ItemType::ArmorChest,
+                    501..=750 => ItemType::ArmorLegs,
+                    751..=1000 => ItemType::ArmorFeet,
                     _ => ItemType::Prop,
                 }
             }
-            501...750 => {
+            501..=750 => {
                 match item_type_num {
-                    0...333 => ItemType::WeaponHammer,
-                    334...666 => ItemType::WeaponSword,
-                    667...1000 => ItemType::WeaponWand,
+                    0..=333 => ItemType::WeaponHammer,
+                    334..=666 => ItemType::WeaponSword,
+                    667..=1000 => ItemType::WeaponWand,
                     _ => ItemType::Prop,
                 }
             }
-            751...1000 => {
+            751..=1000 => {
                 match item_type_num {
-                    0...500 => ItemType::Usable,
-                    501...1000 => ItemType::Prop,
+                    0..=500 => ItemType::Usable,
+                    501..=1000 => ItemType::Prop,
                     _ => ItemType::Prop,
                 }
             }
@@ -175,7 +204,7 @@ impl Rand for ItemType {
 }
 
 /// A type defining the rarity of an item
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum ItemRarity {
     /// Items are found very often
     Common,
@@ -189,15 +218,23 @@ pub enum ItemRarity {
     Legendary,
 }
 
+impl_serialize_enum!(ItemRarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+});
+
 impl ItemRarity {
     /// A helper method to get an ItemRarity
     pub fn by_num(item_rarity_num: u32) -> ItemRarity {
         match item_rarity_num {
-            0...750 => ItemRarity::Common,
-            751...917 => ItemRarity::Uncommon,
-            918...972 => ItemRarity::Rare,
-            873...979 => ItemRarity::Epic,
-            980...1000 => ItemRarity::Legendary,
+            0..=750 => ItemRarity::Common,
+            751..=917 => ItemRarity::Uncommon,
+            918..=972 => ItemRarity::Rare,
+            973..=979 => ItemRarity::Epic,
+            980..=1000 => ItemRarity::Legendary,
             _ => ItemRarity::Common,
         }
     }
@@ -255,8 +292,8 @@ mod tests {
 
     #[test]
     fn item_type() {
-        for class_num in (0..1000) {
-            for type_num in (0..1000) {
+        for class_num in 0..1000 {
+            for type_num in 0..1000 {
                 ItemType::by_num(class_num, type_num);
             }
         }
@@ -2,7 +2,7 @@ use item::*;
 use character::Attribute;
 use rand::Rng;
 use rand;
-use names::{Generator, Name};
+use names::{ADJECTIVES, NOUNS};
 use types::AttributeValue;
 
 /// A builder like generator for items. Missing fields are filled randomly
@@ -73,30 +73,36 @@ impl ItemGenerator {
 
     /// Generates the item using the given data. Missing data will be filed randomly
     pub fn gen(&self) -> Item {
+        self.gen_with(&mut rand::thread_rng())
+    }
+
+    /// Generates the item using the given data. Missing data will be filled using the given
+    /// random number generator, so seeded generators produce the same items every time.
+    pub fn gen_with<R: Rng>(&self, rng: &mut R) -> Item {
         // The item type
         let item_type = if let Some(ref inner_item_type) = self.data_item_type {
             inner_item_type.clone()
         } else {
-            random_item_type()
+            rng.gen::<ItemType>()
         };
 
         // The item rarity
         let rarity = if let Some(ref inner_rarity) = self.data_rarity {
             inner_rarity.clone()
         } else {
-            random_item_rarity()
+            rng.gen::<ItemRarity>()
         };
 
         let influence = if let Some(ref inner_influence) = self.data_influence {
             inner_influence.clone()
         } else {
-            let is_none = rand::thread_rng().gen::<bool>();
+            let is_none = rng.gen::<bool>();
             if is_none || item_type.attributes().is_empty() {
                 None
             } else {
                 Some(ItemInfluence {
-                    attribute: random_influence_attribute(&item_type),
-                    amount: random_influence_amount(&rarity),
+                    attribute: random_influence_attribute(rng, &item_type),
+                    amount: random_influence_amount(rng, &rarity),
                 })
             }
         };
@@ -105,38 +111,43 @@ impl ItemGenerator {
         let stack_size = if let Some(ref inner_stack_size) = self.data_stack_size {
             *inner_stack_size
         } else {
-            random_stack_size(&item_type)
+            random_stack_size(rng, &item_type)
         };
 
         // The name of the item
         let name = if let Some(ref inner_name) = self.data_name {
             inner_name.clone()
         } else {
-            random_item_name(&item_type)
+            random_item_name(rng, &item_type)
         };
 
         Item {
-            name: name,
-            item_type: item_type,
-            influence: influence,
-            stack_size: stack_size,
-            rarity: rarity,
+            name,
+            item_type,
+            influence,
+            stack_size,
+            rarity,
         }
     }
 }
 
-fn random_influence_attribute(item_type: &ItemType) -> Attribute {
+impl Default for ItemGenerator {
+    fn default() -> ItemGenerator {
+        ItemGenerator::new()
+    }
+}
+
+fn random_influence_attribute<R: Rng>(rng: &mut R, item_type: &ItemType) -> Attribute {
     let mut attrbute_set = item_type.attributes();
     if attrbute_set.is_empty() {
         Attribute::Charisma
     } else {
-        let index = rand::thread_rng().gen_range(0, attrbute_set.len());
+        let index = rng.gen_range(0, attrbute_set.len());
         attrbute_set.remove(index)
     }
 }
 
-fn random_influence_amount(item_rarity: &ItemRarity) -> AttributeValue {
-    let mut rng = rand::thread_rng();
+fn random_influence_amount<R: Rng>(rng: &mut R, item_rarity: &ItemRarity) -> AttributeValue {
     let result = match *item_rarity {
         ItemRarity::Common => rng.gen_range(-1, 10),
         ItemRarity::Uncommon => rng.gen_range(1, 50),
@@ -148,31 +159,35 @@ fn random_influence_amount(item_rarity: &ItemRarity) -> AttributeValue {
     if result == 0 { 1 } else { result }
 }
 
-fn random_item_name(item_type: &ItemType) -> String {
+fn random_item_name<R: Rng>(rng: &mut R, item_type: &ItemType) -> String {
     match *item_type {
         ItemType::WeaponSword | ItemType::WeaponHammer | ItemType::WeaponWand => {
-            random_weapon_name()
+            random_weapon_name(rng)
+        }
+        _ => {
+            let adjective = rng.choose(ADJECTIVES).unwrap();
+            let noun = rng.choose(NOUNS).unwrap();
+            format!("{}-{}", adjective, noun)
         }
-        _ => Generator::with_naming(Name::Plain).next().unwrap(),
     }
 }
 
-fn random_weapon_name() -> String {
+fn random_weapon_name<R: Rng>(rng: &mut R) -> String {
     let mut weapon_names: Vec<String> = vec!["Sword", "Boulder", "Wand", "Dagger", "Hammer",
                                              "Rifle"]
         .into_iter()
-        .map(|string| String::from(string))
+        .map(String::from)
         .collect();
 
-    let weapon_name = rand::thread_rng().gen_range(0, weapon_names.len());
+    let weapon_name = rng.gen_range(0, weapon_names.len());
     let weapon_name = weapon_names.remove(weapon_name);
 
     let mut weapon_prefixes: Vec<String> = vec!["Shiny", "Firey", "Wonderous", "Giant"]
         .into_iter()
-        .map(|string| String::from(string))
+        .map(String::from)
         .collect();
 
-    let weapon_prefix = rand::thread_rng().gen_range(0, weapon_prefixes.len());
+    let weapon_prefix = rng.gen_range(0, weapon_prefixes.len());
     let weapon_prefix = weapon_prefixes.remove(weapon_prefix);
 
     let mut weapon_suffixes: Vec<String> = vec!["Nashioce",
@@ -216,27 +231,19 @@ fn random_weapon_name() -> String {
                                                 "Groyt Thington",
                                                 "Fleiw Flen"]
         .into_iter()
-        .map(|string| String::from(string))
+        .map(String::from)
         .collect();
 
-    let weapon_suffix = rand::thread_rng().gen_range(0, weapon_suffixes.len());
+    let weapon_suffix = rng.gen_range(0, weapon_suffixes.len());
     let weapon_suffix = weapon_suffixes.remove(weapon_suffix);
 
     format!("{} {} of {}", weapon_prefix, weapon_name, weapon_suffix)
 }
 
-fn random_item_type() -> ItemType {
-    rand::thread_rng().gen::<ItemType>()
-}
-
-fn random_item_rarity() -> ItemRarity {
-    rand::thread_rng().gen::<ItemRarity>()
-}
-
-fn random_stack_size(item_type: &ItemType) -> usize {
+fn random_stack_size<R: Rng>(rng: &mut R, item_type: &ItemType) -> usize {
     let mut base_sizes = vec![4, 16, 64];
     if item_type.is_stackable() {
-        let index = rand::thread_rng().gen_range(0, base_sizes.len());
+        let index = rng.gen_range(0, base_sizes.len());
         base_sizes.remove(index) as usize
     } else {
         1
@@ -249,7 +256,7 @@ mod tests {
     use character::Attribute;
     use item::{ItemType, ItemRarity, ItemInfluence};
     use rand;
-    use rand::Rng;
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn builder_item_type() {
@@ -296,4 +303,15 @@ mod tests {
 
         assert_eq!(rnd_item.rarity, ItemRarity::Rare);
     }
+
+    #[test]
+    fn seeded_generation() {
+        let mut rng_1 = rand::XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut rng_2 = rand::XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        for _ in 0..100 {
+            assert_eq!(ItemGenerator::new().gen_with(&mut rng_1),
+                       ItemGenerator::new().gen_with(&mut rng_2));
+        }
+    }
 }
@@ -15,20 +15,43 @@ extern crate names;
 extern crate rand;
 extern crate rustc_serialize;
 
+#[macro_use]
+mod macros;
+
 /// The behaviour of entities
 pub mod behaviour;
 /// The structures used to bulid a character
 pub mod character;
+/// Turn-based combat between characters and entities
+pub mod combat;
 /// Everything regarding entities
 pub mod entity;
 /// The structure of events
 pub mod event;
+/// Publishing events to everyone interested in them
+pub mod event_bus;
+/// Collecting the input of the player
+pub mod input;
 /// The structure and mechanics of an inventory
 pub mod inventory;
 /// Generate random items
 pub mod item_generator;
 /// The structure of items
 pub mod item;
+/// Experience, levels and the growth of characters
+pub mod progression;
+/// Recording and replaying game sessions
+pub mod replay;
+/// Mana, stamina and other resources spent by abilities
+pub mod resource;
+/// Saving and loading the state of a game
+pub mod save;
+/// The screens of a game, like menus or the gameplay itself
+pub mod scene;
+/// Effective attributes and the modifiers they are made of
+pub mod stats;
+/// Independent parts of the game logic run by the engine
+pub mod system;
 /// A module for global type consitency
 pub mod types;
 /// Structures for saving, loading and playing a game world
@@ -36,4 +59,4 @@ pub mod world;
 /// The actual engine to run the game and handle the IO
 pub mod engine;
 
-pub use engine::Engine;
+pub use engine::{Engine, EngineBuilder, EngineContext, EngineError};
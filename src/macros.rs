@@ -0,0 +1,72 @@
+/// Implements `Encodable` and `Decodable` for a struct by (de)serializing the given fields in
+/// order. Works like `#[derive(RustcEncodable, RustcDecodable)]`.
+macro_rules! impl_serialize_struct {
+    ($name:ident { $($field:ident),+ $(,)* }) => {
+        impl ::rustc_serialize::Encodable for $name {
+            fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+                let len = [$(stringify!($field)),+].len();
+                e.emit_struct(stringify!($name), len, |e| {
+                    let mut index = 0;
+                    $(
+                        e.emit_struct_field(stringify!($field), index, |e| {
+                            ::rustc_serialize::Encodable::encode(&self.$field, e)
+                        })?;
+                        index += 1;
+                    )+
+                    let _ = index;
+                    Ok(())
+                })
+            }
+        }
+
+        impl ::rustc_serialize::Decodable for $name {
+            fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<$name, D::Error> {
+                let len = [$(stringify!($field)),+].len();
+                d.read_struct(stringify!($name), len, |d| {
+                    let mut index = 0;
+                    $(
+                        let $field = d.read_struct_field(stringify!($field),
+                                                         index,
+                                                         ::rustc_serialize::Decodable::decode)?;
+                        index += 1;
+                    )+
+                    let _ = index;
+                    Ok($name { $($field),+ })
+                })
+            }
+        }
+    }
+}
+
+/// Implements `Encodable` and `Decodable` for an enum consisting of unit variants only. The
+/// variants are (de)serialized by their name.
+macro_rules! impl_serialize_enum {
+    ($name:ident { $($variant:ident),+ $(,)* }) => {
+        impl ::rustc_serialize::Encodable for $name {
+            fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+                let names = [$(stringify!($variant)),+];
+                let name = match *self {
+                    $($name::$variant => stringify!($variant)),+
+                };
+                let index = names.iter().position(|other| *other == name).unwrap_or(0);
+                e.emit_enum(stringify!($name), |e| e.emit_enum_variant(name, index, 0, |_| Ok(())))
+            }
+        }
+
+        impl ::rustc_serialize::Decodable for $name {
+            fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<$name, D::Error> {
+                let names = [$(stringify!($variant)),+];
+                d.read_enum(stringify!($name), |d| {
+                    d.read_enum_variant(&names, |d, index| {
+                        $(
+                            if names[index] == stringify!($variant) {
+                                return Ok($name::$variant);
+                            }
+                        )+
+                        Err(d.error("unknown variant"))
+                    })
+                })
+            }
+        }
+    }
+}
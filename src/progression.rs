@@ -0,0 +1,374 @@
+use std::error::Error;
+use std::fmt;
+
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use character::Attribute;
+use types::{AttributeValue, Experience};
+
+/// The default highest level of a progression
+pub const DEFAULT_MAX_LEVEL: u32 = 100;
+
+/// The experience needed to reach each level
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LevelCurve {
+    /// Reaching level `n` needs `base * (n - 1) ^ exponent` experience
+    Polynomial(Experience, u32),
+    /// The experience needed to reach level 2, 3 and so on. The level after the last entry
+    /// can't be reached.
+    Table(Vec<Experience>),
+}
+
+impl LevelCurve {
+    /// Returns the total experience needed to reach the given level, or `None` if the level
+    /// can't be reached
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rpg::progression::LevelCurve;
+    /// let curve = LevelCurve::Polynomial(100, 2);
+    ///
+    /// assert_eq!(curve.experience(1), Some(0));
+    /// assert_eq!(curve.experience(3), Some(400));
+    /// assert_eq!(LevelCurve::Table(vec![50, 120]).experience(4), None);
+    /// ```
+    pub fn experience(&self, level: u32) -> Option<Experience> {
+        if level <= 1 {
+            return Some(0);
+        }
+
+        match *self {
+            LevelCurve::Polynomial(base, exponent) => {
+                Some(base.saturating_mul(Experience::from(level - 1).saturating_pow(exponent)))
+            }
+            LevelCurve::Table(ref table) => table.get(level as usize - 2).cloned(),
+        }
+    }
+}
+
+impl Encodable for LevelCurve {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_enum("LevelCurve", |e| {
+            match *self {
+                LevelCurve::Polynomial(base, exponent) => {
+                    e.emit_enum_variant("Polynomial", 0, 2, |e| {
+                        e.emit_enum_variant_arg(0, |e| base.encode(e))?;
+                        e.emit_enum_variant_arg(1, |e| exponent.encode(e))
+                    })
+                }
+                LevelCurve::Table(ref table) => {
+                    e.emit_enum_variant("Table", 1, 1, |e| {
+                        e.emit_enum_variant_arg(0, |e| table.encode(e))
+                    })
+                }
+            }
+        })
+    }
+}
+
+impl Decodable for LevelCurve {
+    fn decode<D: Decoder>(d: &mut D) -> Result<LevelCurve, D::Error> {
+        d.read_enum("LevelCurve", |d| {
+            d.read_enum_variant(&["Polynomial", "Table"], |d, index| {
+                match index {
+                    0 => {
+                        let base = d.read_enum_variant_arg(0, Decodable::decode)?;
+                        let exponent = d.read_enum_variant_arg(1, Decodable::decode)?;
+                        Ok(LevelCurve::Polynomial(base, exponent))
+                    }
+                    _ => d.read_enum_variant_arg(0, Decodable::decode).map(LevelCurve::Table),
+                }
+            })
+        })
+    }
+}
+
+/// An attribute increasing automatically when a character levels up
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Growth {
+    attribute: Attribute,
+    amount: AttributeValue,
+    /// The attribute grows on every level that is a multiple of this one
+    every: u32,
+}
+
+impl Encodable for Growth {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("Growth", 3, |e| {
+            e.emit_struct_field("attribute", 0, |e| self.attribute.encode(e))?;
+            e.emit_struct_field("amount", 1, |e| self.amount.encode(e))?;
+            e.emit_struct_field("every", 2, |e| self.every.encode(e))
+        })
+    }
+}
+
+/// Growth every `0` levels is rejected, like in `Progression::growth`
+impl Decodable for Growth {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Growth, D::Error> {
+        d.read_struct("Growth", 3, |d| {
+            let attribute = d.read_struct_field("attribute", 0, Decodable::decode)?;
+            let amount = d.read_struct_field("amount", 1, Decodable::decode)?;
+            let every = d.read_struct_field("every", 2, Decodable::decode)?;
+            if every == 0 {
+                return Err(d.error("attributes can't grow every 0 levels"));
+            }
+
+            Ok(Growth {
+                attribute,
+                amount,
+                every,
+            })
+        })
+    }
+}
+
+/// The rules for the progression of a character: how much experience the levels need, how many
+/// attribute points are granted per level and which attributes grow on their own.
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::character::{Attribute, Character};
+/// # use rpg::progression::{LevelCurve, Progression};
+/// let progression = Progression::new(LevelCurve::Table(vec![100, 300, 600]))
+///     .points_per_level(5)
+///     .growth(Attribute::Strength, 1, 2);
+///
+/// let mut character = Character::new("Michael");
+/// character.set_progression(progression);
+///
+/// let level_ups = character.gain_experience(350);
+///
+/// assert_eq!(level_ups.len(), 2);
+/// assert_eq!(character.level(), 3);
+/// assert_eq!(character.attribute_points(), 10);
+/// assert_eq!(character.get_attribute_value(&Attribute::Strength), 21);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Progression {
+    curve: LevelCurve,
+    max_level: u32,
+    points_per_level: u32,
+    growth: Vec<Growth>,
+}
+
+impl_serialize_struct!(Progression {
+    curve,
+    max_level,
+    points_per_level,
+    growth,
+});
+
+impl Progression {
+    /// Creates a new instance of `Progression` with the given curve and `DEFAULT_MAX_LEVEL` as
+    /// the highest level, without attribute points or growth
+    pub fn new(curve: LevelCurve) -> Progression {
+        Progression {
+            curve,
+            max_level: DEFAULT_MAX_LEVEL,
+            points_per_level: 0,
+            growth: Vec::new(),
+        }
+    }
+
+    /// A builder method for setting the highest level that can be reached
+    pub fn max_level(mut self, max_level: u32) -> Progression {
+        self.max_level = max_level;
+        self
+    }
+
+    /// A builder method for setting the attribute points granted per level
+    pub fn points_per_level(mut self, points: u32) -> Progression {
+        self.points_per_level = points;
+        self
+    }
+
+    /// A builder method for letting the attribute grow by the amount on every level that is a
+    /// multiple of `every`
+    ///
+    /// # Panics
+    ///
+    /// **Panics** if `every` is `0`
+    pub fn growth(mut self,
+                  attribute: Attribute,
+                  amount: AttributeValue,
+                  every: u32)
+                  -> Progression {
+        assert!(every != 0, "attributes can't grow every 0 levels");
+
+        self.growth.push(Growth {
+            attribute,
+            amount,
+            every,
+        });
+        self
+    }
+
+    /// Returns the total experience needed to reach the given level, or `None` if the level
+    /// can't be reached. Levels above the highest level can't be reached, neither can levels
+    /// needing no more experience than the level before.
+    pub fn experience(&self, level: u32) -> Option<Experience> {
+        if level > self.max_level {
+            return None;
+        }
+
+        // A level needing no more experience than the one before would be reached together
+        // with it, like on a flat curve
+        let experience = self.curve.experience(level)?;
+        let before = self.curve.experience(level.saturating_sub(1));
+        if level > 1 && before.is_some_and(|before| experience <= before) {
+            return None;
+        }
+
+        Some(experience)
+    }
+
+    /// Returns the attribute points granted per level
+    pub fn attribute_points(&self) -> u32 {
+        self.points_per_level
+    }
+
+    /// Returns the attributes growing when the given level is reached
+    pub fn growth_at(&self, level: u32) -> Vec<(Attribute, AttributeValue)> {
+        self.growth
+            .iter()
+            .filter(|growth| level.is_multiple_of(growth.every))
+            .map(|growth| (growth.attribute.clone(), growth.amount))
+            .collect()
+    }
+
+    /// Returns how much the attribute grew in total until the given level was reached
+    pub fn growth_until(&self, attribute: &Attribute, level: u32) -> AttributeValue {
+        self.growth
+            .iter()
+            .filter(|growth| growth.attribute == *attribute)
+            .map(|growth| {
+                // The multiples of `every` from level 2 to the given level
+                let levels = level / growth.every - 1 / growth.every;
+                growth.amount * AttributeValue::from(levels)
+            })
+            .sum()
+    }
+}
+
+/// The default progression has 50 levels, reaching level `n` needs `100 * (n - 1)²`
+/// experience, every level grants 3 attribute points and 2 constitution.
+impl Default for Progression {
+    fn default() -> Progression {
+        Progression::new(LevelCurve::Polynomial(100, 2))
+            .max_level(50)
+            .points_per_level(3)
+            .growth(Attribute::Constitution, 2, 1)
+    }
+}
+
+/// A level a character reached
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LevelUp {
+    /// The new level
+    pub level: u32,
+    /// The attribute points granted
+    pub attribute_points: u32,
+    /// The attributes that grew and by how much
+    pub growth: Vec<(Attribute, AttributeValue)>,
+}
+
+/// An error while allocating attribute points
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AllocationError {
+    /// No points were allocated
+    NoPoints,
+    /// The character has fewer unspent points than were allocated
+    NotEnoughPoints(u32),
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AllocationError::NoPoints => write!(f, "no attribute points were allocated"),
+            AllocationError::NotEnoughPoints(available) => {
+                write!(f, "only {} attribute points are available", available)
+            }
+        }
+    }
+}
+
+impl Error for AllocationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::json;
+
+    #[test]
+    fn curves() {
+        let curve = LevelCurve::Polynomial(u64::MAX / 2, 3);
+        assert_eq!(curve.experience(2), Some(u64::MAX / 2));
+        assert_eq!(curve.experience(3), Some(u64::MAX));
+
+        let table = LevelCurve::Table(vec![10, 30]);
+        assert_eq!(table.experience(0), Some(0));
+        assert_eq!(table.experience(3), Some(30));
+        assert_eq!(table.experience(4), None);
+
+        let progression = Progression::new(LevelCurve::Polynomial(10, 1)).max_level(3);
+        assert_eq!(progression.experience(3), Some(20));
+        assert_eq!(progression.experience(4), None);
+
+        let progression = Progression::new(LevelCurve::Polynomial(10, 1));
+        assert_eq!(progression.experience(DEFAULT_MAX_LEVEL), Some(990));
+        assert_eq!(progression.experience(DEFAULT_MAX_LEVEL + 1), None);
+    }
+
+    #[test]
+    fn flat_curves() {
+        let progression = Progression::new(LevelCurve::Polynomial(0, 2));
+        assert_eq!(progression.experience(1), Some(0));
+        assert_eq!(progression.experience(2), None);
+
+        let progression = Progression::new(LevelCurve::Table(vec![10, 30, 30, 50]));
+        assert_eq!(progression.experience(3), Some(30));
+        assert_eq!(progression.experience(4), None);
+
+        let progression = Progression::new(LevelCurve::Polynomial(u64::MAX / 2, 3));
+        assert_eq!(progression.experience(3), Some(u64::MAX));
+        assert_eq!(progression.experience(4), None);
+    }
+
+    #[test]
+    fn growth() {
+        let progression = Progression::default().growth(Attribute::Luck, 1, 5);
+
+        assert_eq!(progression.growth_at(4), vec![(Attribute::Constitution, 2)]);
+        assert_eq!(progression.growth_at(10),
+                   vec![(Attribute::Constitution, 2), (Attribute::Luck, 1)]);
+        assert_eq!(progression.growth_until(&Attribute::Constitution, 1), 0);
+        assert_eq!(progression.growth_until(&Attribute::Constitution, 10), 18);
+        assert_eq!(progression.growth_until(&Attribute::Luck, 9), 1);
+        assert_eq!(progression.growth_until(&Attribute::Strength, 9), 0);
+    }
+
+    #[test]
+    fn save_progression() {
+        let progression = Progression::new(LevelCurve::Table(vec![5, 8])).points_per_level(1);
+        let loaded: Progression = json::decode(&json::encode(&progression).unwrap()).unwrap();
+
+        assert_eq!(loaded, progression);
+    }
+
+    #[test]
+    fn save_growth_every_0_levels() {
+        let progression = Progression::new(LevelCurve::Table(vec![5, 8]))
+            .growth(Attribute::Luck, 1, 3);
+        let save = json::encode(&progression).unwrap();
+
+        assert!(save.contains(r#""every":3"#));
+        match json::decode::<Progression>(&save.replace(r#""every":3"#, r#""every":0"#)) {
+            Err(json::DecoderError::ApplicationError(error)) => {
+                assert_eq!(error, "attributes can't grow every 0 levels")
+            }
+            _ => panic!("growth every 0 levels was loaded"),
+        }
+    }
+}
@@ -0,0 +1,531 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use engine::{EngineBuilder, EngineContext, EngineResult};
+use input::{InputEvent, InputSource, Key};
+use system::System;
+
+/// The version of the replay file format
+pub const REPLAY_VERSION: u32 = 1;
+
+/// A hasher producing the same values on every platform and with every compiler version. Used
+/// for the checksums of a replay. Implements 64 bit FNV-1a.
+#[derive(Clone, Copy, Debug)]
+pub struct StableHasher {
+    hash: u64,
+}
+
+impl StableHasher {
+    /// Creates a new instance of `StableHasher`
+    pub fn new() -> StableHasher {
+        StableHasher { hash: 0xcbf2_9ce4_8422_2325 }
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> StableHasher {
+        StableHasher::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= u64::from(*byte);
+            self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        // Hash as a 64 bit number to get the same checksums on 32 bit platforms
+        self.write_u64(value as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Calculates the checksum of the given value using a `StableHasher`
+pub fn checksum<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = StableHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// An error while saving, loading or playing a replay
+#[derive(Debug)]
+pub enum ReplayError {
+    /// Reading or writing the replay failed
+    Io(io::Error),
+    /// The given line of the replay is malformed
+    Parse(usize, String),
+    /// The replayed game is not in the recorded state at the given tick
+    Divergence {
+        /// The tick the states were compared at
+        tick: u64,
+        /// The recorded checksum
+        expected: u64,
+        /// The checksum of the replayed game
+        actual: u64,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Io(ref error) => write!(f, "{}", error),
+            ReplayError::Parse(line, ref message) => write!(f, "line {}: {}", line, message),
+            ReplayError::Divergence { tick, expected, actual } => {
+                write!(f,
+                       "the replay diverged at tick {}: expected checksum {:016x}, got {:016x}",
+                       tick,
+                       expected,
+                       actual)
+            }
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ReplayError::Io(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> ReplayError {
+        ReplayError::Io(error)
+    }
+}
+
+/// A recorded game session. Contains everything fed into the engine to reach the same state
+/// again: the seed, the rate of the updates and the input of every update. Checksums of the
+/// state are stored periodically to detect divergence.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    /// The seed of the random number generator
+    pub seed: u64,
+    /// The amount of updates per second
+    pub ticks_per_second: u32,
+    /// The input of every update that received any, by tick
+    pub inputs: Vec<(u64, Vec<InputEvent>)>,
+    /// The checksums of the state at the beginning of an update, by tick
+    pub checksums: Vec<(u64, u64)>,
+}
+
+impl Replay {
+    /// Creates a new, empty instance of `Replay`
+    pub fn new(seed: u64, ticks_per_second: u32) -> Replay {
+        Replay {
+            seed,
+            ticks_per_second,
+            inputs: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Configures the given builder to replay this session. Sets the seed, the rate of updates
+    /// and the input, and registers a system comparing the state to the recorded checksums. The
+    /// engine is aborted with a `ReplayError::Divergence` once they differ.
+    ///
+    /// The `checksum` has to be the same function the session was recorded with.
+    pub fn play<S, F>(self, builder: EngineBuilder<S>, checksum: F) -> EngineBuilder<S>
+        where S: 'static,
+              F: Fn(&EngineContext<S>) -> u64 + 'static
+    {
+        let mut inputs = VecDeque::new();
+        let mut tick = 0;
+        for (input_tick, events) in self.inputs {
+            while tick < input_tick {
+                inputs.push_back(Vec::new());
+                tick += 1;
+            }
+            inputs.push_back(events);
+            tick += 1;
+        }
+
+        builder.seed(self.seed)
+            .ticks_per_second(self.ticks_per_second)
+            .input(ReplaySource { inputs })
+            .system(ReplayVerifier {
+                checksums: self.checksums.into_iter().collect(),
+                checksum: Box::new(checksum),
+            })
+    }
+
+    /// Saves the replay to the given file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let mut file = File::create(path)?;
+        self.write(&mut file)
+    }
+
+    /// Loads a replay from the given file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        Replay::read(File::open(path)?)
+    }
+
+    /// Writes the replay in its line based text format
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), ReplayError> {
+        writeln!(writer, "replay {}", REPLAY_VERSION)?;
+        writeln!(writer, "seed {}", self.seed)?;
+        writeln!(writer, "ticks_per_second {}", self.ticks_per_second)?;
+
+        for &(tick, ref events) in &self.inputs {
+            for event in events {
+                writeln!(writer, "input {} {}", tick, encode_event(event))?;
+            }
+        }
+
+        for &(tick, checksum) in &self.checksums {
+            writeln!(writer, "checksum {} {:016x}", tick, checksum)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a replay written by `Replay::write`
+    pub fn read<R: Read>(reader: R) -> Result<Replay, ReplayError> {
+        let mut replay = Replay::default();
+        let mut has_header = false;
+
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let number = index + 1;
+            let error = |message: &str| ReplayError::Parse(number, message.to_owned());
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(3, ' ');
+            let keyword = parts.next().unwrap_or("");
+            let first = parts.next().ok_or_else(|| error("missing value"))?;
+
+            match keyword {
+                "replay" => {
+                    let version: u32 = first.parse().map_err(|_| error("invalid version"))?;
+                    if version != REPLAY_VERSION {
+                        return Err(error("unsupported version"));
+                    }
+                    has_header = true;
+                }
+                _ if !has_header => return Err(error("missing replay header")),
+                "seed" => replay.seed = first.parse().map_err(|_| error("invalid seed"))?,
+                "ticks_per_second" => {
+                    replay.ticks_per_second =
+                        first.parse().map_err(|_| error("invalid ticks per second"))?
+                }
+                "input" => {
+                    let tick = first.parse().map_err(|_| error("invalid tick"))?;
+                    let event = parts.next()
+                        .and_then(decode_event)
+                        .ok_or_else(|| error("invalid input event"))?;
+
+                    let is_same_tick = replay.inputs.last().is_some_and(|&(last, _)| last == tick);
+                    if is_same_tick {
+                        replay.inputs.last_mut().unwrap().1.push(event);
+                    } else {
+                        replay.inputs.push((tick, vec![event]));
+                    }
+                }
+                "checksum" => {
+                    let tick = first.parse().map_err(|_| error("invalid tick"))?;
+                    let checksum = parts.next()
+                        .and_then(|checksum| u64::from_str_radix(checksum, 16).ok())
+                        .ok_or_else(|| error("invalid checksum"))?;
+                    replay.checksums.push((tick, checksum));
+                }
+                _ => return Err(error("unknown keyword")),
+            }
+        }
+
+        if has_header {
+            Ok(replay)
+        } else {
+            Err(ReplayError::Parse(0, "missing replay header".to_owned()))
+        }
+    }
+}
+
+fn encode_event(event: &InputEvent) -> String {
+    match *event {
+        InputEvent::Key(Key::Char(c)) => format!("char {}", c as u32),
+        InputEvent::Key(key) => format!("key {:?}", key),
+        InputEvent::Command(ref command) => format!("command {}", command),
+    }
+}
+
+fn decode_event(text: &str) -> Option<InputEvent> {
+    let (kind, value) = text.split_once(' ')?;
+
+    let key = match (kind, value) {
+        ("command", command) => return Some(InputEvent::Command(command.to_owned())),
+        ("char", code) => Key::Char(code.parse().ok().and_then(::std::char::from_u32)?),
+        ("key", "Up") => Key::Up,
+        ("key", "Down") => Key::Down,
+        ("key", "Left") => Key::Left,
+        ("key", "Right") => Key::Right,
+        ("key", "Enter") => Key::Enter,
+        ("key", "Escape") => Key::Escape,
+        ("key", "Backspace") => Key::Backspace,
+        ("key", "Tab") => Key::Tab,
+        _ => return None,
+    };
+
+    Some(InputEvent::Key(key))
+}
+
+/// Calculates the checksum of the context to compare the recorded and the replayed state
+type ChecksumFn<S> = Box<dyn Fn(&EngineContext<S>) -> u64>;
+
+/// A system recording a session into a `Replay`. Register it before all other systems, so the
+/// checksums are taken before anything changes the state.
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::engine::EngineBuilder;
+/// # use rpg::input::{InputEvent, Key, ScriptedSource};
+/// # use rpg::replay::{checksum, Recorder};
+/// let recorder = Recorder::new(10, |context| checksum(&context.state));
+/// let replay = recorder.replay();
+///
+/// EngineBuilder::with_state(0)
+///     .input(ScriptedSource::new().idle(5).tick(vec![InputEvent::Key(Key::Enter)]))
+///     .system(recorder)
+///     .update(|mut context| {
+///         context.state += context.input.events.len();
+///         Ok(context)
+///     })
+///     .build()
+///     .unwrap()
+///     .run_headless(20)
+///     .unwrap();
+///
+/// assert_eq!(replay.borrow().inputs, vec![(5, vec![InputEvent::Key(Key::Enter)])]);
+/// assert_eq!(replay.borrow().checksums.len(), 2);
+/// ```
+pub struct Recorder<S = ()> {
+    interval: u64,
+    checksum: ChecksumFn<S>,
+    replay: Rc<RefCell<Replay>>,
+}
+
+impl<S> Recorder<S> {
+    /// Creates a new instance of `Recorder` storing the `checksum` of the context every
+    /// `interval` updates
+    ///
+    /// # Panics
+    ///
+    /// **Panics** if `interval` is `0`
+    pub fn new<F>(interval: u64, checksum: F) -> Recorder<S>
+        where F: Fn(&EngineContext<S>) -> u64 + 'static
+    {
+        assert!(interval > 0);
+        Recorder {
+            interval,
+            checksum: Box::new(checksum),
+            replay: Rc::new(RefCell::new(Replay::default())),
+        }
+    }
+
+    /// Returns a handle to the recorded replay. It stays accessible after the engine stopped.
+    pub fn replay(&self) -> Rc<RefCell<Replay>> {
+        self.replay.clone()
+    }
+}
+
+impl<S> System<S> for Recorder<S> {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn setup(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        let ticks_per_second = (1_f64 / context.delta).round() as u32;
+        *self.replay.borrow_mut() = Replay::new(context.seed, ticks_per_second);
+        Ok(context)
+    }
+
+    fn update(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        let mut replay = self.replay.borrow_mut();
+
+        if !context.input.events.is_empty() {
+            replay.inputs.push((context.tick, context.input.events.clone()));
+        }
+
+        if context.tick.is_multiple_of(self.interval) {
+            replay.checksums.push((context.tick, (self.checksum)(&context)));
+        }
+
+        Ok(context)
+    }
+}
+
+/// Feeds the recorded input into the engine
+struct ReplaySource {
+    inputs: VecDeque<Vec<InputEvent>>,
+}
+
+impl InputSource for ReplaySource {
+    fn poll(&mut self) -> Vec<InputEvent> {
+        self.inputs.pop_front().unwrap_or_default()
+    }
+}
+
+/// Compares the state of the replayed game to the recorded checksums
+struct ReplayVerifier<S> {
+    checksums: VecDeque<(u64, u64)>,
+    checksum: ChecksumFn<S>,
+}
+
+impl<S> System<S> for ReplayVerifier<S> {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn update(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        match self.checksums.front() {
+            Some(&(tick, expected)) if tick == context.tick => {
+                self.checksums.pop_front();
+
+                let actual = (self.checksum)(&context);
+                if actual != expected {
+                    return Err(context.abort(ReplayError::Divergence {
+                        tick,
+                        expected,
+                        actual,
+                    }));
+                }
+
+                Ok(context)
+            }
+            _ => Ok(context),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use character::{Attribute, Character};
+    use engine::{EngineBuilder, EngineContext, EngineError};
+    use input::{Action, InputEvent, Key, ScriptedSource};
+    use item_generator::ItemGenerator;
+    use rand::Rng;
+    use world::World;
+    use world::two_dimensional::World2d;
+
+    type Game = (Character, World2d);
+
+    fn new_game() -> Game {
+        (Character::new("Thomas"), World2d::new("Hunger Game", (10, 10)))
+    }
+
+    fn builder(game: Game) -> EngineBuilder<Game> {
+        EngineBuilder::with_state(game).update(|mut context| {
+            for action in context.input.actions() {
+                if let Action::Move(movement) = action {
+                    context.state.1.move_to(movement);
+
+                    let loot = ItemGenerator::new().gen_with(&mut context.rng);
+                    let _ = context.state.0.inventory_mut().add_item(loot);
+                }
+            }
+
+            let luck = context.rng.gen_range(0, 100);
+            context.state.0.update_attribute(&Attribute::Luck, luck);
+
+            Ok(context)
+        })
+    }
+
+    fn record() -> (Replay, EngineContext<Game>) {
+        let input = ScriptedSource::new()
+            .tick(vec![InputEvent::Key(Key::Char('d'))])
+            .idle(10)
+            .tick(vec![InputEvent::Key(Key::Char('s')), InputEvent::Key(Key::Right)])
+            .tick(vec![InputEvent::Command("look".to_owned())]);
+        let recorder = Recorder::new(5, |context: &EngineContext<Game>| checksum(&context.state));
+        let replay = recorder.replay();
+
+        let context = builder(new_game())
+            .seed(4711)
+            .input(input)
+            .system(recorder)
+            .build()
+            .unwrap()
+            .run_headless(30)
+            .unwrap();
+
+        let replay = replay.borrow().clone();
+        (replay, context)
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let (replay, recorded) = record();
+
+        assert_eq!(replay.seed, 4711);
+        assert_eq!(replay.inputs.len(), 3);
+        assert_eq!(replay.checksums.len(), 6);
+
+        let mut file = Vec::new();
+        replay.write(&mut file).unwrap();
+        let loaded = Replay::read(&file[..]).unwrap();
+
+        assert_eq!(loaded, replay);
+
+        let replayed = loaded.play(builder(new_game()), |context| checksum(&context.state))
+            .build()
+            .unwrap()
+            .run_headless(30)
+            .unwrap();
+
+        assert_eq!(checksum(&replayed.state), checksum(&recorded.state));
+        assert_eq!(replayed.state.1.get_position(), (2, 1));
+    }
+
+    #[test]
+    fn detect_divergence() {
+        let (replay, _) = record();
+
+        let mut game = new_game();
+        game.1.move_to((1, 1));
+
+        let result = replay.play(builder(game), |context| checksum(&context.state))
+            .build()
+            .unwrap()
+            .run_headless(30);
+
+        match result {
+            Err(EngineError::Aborted(context, error)) => {
+                assert_eq!(context.tick, 0);
+                assert!(error.to_string().starts_with("the replay diverged at tick 0"));
+            }
+            _ => panic!("the divergence was not detected"),
+        }
+    }
+
+    #[test]
+    fn malformed_replay() {
+        let text = "replay 1\nseed 12\ninput 3 key Sideways\n";
+
+        match Replay::read(text.as_bytes()) {
+            Err(ReplayError::Parse(line, _)) => assert_eq!(line, 3),
+            _ => panic!("the malformed replay was accepted"),
+        }
+
+        assert!(Replay::read("seed 12\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use character::Attribute;
+use item::ItemType;
+use types::{AttributeValue, ResourceAmount};
+
+/// A value calculated from the attributes of a character, like the maximum mana
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::character::Attribute;
+/// # use rpg::resource::Formula;
+/// // 10 + 2 * Intelligence + Wisdom / 2
+/// let formula = Formula::new(10)
+///     .plus(Attribute::Intelligence, 2)
+///     .plus_ratio(Attribute::Wisdom, 1, 2);
+///
+/// assert_eq!(formula.evaluate(|_| 5), 22);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Formula {
+    base: AttributeValue,
+    terms: Vec<FormulaTerm>,
+}
+
+impl_serialize_struct!(Formula { base, terms });
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct FormulaTerm {
+    attribute: Attribute,
+    multiplier: AttributeValue,
+    divisor: AttributeValue,
+}
+
+impl Encodable for FormulaTerm {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("FormulaTerm", 3, |e| {
+            e.emit_struct_field("attribute", 0, |e| self.attribute.encode(e))?;
+            e.emit_struct_field("multiplier", 1, |e| self.multiplier.encode(e))?;
+            e.emit_struct_field("divisor", 2, |e| self.divisor.encode(e))
+        })
+    }
+}
+
+/// Terms dividing by `0` are rejected, like in `Formula::plus_ratio`
+impl Decodable for FormulaTerm {
+    fn decode<D: Decoder>(d: &mut D) -> Result<FormulaTerm, D::Error> {
+        d.read_struct("FormulaTerm", 3, |d| {
+            let attribute = d.read_struct_field("attribute", 0, Decodable::decode)?;
+            let multiplier = d.read_struct_field("multiplier", 1, Decodable::decode)?;
+            let divisor = d.read_struct_field("divisor", 2, Decodable::decode)?;
+            if divisor == 0 {
+                return Err(d.error("the divisor of a formula term must not be 0"));
+            }
+
+            Ok(FormulaTerm {
+                attribute,
+                multiplier,
+                divisor,
+            })
+        })
+    }
+}
+
+impl Formula {
+    /// Creates a new instance of `Formula` with the given constant value
+    pub fn new(base: AttributeValue) -> Formula {
+        Formula {
+            base,
+            terms: Vec::new(),
+        }
+    }
+
+    /// A builder method for adding the attribute times the multiplier
+    pub fn plus(self, attribute: Attribute, multiplier: AttributeValue) -> Formula {
+        self.plus_ratio(attribute, multiplier, 1)
+    }
+
+    /// A builder method for adding the attribute times the multiplier divided by the divisor.
+    /// The result of the division is rounded towards zero.
+    ///
+    /// # Panics
+    ///
+    /// **Panics** if the divisor is `0`
+    pub fn plus_ratio(mut self,
+                      attribute: Attribute,
+                      multiplier: AttributeValue,
+                      divisor: AttributeValue)
+                      -> Formula {
+        assert!(divisor != 0, "the divisor of a formula term must not be 0");
+
+        self.terms.push(FormulaTerm {
+            attribute,
+            multiplier,
+            divisor,
+        });
+        self
+    }
+
+    /// Calculates the value of the formula with the given values of the attributes
+    pub fn evaluate<F: Fn(&Attribute) -> AttributeValue>(&self, attribute: F) -> AttributeValue {
+        self.terms.iter().fold(self.base, |value, term| {
+            value + attribute(&term.attribute) * term.multiplier / term.divisor
+        })
+    }
+}
+
+/// The amount of a resource restored by consuming an item of a certain type
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Restoration {
+    item_type: ItemType,
+    amount: ResourceAmount,
+}
+
+impl_serialize_struct!(Restoration { item_type, amount });
+
+/// A resource of a character, like mana or stamina, that is spent by abilities. The maximum
+/// and the amount regenerated per tick are formulas over the attributes of the character.
+///
+/// The pools are usually used through the character, see `Character::add_resource`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ResourcePool {
+    name: String,
+    maximum: Formula,
+    regeneration: Formula,
+    restorations: Vec<Restoration>,
+    current: ResourceAmount,
+}
+
+impl_serialize_struct!(ResourcePool {
+    name,
+    maximum,
+    regeneration,
+    restorations,
+    current,
+});
+
+impl ResourcePool {
+    /// Creates a new, empty instance of `ResourcePool` that doesn't regenerate
+    pub fn new(name: &str, maximum: Formula) -> ResourcePool {
+        ResourcePool {
+            name: name.to_owned(),
+            maximum,
+            regeneration: Formula::new(0),
+            restorations: Vec::new(),
+            current: 0,
+        }
+    }
+
+    /// Mana for casting spells. The maximum is `10 + 2 * Intelligence + Wisdom`, one point
+    /// per five wisdom is regenerated and potions restore 20 points.
+    pub fn mana() -> ResourcePool {
+        ResourcePool::new("mana",
+                          Formula::new(10)
+                              .plus(Attribute::Intelligence, 2)
+                              .plus(Attribute::Wisdom, 1))
+            .regeneration(Formula::new(0).plus_ratio(Attribute::Wisdom, 1, 5))
+            .restored_by(ItemType::ConsumablePotion, 20)
+    }
+
+    /// Stamina for physical abilities. The maximum is `20 + 2 * Willpower`, one point plus one
+    /// per ten constitution is regenerated and food restores 15 points.
+    pub fn stamina() -> ResourcePool {
+        ResourcePool::new("stamina", Formula::new(20).plus(Attribute::Willpower, 2))
+            .regeneration(Formula::new(1).plus_ratio(Attribute::Constitution, 1, 10))
+            .restored_by(ItemType::ConsumableFood, 15)
+    }
+
+    /// A builder method for setting the amount regenerated per tick
+    pub fn regeneration(mut self, regeneration: Formula) -> ResourcePool {
+        self.regeneration = regeneration;
+        self
+    }
+
+    /// A builder method for restoring the given amount whenever an item of the given type is
+    /// consumed
+    pub fn restored_by(mut self, item_type: ItemType, amount: ResourceAmount) -> ResourcePool {
+        self.restorations.retain(|restoration| restoration.item_type != item_type);
+        self.restorations.push(Restoration {
+            item_type,
+            amount,
+        });
+        self
+    }
+
+    /// Returns the name of the resource
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the current amount of the resource
+    pub fn current(&self) -> ResourceAmount {
+        self.current
+    }
+
+    /// Returns the maximum amount of the resource with the given values of the attributes
+    pub fn maximum<F>(&self, attribute: F) -> ResourceAmount
+        where F: Fn(&Attribute) -> AttributeValue
+    {
+        self.maximum.evaluate(attribute).max(0) as ResourceAmount
+    }
+
+    /// Returns the amount restored by consuming an item of the given type
+    pub fn restoration(&self, item_type: &ItemType) -> ResourceAmount {
+        self.restorations
+            .iter()
+            .find(|restoration| restoration.item_type == *item_type)
+            .map_or(0, |restoration| restoration.amount)
+    }
+
+    /// Removes the given amount from the pool. If there is not enough left, nothing is removed
+    /// and `false` is returned.
+    pub fn spend(&mut self, amount: ResourceAmount) -> bool {
+        if amount > self.current {
+            return false;
+        }
+
+        self.current -= amount;
+        true
+    }
+
+    /// Adds the given amount to the pool, but not above the maximum. Returns the amount that was
+    /// actually restored.
+    pub fn restore<F>(&mut self, amount: ResourceAmount, attribute: F) -> ResourceAmount
+        where F: Fn(&Attribute) -> AttributeValue
+    {
+        let current = self.current.saturating_add(amount).min(self.maximum(attribute));
+        let restored = current.saturating_sub(self.current);
+        self.current = self.current.max(current);
+        restored
+    }
+
+    /// Restores the amount regenerated per tick and returns it
+    pub fn regenerate<F>(&mut self, attribute: F) -> ResourceAmount
+        where F: Fn(&Attribute) -> AttributeValue
+    {
+        let amount = self.regeneration.evaluate(&attribute).max(0) as ResourceAmount;
+        self.restore(amount, attribute)
+    }
+
+    /// Fills the pool up to its maximum
+    pub fn fill<F>(&mut self, attribute: F)
+        where F: Fn(&Attribute) -> AttributeValue
+    {
+        self.current = self.maximum(attribute);
+    }
+
+    /// Lowers the current amount to the maximum if it is above it
+    pub fn limit<F>(&mut self, attribute: F)
+        where F: Fn(&Attribute) -> AttributeValue
+    {
+        self.current = self.current.min(self.maximum(attribute));
+    }
+}
+
+/// Something a character can do by spending resources, like casting a spell
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ability {
+    /// The name of the ability
+    pub name: String,
+    /// The names of the resources spent and their amounts
+    pub costs: Vec<(String, ResourceAmount)>,
+}
+
+impl_serialize_struct!(Ability { name, costs });
+
+impl Ability {
+    /// Creates a new instance of `Ability` without any costs
+    pub fn new(name: &str) -> Ability {
+        Ability {
+            name: name.to_owned(),
+            costs: Vec::new(),
+        }
+    }
+
+    /// A builder method for adding a cost
+    pub fn cost(mut self, resource: &str, amount: ResourceAmount) -> Ability {
+        self.costs.push((resource.to_owned(), amount));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::json;
+
+    fn attribute(attribute: &Attribute) -> AttributeValue {
+        match *attribute {
+            Attribute::Intelligence => 10,
+            Attribute::Wisdom => 12,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn formulas() {
+        assert_eq!(Formula::new(3).evaluate(attribute), 3);
+        assert_eq!(Formula::new(0)
+                       .plus(Attribute::Intelligence, -1)
+                       .plus_ratio(Attribute::Wisdom, 1, 5)
+                       .evaluate(attribute),
+                   -8);
+    }
+
+    #[test]
+    fn save_formulas() {
+        let formula = Formula::new(2).plus_ratio(Attribute::Wisdom, 1, 5);
+        let save = json::encode(&formula).unwrap();
+        let loaded: Formula = json::decode(&save).unwrap();
+
+        assert_eq!(loaded, formula);
+        assert!(save.contains(r#""divisor":5"#));
+        match json::decode::<Formula>(&save.replace(r#""divisor":5"#, r#""divisor":0"#)) {
+            Err(json::DecoderError::ApplicationError(error)) => {
+                assert_eq!(error, "the divisor of a formula term must not be 0")
+            }
+            _ => panic!("a formula dividing by 0 was loaded"),
+        }
+    }
+
+    #[test]
+    fn pools() {
+        let mut mana = ResourcePool::mana();
+        assert_eq!(mana.current(), 0);
+        assert_eq!(mana.maximum(attribute), 42);
+        assert_eq!(mana.restoration(&ItemType::ConsumablePotion), 20);
+        assert_eq!(mana.restoration(&ItemType::ConsumableFood), 0);
+
+        assert_eq!(mana.regenerate(attribute), 2);
+        assert!(!mana.spend(3));
+        assert!(mana.spend(2));
+        assert_eq!(mana.restore(100, attribute), 42);
+
+        mana.limit(|_| 0);
+        assert_eq!(mana.current(), 10);
+        assert_eq!(mana.restore(1, |_| 0), 0);
+        mana.fill(attribute);
+        assert_eq!(mana.current(), 42);
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+
+use rustc_serialize::json::{Json, Object};
+
+use super::{SaveError, SAVE_VERSION};
+
+/// Upgrades the saved context by one version of the save format
+pub type Migration = fn(Json) -> Result<Json, SaveError>;
+
+/// The migrations of the save format, oldest first. The migration at index `i` upgrades a save
+/// from version `i + 1` to version `i + 2`. A new migration has to be appended whenever a saved
+/// structure changes, together with an increase of `SAVE_VERSION` and a fixture save.
+pub const MIGRATIONS: &[Migration] = &[entity_behaviours,
+                                        entity_state,
+                                        character_regeneration,
+                                        character_resources,
+                                        character_progression,
+                                        character_effects];
+
+/// Upgrades a saved context of the given version step by step to the current `SAVE_VERSION`
+pub fn migrate(mut context: Json, version: u64) -> Result<Json, SaveError> {
+    if version == 0 || version > u64::from(SAVE_VERSION) {
+        return Err(SaveError::UnsupportedVersion(Some(version)));
+    }
+
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        context = migration(context)?;
+    }
+
+    Ok(context)
+}
+
+/// Version 2: entities save their behaviours. Entities of older saves had none.
+fn entity_behaviours(mut context: Json) -> Result<Json, SaveError> {
+    visit_entities(&mut context, &mut |entity| {
+        if has_keys(entity, &["name"]) {
+            entity.insert("behaviours".to_owned(), Json::Array(Vec::new()));
+        }
+    });
+    Ok(context)
+}
+
+/// Version 3: entities save their state. Entities of older saves had an empty one.
+fn entity_state(mut context: Json) -> Result<Json, SaveError> {
+    visit_entities(&mut context, &mut |entity| {
+        if has_keys(entity, &["behaviours", "name"]) {
+            let mut state = BTreeMap::new();
+            state.insert("values".to_owned(), Json::Object(BTreeMap::new()));
+            entity.insert("state".to_owned(), Json::Object(state));
+        }
+    });
+    Ok(context)
+}
+
+/// Version 4: characters save their regeneration. Characters of older saves didn't regenerate.
+fn character_regeneration(mut context: Json) -> Result<Json, SaveError> {
+    let keys = ["armor_slot_chest",
+                "armor_slot_feet",
+                "armor_slot_head",
+                "armor_slot_legs",
+                "attributes",
+                "health",
+                "inventory",
+                "name",
+                "weapon_slot_left",
+                "weapon_slot_right"];
+
+    visit_objects(&mut context, &mut |object| {
+        if has_keys(object, &keys) {
+            object.insert("regeneration".to_owned(), Json::U64(0));
+        }
+    });
+    Ok(context)
+}
+
+/// Version 5: characters save their resource pools. Characters of older saves had none.
+fn character_resources(mut context: Json) -> Result<Json, SaveError> {
+    let keys = ["armor_slot_chest",
+                "armor_slot_feet",
+                "armor_slot_head",
+                "armor_slot_legs",
+                "attributes",
+                "health",
+                "inventory",
+                "name",
+                "regeneration",
+                "weapon_slot_left",
+                "weapon_slot_right"];
+
+    visit_objects(&mut context, &mut |object| {
+        if has_keys(object, &keys) {
+            object.insert("resources".to_owned(), Json::Array(Vec::new()));
+        }
+    });
+    Ok(context)
+}
+
+/// Version 6: characters save their progression. Characters of older saves were at level 1 of
+/// the default progression.
+fn character_progression(mut context: Json) -> Result<Json, SaveError> {
+    let keys = ["armor_slot_chest",
+                "armor_slot_feet",
+                "armor_slot_head",
+                "armor_slot_legs",
+                "attributes",
+                "health",
+                "inventory",
+                "name",
+                "regeneration",
+                "resources",
+                "weapon_slot_left",
+                "weapon_slot_right"];
+    let progression = Json::from_str(r#"{"curve":{"variant":"Polynomial","fields":[100,2]},
+                                         "max_level":50,
+                                         "points_per_level":3,
+                                         "growth":[{"attribute":"Constitution",
+                                                    "amount":2,
+                                                    "every":1}]}"#)?;
+
+    visit_objects(&mut context, &mut |object| {
+        if has_keys(object, &keys) {
+            object.insert("experience".to_owned(), Json::U64(0));
+            object.insert("level".to_owned(), Json::U64(1));
+            object.insert("attribute_points".to_owned(), Json::U64(0));
+            object.insert("progression".to_owned(), progression.clone());
+        }
+    });
+    Ok(context)
+}
+
+/// Version 7: characters save their effects. Characters of older saves had none. The growth
+/// with the level is now a modifier instead of being added to the attributes, so it is
+/// subtracted from them.
+fn character_effects(mut context: Json) -> Result<Json, SaveError> {
+    let keys = ["armor_slot_chest",
+                "armor_slot_feet",
+                "armor_slot_head",
+                "armor_slot_legs",
+                "attribute_points",
+                "attributes",
+                "experience",
+                "health",
+                "inventory",
+                "level",
+                "name",
+                "progression",
+                "regeneration",
+                "resources",
+                "weapon_slot_left",
+                "weapon_slot_right"];
+
+    visit_objects(&mut context, &mut |object| {
+        if !has_keys(object, &keys) {
+            return;
+        }
+
+        let level = object.get("level").and_then(Json::as_u64).unwrap_or(1);
+        let growth = object.get("progression")
+            .and_then(|progression| progression.find("growth"))
+            .and_then(Json::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        if let Some(&mut Json::Object(ref mut attributes)) = object.get_mut("attributes") {
+            for growth in &growth {
+                let attribute = growth.find("attribute").and_then(Json::as_string);
+                let amount = growth.find("amount").and_then(Json::as_i64).unwrap_or(0);
+                let every = growth.find("every").and_then(Json::as_u64).unwrap_or(1).max(1);
+                let levels = (level / every - 1 / every) as i64;
+
+                let value = attribute.and_then(|attribute| attributes.get_mut(attribute));
+                if let Some(value) = value {
+                    *value = Json::I64(value.as_i64().unwrap_or(0) - amount * levels);
+                }
+            }
+        }
+
+        object.insert("effects".to_owned(), Json::Array(Vec::new()));
+    });
+    Ok(context)
+}
+
+/// Returns `true` if the object has exactly the given keys, which have to be sorted
+fn has_keys(object: &Object, keys: &[&str]) -> bool {
+    object.len() == keys.len() && object.keys().zip(keys).all(|(key, other)| key == other)
+}
+
+/// Calls `f` for every entity in the given value. Entities are only saved in the fields of a
+/// `World2d`, objects elsewhere in the state of a game can look the same.
+fn visit_entities<F: FnMut(&mut Object)>(value: &mut Json, f: &mut F) {
+    visit_objects(value, &mut |object| {
+        if has_keys(object, &["entity", "field_type", "height"]) {
+            if let Some(&mut Json::Object(ref mut entity)) = object.get_mut("entity") {
+                f(entity);
+            }
+        }
+    });
+}
+
+/// Calls `f` for every object in the given value, parents before their children
+fn visit_objects<F: FnMut(&mut Object)>(value: &mut Json, f: &mut F) {
+    match *value {
+        Json::Object(ref mut object) => {
+            f(object);
+            for value in object.values_mut() {
+                visit_objects(value, f);
+            }
+        }
+        Json::Array(ref mut array) => {
+            for value in array {
+                visit_objects(value, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migration_per_version() {
+        assert_eq!(MIGRATIONS.len() + 1, SAVE_VERSION as usize);
+    }
+
+    #[test]
+    fn add_behaviours_to_entities() {
+        let old = Json::from_str(r#"{"state":[{"entity":{"name":"Troll"},"field_type":"Grass",
+                                               "height":0},
+                                              {"entity":null,"field_type":"Grass","height":0},
+                                              {"name":"Hero","health":30}]}"#);
+        let new = Json::from_str(r#"{"state":[{"entity":{"name":"Troll","behaviours":[]},
+                                               "field_type":"Grass","height":0},
+                                              {"entity":null,"field_type":"Grass","height":0},
+                                              {"name":"Hero","health":30}]}"#);
+
+        assert_eq!(entity_behaviours(old.unwrap()).unwrap(), new.unwrap());
+    }
+
+    #[test]
+    fn keep_objects_looking_like_entities() {
+        let old = r#"{"state":{"pet":{"name":"Rex"},"entity":{"name":"Troll"}}}"#;
+
+        assert_eq!(entity_behaviours(Json::from_str(old).unwrap()).unwrap(),
+                   Json::from_str(old).unwrap());
+    }
+
+    #[test]
+    fn add_state_to_entities() {
+        let old = Json::from_str(r#"[{"entity":{"name":"Troll","behaviours":[]},
+                                      "field_type":"Grass","height":0},
+                                     {"name":"Hero","behaviours":[]}]"#);
+        let new = Json::from_str(r#"[{"entity":{"name":"Troll","behaviours":[],
+                                                "state":{"values":{}}},
+                                      "field_type":"Grass","height":0},
+                                     {"name":"Hero","behaviours":[]}]"#);
+
+        assert_eq!(entity_state(old.unwrap()).unwrap(), new.unwrap());
+    }
+
+    #[test]
+    fn add_regeneration_to_characters() {
+        let character = r#"{"armor_slot_chest":null,"armor_slot_feet":null,"armor_slot_head":null,
+                            "armor_slot_legs":null,"attributes":{},"health":30,"inventory":{},
+                            "name":"Hero","weapon_slot_left":null,"weapon_slot_right":null"#;
+        let old = Json::from_str(&format!("[{}}},{{\"name\":\"Troll\"}}]", character));
+        let new = Json::from_str(&format!("[{},\"regeneration\":0}},{{\"name\":\"Troll\"}}]",
+                                          character));
+
+        assert_eq!(character_regeneration(old.unwrap()).unwrap(), new.unwrap());
+    }
+
+    #[test]
+    fn add_resources_to_characters() {
+        let character = r#"{"armor_slot_chest":null,"armor_slot_feet":null,"armor_slot_head":null,
+                            "armor_slot_legs":null,"attributes":{},"health":30,"inventory":{},
+                            "name":"Hero","regeneration":0,"weapon_slot_left":null,
+                            "weapon_slot_right":null"#;
+        let old = Json::from_str(&format!("{}}}", character));
+        let new = Json::from_str(&format!("{},\"resources\":[]}}", character));
+
+        assert_eq!(character_resources(old.unwrap()).unwrap(), new.unwrap());
+    }
+
+    #[test]
+    fn add_progression_to_characters() {
+        use rustc_serialize::Decodable;
+        use rustc_serialize::json::Decoder;
+
+        use progression::Progression;
+
+        let character = r#"{"armor_slot_chest":null,"armor_slot_feet":null,"armor_slot_head":null,
+                            "armor_slot_legs":null,"attributes":{},"health":30,"inventory":{},
+                            "name":"Hero","regeneration":0,"resources":[],
+                            "weapon_slot_left":null,"weapon_slot_right":null}"#;
+        let new = character_progression(Json::from_str(character).unwrap()).unwrap();
+        let progression = new.find("progression").cloned().unwrap();
+
+        assert_eq!(new.find("level"), Some(&Json::U64(1)));
+        assert_eq!(Progression::decode(&mut Decoder::new(progression)).unwrap(),
+                   Progression::default());
+    }
+
+    #[test]
+    fn add_effects_to_characters() {
+        let character = r#"{"armor_slot_chest":null,"armor_slot_feet":null,"armor_slot_head":null,
+                            "armor_slot_legs":null,"attribute_points":0,
+                            "attributes":{"Constitution":38,"Luck":3},"experience":1600,
+                            "health":30,"inventory":{},"level":5,"name":"Hero",
+                            "progression":{"growth":[{"attribute":"Constitution","amount":2,
+                                                      "every":1},
+                                                     {"attribute":"Luck","amount":1,
+                                                      "every":2}]},
+                            "regeneration":0,"resources":[],"weapon_slot_left":null,
+                            "weapon_slot_right":null}"#;
+        let new = character_effects(Json::from_str(character).unwrap()).unwrap();
+
+        assert_eq!(new.find_path(&["attributes", "Constitution"]), Some(&Json::I64(30)));
+        assert_eq!(new.find_path(&["attributes", "Luck"]), Some(&Json::I64(1)));
+        assert_eq!(new.find("effects"), Some(&Json::Array(Vec::new())));
+    }
+
+    #[test]
+    fn unsupported_versions() {
+        for &version in &[0, u64::from(SAVE_VERSION) + 1] {
+            match migrate(Json::Null, version) {
+                Err(SaveError::UnsupportedVersion(Some(v))) => assert_eq!(v, version),
+                _ => panic!("version {} was migrated", version),
+            }
+        }
+    }
+}
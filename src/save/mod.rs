@@ -0,0 +1,493 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use rustc_serialize::json::{self, Json};
+use rustc_serialize::{Decodable, Encodable, Encoder};
+
+use engine::EngineContext;
+use entity;
+
+/// Upgrading saves of older versions
+pub mod migration;
+
+/// The version of the save file format. Stored in every save to be able to migrate old ones.
+pub const SAVE_VERSION: u32 = 7;
+
+/// The file extension of the saves in a `SaveSlots` directory
+const EXTENSION: &str = "json";
+
+/// An error while saving or loading a game
+#[derive(Debug)]
+pub enum SaveError {
+    /// Reading or writing the save failed
+    Io(io::Error),
+    /// The context could not be encoded as JSON
+    Encode(json::EncoderError),
+    /// The save is not valid JSON
+    Parse(json::ParserError),
+    /// The save does not contain a valid context
+    Decode(json::DecoderError),
+    /// The save has no version or one this version of the crate can't read
+    UnsupportedVersion(Option<u64>),
+    /// The name of a save slot must not be empty and may only contain letters, digits, `-`
+    /// and `_`
+    InvalidSlot(String),
+    /// The entity with the given name has a behaviour that can't be saved
+    Unsaveable(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref error) => write!(f, "{}", error),
+            SaveError::Encode(ref error) => write!(f, "{}", error),
+            SaveError::Parse(ref error) => write!(f, "{}", error),
+            SaveError::Decode(ref error) => write!(f, "{}", error),
+            SaveError::UnsupportedVersion(Some(version)) => {
+                write!(f, "unsupported save version {}", version)
+            }
+            SaveError::UnsupportedVersion(None) => write!(f, "the save has no version"),
+            SaveError::InvalidSlot(ref slot) => write!(f, "invalid save slot {:?}", slot),
+            SaveError::Unsaveable(ref name) => {
+                write!(f, "the entity {:?} has a behaviour that can't be saved", name)
+            }
+        }
+    }
+}
+
+impl Error for SaveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SaveError::Io(ref error) => Some(error),
+            SaveError::Encode(ref error) => Some(error),
+            SaveError::Parse(ref error) => Some(error),
+            SaveError::Decode(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> SaveError {
+        SaveError::Io(error)
+    }
+}
+
+impl From<json::EncoderError> for SaveError {
+    fn from(error: json::EncoderError) -> SaveError {
+        SaveError::Encode(error)
+    }
+}
+
+impl From<json::ParserError> for SaveError {
+    fn from(error: json::ParserError) -> SaveError {
+        SaveError::Parse(error)
+    }
+}
+
+impl From<json::DecoderError> for SaveError {
+    fn from(error: json::DecoderError) -> SaveError {
+        SaveError::Decode(error)
+    }
+}
+
+/// The top level object of a save, wrapping the context together with the format version
+struct SaveFile<'a, S: 'a> {
+    context: &'a EngineContext<S>,
+}
+
+impl<'a, S: Encodable> Encodable for SaveFile<'a, S> {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("SaveFile", 2, |e| {
+            e.emit_struct_field("version", 0, |e| SAVE_VERSION.encode(e))?;
+            e.emit_struct_field("context", 1, |e| self.context.encode(e))
+        })
+    }
+}
+
+/// Encodes the given context as a JSON save. Fails with `SaveError::Unsaveable` if an entity
+/// has a behaviour that can't be saved, as it couldn't be restored with its full behaviour chain.
+///
+/// Afterwards the random number generator of the context is split, so the game continues with
+/// the same random numbers as a game loaded from the save. See `EngineContext::split_rng`.
+pub fn encode<S: Encodable>(context: &mut EngineContext<S>) -> Result<String, SaveError> {
+    entity::take_unsaveable();
+    let save = json::encode(&SaveFile { context });
+
+    // The entities are checked while they are encoded, see `Entity::can_be_saved`
+    if let Some(name) = entity::take_unsaveable().into_iter().next() {
+        return Err(SaveError::Unsaveable(name));
+    }
+
+    let save = save?;
+    context.split_rng();
+    Ok(save)
+}
+
+/// Decodes a context from a JSON save created by `encode`. Saves of older versions are
+/// migrated to the current one first.
+pub fn decode<S: Decodable>(save: &str) -> Result<EngineContext<S>, SaveError> {
+    let save = Json::from_str(save)?;
+
+    let version = match save.find("version").and_then(Json::as_u64) {
+        Some(version) => version,
+        None => return Err(SaveError::UnsupportedVersion(None)),
+    };
+
+    let context = save.find("context").cloned().unwrap_or(Json::Null);
+    let context = migration::migrate(context, version)?;
+    Ok(Decodable::decode(&mut json::Decoder::new(context))?)
+}
+
+/// A directory of named saves. Every slot is stored in its own JSON file.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use rpg::engine::EngineContext;
+/// # use rpg::save::SaveSlots;
+/// let slots = SaveSlots::new("saves");
+/// let mut context = EngineContext::new(42);
+///
+/// slots.save("quicksave", &mut context).unwrap();
+/// let loaded: EngineContext<i32> = slots.load("quicksave").unwrap();
+///
+/// assert_eq!(loaded.state, 42);
+/// assert_eq!(slots.list().unwrap(), vec!["quicksave"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SaveSlots {
+    directory: PathBuf,
+}
+
+impl SaveSlots {
+    /// Creates a new instance of `SaveSlots` storing the saves in the given directory. The
+    /// directory is created with the first save.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> SaveSlots {
+        SaveSlots { directory: directory.into() }
+    }
+
+    /// Saves the context in the given slot, replacing the previous save of the slot
+    pub fn save<S: Encodable>(&self,
+                              slot: &str,
+                              context: &mut EngineContext<S>)
+                              -> Result<(), SaveError> {
+        let path = self.path(slot)?;
+        let save = encode(context)?;
+
+        fs::create_dir_all(&self.directory)?;
+        let mut file = File::create(path)?;
+        file.write_all(save.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Loads the context saved in the given slot
+    pub fn load<S: Decodable>(&self, slot: &str) -> Result<EngineContext<S>, SaveError> {
+        let mut save = String::new();
+        File::open(self.path(slot)?)?.read_to_string(&mut save)?;
+        decode(&save)
+    }
+
+    /// Returns `true` if there is a save in the given slot
+    pub fn exists(&self, slot: &str) -> bool {
+        self.path(slot).map(|path| path.is_file()).unwrap_or(false)
+    }
+
+    /// Returns the names of all slots containing a save, sorted alphabetically
+    pub fn list(&self) -> Result<Vec<String>, SaveError> {
+        if !self.directory.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut slots = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+
+            if let Some(slot) = path.file_stem().and_then(|stem| stem.to_str()) {
+                if is_valid_slot(slot) {
+                    slots.push(slot.to_owned());
+                }
+            }
+        }
+
+        slots.sort();
+        Ok(slots)
+    }
+
+    /// Deletes the save in the given slot
+    pub fn delete(&self, slot: &str) -> Result<(), SaveError> {
+        fs::remove_file(self.path(slot)?)?;
+        Ok(())
+    }
+
+    fn path(&self, slot: &str) -> Result<PathBuf, SaveError> {
+        if !is_valid_slot(slot) {
+            return Err(SaveError::InvalidSlot(slot.to_owned()));
+        }
+
+        Ok(self.directory.join(format!("{}.{}", slot, EXTENSION)))
+    }
+}
+
+fn is_valid_slot(slot: &str) -> bool {
+    !slot.is_empty() && slot.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    use rand::Rng;
+
+    use behaviour::{register_tree_action, register_tree_condition, Behaviour, BehaviourResult,
+                    BehaviourTree, DefaultResponse, Node, Status, TickContext};
+    use character::{Attribute, Character};
+    use engine::{EngineBuilder, EngineContext};
+    use entity::{Entity, EntityState};
+    use event::Event;
+    use item::{Item, ItemInfluence, ItemRarity, ItemType};
+    use world::World;
+    use world::campaign::Campaign;
+    use world::two_dimensional::{Field, FieldType, World2d};
+
+    struct Game {
+        hero: Character,
+        world: World2d,
+        campaign: Campaign<World2d>,
+    }
+
+    impl_serialize_struct!(Game {
+        hero,
+        world,
+        campaign,
+    });
+
+    fn game() -> Game {
+        let mut hero = Character::new("Hero");
+        hero.update_attribute(&Attribute::Strength, 7);
+        hero.inventory_mut()
+            .add_item(Item {
+                name: "Potion".to_owned(),
+                item_type: ItemType::ConsumablePotion,
+                influence: Some(ItemInfluence::new(Attribute::Luck, 2)),
+                stack_size: 5,
+                rarity: ItemRarity::Rare,
+            })
+            .unwrap();
+
+        let mut troll = Entity::new("Troll");
+        troll.append_behaviour(DefaultResponse::new("Pay the toll!"));
+        troll.state_mut().set("toll", 5);
+
+        let mut world = World2d::new("Cave", (3, 2));
+        world.set_field(Field::new(FieldType::Stone).height(2).entity(troll), (1, 1));
+        world.move_to((1, 0));
+
+        let mut campaign = Campaign::new("Adventure");
+        campaign.add_world(World2d::new("Town", (2, 2)));
+
+        Game {
+            hero,
+            world,
+            campaign,
+        }
+    }
+
+    #[derive(Clone)]
+    struct Sleeping;
+
+    impl Behaviour for Sleeping {
+        fn handle_event(&self, _: &Event, _: &mut EntityState) -> BehaviourResult {
+            BehaviourResult::Stop
+        }
+    }
+
+    fn far_from_player(context: &TickContext, _: &EntityState) -> bool {
+        context.distance_to_player() > 1
+    }
+
+    fn walk_to_player(context: &mut TickContext, _: &mut EntityState) -> Status {
+        let direction = context.direction_to_player();
+        context.walk(direction);
+        Status::Running
+    }
+
+    // Compares the parsed JSON, the order of the attributes in a character is not fixed
+    fn to_json<T: Encodable>(value: &T) -> Json {
+        Json::from_str(&json::encode(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut context = EngineContext::new(game());
+        context.tick = 17;
+        context.reseed(99);
+        context.disable_system("ai");
+
+        let save = encode(&mut context).unwrap();
+        let mut loaded: EngineContext<Game> = decode(&save).unwrap();
+
+        assert_eq!(to_json(&loaded.state), to_json(&context.state));
+        assert_eq!(loaded.tick, 17);
+        assert_eq!(loaded.seed, 99);
+        assert!(!loaded.is_system_enabled("ai"));
+        assert_eq!(loaded.rng.gen::<u64>(), context.rng.gen::<u64>());
+    }
+
+    #[test]
+    fn unsaveable_entity() {
+        let mut game = game();
+        let mut dragon = Entity::new("Dragon");
+        dragon.append_behaviour(Sleeping);
+        game.world.set_field(Field::new(FieldType::Stone).entity(dragon), (2, 1));
+
+        let mut context = EngineContext::new(game);
+        let mut rng = context.rng.clone();
+
+        match encode(&mut context) {
+            Err(SaveError::Unsaveable(name)) => assert_eq!(name, "Dragon"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        // The random number generator is only split by successful saves
+        assert_eq!(context.rng.gen::<u64>(), rng.gen::<u64>());
+
+        let directory = env::temp_dir().join(format!("rpg-unsaveable-{}", process::id()));
+        let slots = SaveSlots::new(&directory);
+
+        assert!(slots.save("dragon", &mut context).is_err());
+        assert!(!slots.exists("dragon"));
+
+        context.state.world.set_field(Field::new(FieldType::Stone), (2, 1));
+
+        assert!(encode(&mut context).is_ok());
+    }
+
+    #[test]
+    fn save_behaviour_tree() {
+        register_tree_condition("far_from_player", far_from_player);
+        register_tree_action("walk_to_player", walk_to_player);
+
+        let mut wolf = Entity::new("Wolf");
+        wolf.append_behaviour(BehaviourTree::new(Node::sequence(vec![
+            Node::named_condition("far_from_player"),
+            Node::named_action("walk_to_player"),
+        ])));
+
+        let mut game = game();
+        game.world = World2d::new("Forest", (6, 1));
+        game.world.set_field(Field::new(FieldType::Grass).entity(wolf), (4, 0));
+        game.world.move_to((0, 0));
+
+        let save = encode(&mut EngineContext::new(game)).unwrap();
+        let mut loaded: EngineContext<Game> = decode(&save).unwrap();
+        loaded.state.world.tick(&loaded.state.hero, &mut loaded.rng);
+
+        assert!(loaded.state.world.get_field((4, 0)).unwrap().entity.is_none());
+        assert!(loaded.state.world.get_field((3, 0)).unwrap().entity.is_some());
+    }
+
+    #[test]
+    fn load_fixtures() {
+        let fixtures = [include_str!("fixtures/v1.json"),
+                        include_str!("fixtures/v2.json"),
+                        include_str!("fixtures/v3.json"),
+                        include_str!("fixtures/v4.json"),
+                        include_str!("fixtures/v5.json"),
+                        include_str!("fixtures/v6.json"),
+                        include_str!("fixtures/v7.json")];
+
+        assert_eq!(fixtures.len(), SAVE_VERSION as usize);
+
+        for (index, fixture) in fixtures.iter().enumerate() {
+            let context: EngineContext<Game> = decode(fixture).unwrap();
+            let field = context.state.world.get_field((1, 1)).unwrap();
+            let mut troll = field.entity.clone().unwrap();
+            // Entities of version 1 could not save their behaviours
+            let response = match index {
+                0 => Event::Nothing,
+                _ => Event::Tell("Pay the toll!".to_owned()),
+            };
+
+            assert_eq!(context.tick, 17);
+            assert_eq!(context.seed, 99);
+            assert_eq!(context.state.hero.get_attribute_value(&Attribute::Strength), 7);
+            assert_eq!(context.state.world.get_position(), (1, 0));
+            assert_eq!(troll.name(), "Troll");
+            assert_eq!(troll.send_event(Event::Nothing), response);
+            assert_eq!(troll.state().get_int("toll"), if index >= 2 { Some(5) } else { None });
+            // Characters of versions before 5 had no resources
+            let mana = if index >= 4 { Some(25) } else { None };
+            assert_eq!(context.state.hero.resource("mana"), mana);
+        }
+    }
+
+    #[test]
+    fn unsupported_version() {
+        match decode::<u8>(r#"{"version":0,"context":{}}"#) {
+            Err(SaveError::UnsupportedVersion(Some(0))) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        match decode::<u8>(r#"{"context":{}}"#) {
+            Err(SaveError::UnsupportedVersion(None)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn save_slots() {
+        let directory = env::temp_dir().join(format!("rpg-save-slots-{}", process::id()));
+        let slots = SaveSlots::new(&directory);
+
+        assert!(slots.list().unwrap().is_empty());
+        assert!(slots.save("../escape", &mut EngineContext::new(0)).is_err());
+
+        slots.save("second", &mut EngineContext::new(2)).unwrap();
+        slots.save("first", &mut EngineContext::new(1)).unwrap();
+
+        assert_eq!(slots.list().unwrap(), vec!["first", "second"]);
+        assert_eq!(slots.load::<i32>("second").unwrap().state, 2);
+
+        slots.delete("second").unwrap();
+
+        assert!(!slots.exists("second"));
+        assert_eq!(slots.list().unwrap(), vec!["first"]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn resume_saved_game() {
+        let run = |builder: EngineBuilder<Vec<u32>>, ticks| {
+            builder.update(|mut context| {
+                    let roll = context.rng.gen_range(0, 100);
+                    context.state.push(roll);
+                    Ok(context)
+                })
+                .build()
+                .unwrap()
+                .run_headless(ticks)
+                .unwrap()
+        };
+
+        let uninterrupted = run(EngineBuilder::with_state(Vec::new()).seed(5), 6);
+
+        let mut halfway = run(EngineBuilder::with_state(Vec::new()).seed(5), 3);
+        let save = encode(&mut halfway).unwrap();
+        let resumed = run(EngineBuilder::resume(decode(&save).unwrap()), 6);
+        let continued = run(EngineBuilder::resume(halfway), 6);
+
+        assert_eq!(resumed.state.len(), 6);
+        assert_eq!(resumed.state, continued.state);
+        assert_eq!(resumed.state[..3], uninterrupted.state[..3]);
+    }
+}
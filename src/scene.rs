@@ -0,0 +1,235 @@
+use engine::{EngineContext, EngineError, EngineResult};
+
+/// The result of updating a scene. Contains the transition the scene stack performs afterwards.
+pub type SceneResult<S = ()> = Result<(EngineContext<S>, Transition<S>), EngineError<S>>;
+
+/// A change of the scene stack requested by the active scene
+pub enum Transition<S = ()> {
+    /// Keep the stack as it is
+    None,
+    /// Put a new scene on top of the active one, e.g. a pause overlay
+    Push(Box<dyn Scene<S>>),
+    /// Remove the active scene and return to the one below
+    Pop,
+    /// Swap the active scene with a new one, e.g. from the title menu to the gameplay
+    Replace(Box<dyn Scene<S>>),
+}
+
+/// A screen of the game like a menu, the gameplay or an inventory screen. Only the scene on top
+/// of the stack is updated.
+pub trait Scene<S = ()> {
+    /// Called when the scene is put on the stack
+    fn enter(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        Ok(context)
+    }
+
+    /// Called when the scene is removed from the stack
+    fn exit(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        Ok(context)
+    }
+
+    /// Called on every update while the scene is on top of the stack
+    fn update(&mut self, context: EngineContext<S>) -> SceneResult<S>;
+
+    /// Called on every drawn frame while the scene is visible
+    fn draw(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        Ok(context)
+    }
+
+    /// Returns `true` if the scene keeps being drawn underneath the scenes pushed on top of it
+    fn draw_when_covered(&self) -> bool {
+        false
+    }
+}
+
+/// A stack of scenes. The topmost scene is the active one.
+pub struct SceneStack<S = ()> {
+    scenes: Vec<Box<dyn Scene<S>>>,
+}
+
+impl<S> SceneStack<S> {
+    /// Creates a new, empty instance of `SceneStack`
+    pub fn new() -> SceneStack<S> {
+        SceneStack { scenes: Vec::new() }
+    }
+
+    /// Returns the amount of scenes on the stack
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// Returns `true` if there is no scene on the stack
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// Enters the given scene and puts it on top of the stack
+    pub fn push(&mut self,
+                mut scene: Box<dyn Scene<S>>,
+                context: EngineContext<S>)
+                -> EngineResult<S> {
+        let context = scene.enter(context)?;
+        self.scenes.push(scene);
+        Ok(context)
+    }
+
+    /// Removes the active scene from the stack and exits it
+    pub fn pop(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        match self.scenes.pop() {
+            Some(mut scene) => scene.exit(context),
+            None => Ok(context),
+        }
+    }
+
+    /// Exits the active scene and puts the given one in its place
+    pub fn replace(&mut self,
+                   scene: Box<dyn Scene<S>>,
+                   context: EngineContext<S>)
+                   -> EngineResult<S> {
+        let context = self.pop(context)?;
+        self.push(scene, context)
+    }
+
+    /// Performs the given transition
+    pub fn apply(&mut self,
+                 transition: Transition<S>,
+                 context: EngineContext<S>)
+                 -> EngineResult<S> {
+        match transition {
+            Transition::None => Ok(context),
+            Transition::Push(scene) => self.push(scene, context),
+            Transition::Pop => self.pop(context),
+            Transition::Replace(scene) => self.replace(scene, context),
+        }
+    }
+
+    /// Updates the active scene and performs the transition it requested
+    pub fn update(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        let (context, transition) = match self.scenes.last_mut() {
+            Some(scene) => scene.update(context)?,
+            None => return Ok(context),
+        };
+
+        self.apply(transition, context)
+    }
+
+    /// Draws the active scene and, from the bottom up, the scenes directly below it that are
+    /// drawn when covered
+    pub fn draw(&mut self, mut context: EngineContext<S>) -> EngineResult<S> {
+        let mut first = self.scenes.len();
+        while first > 1 && self.scenes[first - 2].draw_when_covered() {
+            first -= 1;
+        }
+
+        for scene in self.scenes.iter_mut().skip(first.saturating_sub(1)) {
+            context = scene.draw(context)?;
+        }
+
+        Ok(context)
+    }
+}
+
+impl<S> Default for SceneStack<S> {
+    fn default() -> SceneStack<S> {
+        SceneStack::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::EngineContext;
+
+    type Log = Vec<String>;
+
+    struct Named {
+        name: &'static str,
+        draw_when_covered: bool,
+        next: Option<Transition<Log>>,
+    }
+
+    impl Named {
+        fn new(name: &'static str) -> Named {
+            Named {
+                name,
+                draw_when_covered: false,
+                next: None,
+            }
+        }
+    }
+
+    impl Scene<Log> for Named {
+        fn enter(&mut self, mut context: EngineContext<Log>) -> EngineResult<Log> {
+            context.state.push(format!("enter {}", self.name));
+            Ok(context)
+        }
+
+        fn exit(&mut self, mut context: EngineContext<Log>) -> EngineResult<Log> {
+            context.state.push(format!("exit {}", self.name));
+            Ok(context)
+        }
+
+        fn update(&mut self, mut context: EngineContext<Log>) -> SceneResult<Log> {
+            context.state.push(format!("update {}", self.name));
+            Ok((context, self.next.take().unwrap_or(Transition::None)))
+        }
+
+        fn draw(&mut self, mut context: EngineContext<Log>) -> EngineResult<Log> {
+            context.state.push(format!("draw {}", self.name));
+            Ok(context)
+        }
+
+        fn draw_when_covered(&self) -> bool {
+            self.draw_when_covered
+        }
+    }
+
+    #[test]
+    fn transitions() {
+        let mut stack = SceneStack::new();
+        let mut menu = Named::new("menu");
+        let mut game = Named::new("game");
+
+        game.next = Some(Transition::Push(Box::new(Named::new("pause"))));
+        menu.next = Some(Transition::Replace(Box::new(game)));
+
+        let mut context = EngineContext::new(Vec::new());
+        context = stack.push(Box::new(menu), context).unwrap();
+        context = stack.update(context).unwrap();
+        context = stack.update(context).unwrap();
+
+        assert_eq!(stack.len(), 2);
+
+        context = stack.apply(Transition::Pop, context).unwrap();
+
+        assert_eq!(context.state,
+                   vec!["enter menu",
+                        "update menu",
+                        "exit menu",
+                        "enter game",
+                        "update game",
+                        "enter pause",
+                        "exit pause"]);
+    }
+
+    #[test]
+    fn draw_underneath_overlays() {
+        let mut stack = SceneStack::new();
+        let mut game = Named::new("game");
+        let mut inventory = Named::new("inventory");
+
+        game.draw_when_covered = true;
+        inventory.draw_when_covered = true;
+
+        let mut context = EngineContext::new(Vec::new());
+        context = stack.push(Box::new(Named::new("menu")), context).unwrap();
+        context = stack.push(Box::new(game), context).unwrap();
+        context = stack.push(Box::new(inventory), context).unwrap();
+        context = stack.push(Box::new(Named::new("pause")), context).unwrap();
+        context.state.clear();
+
+        context = stack.draw(context).unwrap();
+
+        assert_eq!(context.state, vec!["draw game", "draw inventory", "draw pause"]);
+    }
+}
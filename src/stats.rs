@@ -0,0 +1,297 @@
+use std::fmt;
+
+use character::Attribute;
+use types::AttributeValue;
+
+/// How a modifier changes an attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModifierKind {
+    /// Adds the value to the attribute
+    Flat,
+    /// Increases the attribute by the value in percent, after all flat modifiers are added
+    Percent,
+}
+
+impl_serialize_enum!(ModifierKind { Flat, Percent });
+
+/// A change of an attribute
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Modifier {
+    /// The attribute that is changed
+    pub attribute: Attribute,
+    /// How the attribute is changed
+    pub kind: ModifierKind,
+    /// The amount or percentage the attribute is changed by, negative values decrease it
+    pub value: AttributeValue,
+}
+
+impl_serialize_struct!(Modifier {
+    attribute,
+    kind,
+    value,
+});
+
+impl Modifier {
+    /// Creates a new flat modifier
+    pub fn flat(attribute: Attribute, value: AttributeValue) -> Modifier {
+        Modifier {
+            attribute,
+            kind: ModifierKind::Flat,
+            value,
+        }
+    }
+
+    /// Creates a new percentage modifier
+    pub fn percent(attribute: Attribute, value: AttributeValue) -> Modifier {
+        Modifier {
+            attribute,
+            kind: ModifierKind::Percent,
+            value,
+        }
+    }
+}
+
+/// A temporary or permanent change of the attributes of a character, like a blessing or poison
+///
+/// # Examples
+///
+/// ```
+/// # use rpg::character::{Attribute, Character};
+/// # use rpg::stats::Effect;
+/// let mut character = Character::new("Michael");
+/// character.add_effect(Effect::new("Rage")
+///     .flat(Attribute::Strength, 5)
+///     .percent(Attribute::Strength, 20)
+///     .duration(2));
+///
+/// // (20 + 5) * 120%
+/// assert_eq!(character.get_attribute_value(&Attribute::Strength), 30);
+///
+/// character.tick_effects();
+/// character.tick_effects();
+///
+/// assert_eq!(character.get_attribute_value(&Attribute::Strength), 20);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Effect {
+    name: String,
+    modifiers: Vec<Modifier>,
+    remaining: Option<u32>,
+}
+
+impl_serialize_struct!(Effect {
+    name,
+    modifiers,
+    remaining,
+});
+
+impl Effect {
+    /// Creates a new instance of `Effect` without modifiers that lasts until it is removed
+    pub fn new(name: &str) -> Effect {
+        Effect {
+            name: name.to_owned(),
+            modifiers: Vec::new(),
+            remaining: None,
+        }
+    }
+
+    /// A builder method for adding a flat modifier
+    pub fn flat(self, attribute: Attribute, value: AttributeValue) -> Effect {
+        self.modifier(Modifier::flat(attribute, value))
+    }
+
+    /// A builder method for adding a percentage modifier
+    pub fn percent(self, attribute: Attribute, value: AttributeValue) -> Effect {
+        self.modifier(Modifier::percent(attribute, value))
+    }
+
+    /// A builder method for adding a modifier
+    pub fn modifier(mut self, modifier: Modifier) -> Effect {
+        self.modifiers.push(modifier);
+        self
+    }
+
+    /// A builder method for letting the effect end after the given amount of ticks
+    pub fn duration(mut self, ticks: u32) -> Effect {
+        self.remaining = Some(ticks);
+        self
+    }
+
+    /// Returns the name of the effect
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the modifiers of the effect
+    pub fn modifiers(&self) -> &[Modifier] {
+        &self.modifiers
+    }
+
+    /// Returns the ticks until the effect ends, or `None` if it lasts until it is removed
+    pub fn remaining(&self) -> Option<u32> {
+        self.remaining
+    }
+
+    /// Counts down the remaining ticks and returns `true` if the effect ended
+    pub fn tick(&mut self) -> bool {
+        match self.remaining {
+            Some(remaining) => {
+                self.remaining = Some(remaining.saturating_sub(1));
+                remaining <= 1
+            }
+            None => false,
+        }
+    }
+}
+
+/// Where a modifier comes from
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ModifierSource {
+    /// The equipped item with the given name
+    Equipment(String),
+    /// The active effect with the given name
+    Effect(String),
+    /// The growth of the attribute with the level of the character
+    Level,
+}
+
+impl fmt::Display for ModifierSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ModifierSource::Equipment(ref name) |
+            ModifierSource::Effect(ref name) => write!(f, "{}", name),
+            ModifierSource::Level => write!(f, "level"),
+        }
+    }
+}
+
+/// A modifier together with where it comes from
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Contribution {
+    /// Where the modifier comes from
+    pub source: ModifierSource,
+    /// How the attribute is changed
+    pub kind: ModifierKind,
+    /// The amount or percentage the attribute is changed by
+    pub value: AttributeValue,
+}
+
+/// The effective value of an attribute and where each point of it comes from. The value is
+/// `(base + flat modifiers) * (100 + percentage modifiers) / 100`, rounded towards zero.
+///
+/// Displaying a breakdown explains it in one line, like `Strength 36 = 20 base + 10 Sword +
+/// 20% Rage`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StatBreakdown {
+    /// The attribute
+    pub attribute: Attribute,
+    /// The value of the attribute without any modifiers
+    pub base: AttributeValue,
+    /// The modifiers of the attribute, in the order they were collected
+    pub contributions: Vec<Contribution>,
+}
+
+impl StatBreakdown {
+    /// Creates a new instance of `StatBreakdown` without contributions
+    pub fn new(attribute: Attribute, base: AttributeValue) -> StatBreakdown {
+        StatBreakdown {
+            attribute,
+            base,
+            contributions: Vec::new(),
+        }
+    }
+
+    /// Adds the modifier if it changes the attribute of the breakdown
+    pub fn add(&mut self, source: &ModifierSource, modifier: &Modifier) {
+        if modifier.attribute == self.attribute && modifier.value != 0 {
+            self.contributions.push(Contribution {
+                source: source.clone(),
+                kind: modifier.kind,
+                value: modifier.value,
+            });
+        }
+    }
+
+    /// Returns the sum of the flat modifiers
+    pub fn flat(&self) -> AttributeValue {
+        self.sum(ModifierKind::Flat)
+    }
+
+    /// Returns the sum of the percentage modifiers
+    pub fn percent(&self) -> AttributeValue {
+        self.sum(ModifierKind::Percent)
+    }
+
+    /// Returns the effective value of the attribute
+    pub fn total(&self) -> AttributeValue {
+        (self.base + self.flat()) * (100 + self.percent()) / 100
+    }
+
+    fn sum(&self, kind: ModifierKind) -> AttributeValue {
+        self.contributions
+            .iter()
+            .filter(|contribution| contribution.kind == kind)
+            .map(|contribution| contribution.value)
+            .sum()
+    }
+}
+
+impl fmt::Display for StatBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} {} = {} base", self.attribute, self.total(), self.base)?;
+
+        for contribution in &self.contributions {
+            let sign = if contribution.value < 0 { "-" } else { "+" };
+            let unit = match contribution.kind {
+                ModifierKind::Flat => "",
+                ModifierKind::Percent => "%",
+            };
+
+            write!(f,
+                   " {} {}{} {}",
+                   sign,
+                   contribution.value.abs(),
+                   unit,
+                   contribution.source)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effect_duration() {
+        let mut lasting = Effect::new("Blessing");
+        let mut short = Effect::new("Poison").duration(2);
+
+        assert!(!lasting.tick());
+        assert!(!short.tick());
+        assert_eq!(short.remaining(), Some(1));
+        assert!(short.tick());
+        assert!(Effect::new("Instant").duration(0).tick());
+    }
+
+    #[test]
+    fn breakdown() {
+        let sword = ModifierSource::Equipment("Sword".to_owned());
+        let rage = ModifierSource::Effect("Rage".to_owned());
+
+        let mut breakdown = StatBreakdown::new(Attribute::Strength, 20);
+        breakdown.add(&sword, &Modifier::flat(Attribute::Strength, 10));
+        breakdown.add(&sword, &Modifier::flat(Attribute::Dexterity, 10));
+        breakdown.add(&ModifierSource::Level, &Modifier::flat(Attribute::Strength, -3));
+        breakdown.add(&rage, &Modifier::percent(Attribute::Strength, 20));
+        breakdown.add(&rage, &Modifier::percent(Attribute::Strength, 0));
+
+        assert_eq!(breakdown.contributions.len(), 3);
+        assert_eq!(breakdown.flat(), 7);
+        assert_eq!(breakdown.percent(), 20);
+        assert_eq!(breakdown.total(), 32);
+        assert_eq!(breakdown.to_string(),
+                   "Strength 32 = 20 base + 10 Sword - 3 level + 20% Rage");
+    }
+}
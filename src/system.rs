@@ -0,0 +1,228 @@
+use engine::{EngineContext, EngineError, EngineResult};
+
+/// A self-contained part of the game logic like combat, AI or quest tracking. Systems are
+/// registered at the `EngineBuilder` and run in every phase of the engine.
+pub trait System<S = ()> {
+    /// The unique name of the system. Used to order and toggle it
+    fn name(&self) -> &str;
+
+    /// The names of the systems that have to run before this one. Systems that are not
+    /// registered are ignored.
+    fn after(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// The names of the systems that have to run after this one. Systems that are not
+    /// registered are ignored.
+    fn before(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Called once when the engine starts
+    fn setup(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        Ok(context)
+    }
+
+    /// Called on every update
+    fn update(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        Ok(context)
+    }
+
+    /// Called on every drawn frame
+    fn draw(&mut self, context: EngineContext<S>) -> EngineResult<S> {
+        Ok(context)
+    }
+}
+
+/// The systems of the engine, sorted by their ordering constraints
+pub struct Systems<S = ()> {
+    systems: Vec<Box<dyn System<S>>>,
+}
+
+impl<S> Systems<S> {
+    /// Sorts the given systems so all ordering constraints are fulfilled. Systems without
+    /// constraints between them keep the order they were registered in.
+    pub fn new(systems: Vec<Box<dyn System<S>>>) -> Result<Systems<S>, EngineError<S>> {
+        let names: Vec<String> = systems.iter().map(|system| system.name().to_owned()).collect();
+
+        for (index, name) in names.iter().enumerate() {
+            if names[..index].contains(name) {
+                return Err(EngineError::DuplicateSystem(name.clone()));
+            }
+        }
+
+        // dependencies[i] contains the indices of the systems that have to run before system i
+        let mut dependencies = vec![Vec::new(); systems.len()];
+        for (index, system) in systems.iter().enumerate() {
+            for name in system.after() {
+                if let Some(other) = names.iter().position(|n| n == name) {
+                    dependencies[index].push(other);
+                }
+            }
+
+            for name in system.before() {
+                if let Some(other) = names.iter().position(|n| n == name) {
+                    dependencies[other].push(index);
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(systems.len());
+        let mut placed = vec![false; systems.len()];
+        while order.len() < systems.len() {
+            let next = (0..systems.len()).find(|&index| {
+                !placed[index] && dependencies[index].iter().all(|&other| placed[other])
+            });
+
+            match next {
+                Some(index) => {
+                    placed[index] = true;
+                    order.push(index);
+                }
+                None => {
+                    let cycle = (0..systems.len())
+                        .filter(|&index| !placed[index])
+                        .map(|index| names[index].clone())
+                        .collect();
+                    return Err(EngineError::SystemCycle(cycle));
+                }
+            }
+        }
+
+        let mut systems: Vec<Option<Box<dyn System<S>>>> = systems.into_iter().map(Some).collect();
+        let systems = order.into_iter().filter_map(|index| systems[index].take()).collect();
+
+        Ok(Systems { systems })
+    }
+
+    /// Returns the names of the systems in the order they are run
+    pub fn names(&self) -> Vec<&str> {
+        self.systems.iter().map(|system| system.name()).collect()
+    }
+
+    /// Returns `true` if there are no systems
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// Runs the setup of all enabled systems
+    pub fn setup(&mut self, mut context: EngineContext<S>) -> EngineResult<S> {
+        for system in &mut self.systems {
+            if context.is_system_enabled(system.name()) {
+                context = system.setup(context)?;
+            }
+        }
+
+        Ok(context)
+    }
+
+    /// Runs the update of all enabled systems
+    pub fn update(&mut self, mut context: EngineContext<S>) -> EngineResult<S> {
+        for system in &mut self.systems {
+            if context.is_system_enabled(system.name()) {
+                context = system.update(context)?;
+            }
+        }
+
+        Ok(context)
+    }
+
+    /// Runs the drawing of all enabled systems
+    pub fn draw(&mut self, mut context: EngineContext<S>) -> EngineResult<S> {
+        for system in &mut self.systems {
+            if context.is_system_enabled(system.name()) {
+                context = system.draw(context)?;
+            }
+        }
+
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::{EngineContext, EngineError};
+
+    struct Named {
+        name: &'static str,
+        after: Vec<&'static str>,
+        before: Vec<&'static str>,
+    }
+
+    impl Named {
+        fn boxed(name: &'static str,
+                 after: Vec<&'static str>,
+                 before: Vec<&'static str>)
+                 -> Box<dyn System<Vec<&'static str>>> {
+            Box::new(Named {
+                name,
+                after,
+                before,
+            })
+        }
+    }
+
+    impl System<Vec<&'static str>> for Named {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn after(&self) -> Vec<&str> {
+            self.after.clone()
+        }
+
+        fn before(&self) -> Vec<&str> {
+            self.before.clone()
+        }
+
+        fn update(&mut self,
+                  mut context: EngineContext<Vec<&'static str>>)
+                  -> EngineResult<Vec<&'static str>> {
+            context.state.push(self.name);
+            Ok(context)
+        }
+    }
+
+    #[test]
+    fn ordering_constraints() {
+        let systems = Systems::new(vec![Named::boxed("quests", vec!["combat"], vec![]),
+                                        Named::boxed("combat", vec![], vec![]),
+                                        Named::boxed("ai", vec![], vec!["combat"]),
+                                        Named::boxed("sound", vec!["missing"], vec![])])
+            .unwrap();
+
+        assert_eq!(systems.names(), vec!["ai", "combat", "quests", "sound"]);
+    }
+
+    #[test]
+    fn invalid_constraints() {
+        match Systems::new(vec![Named::boxed("ai", vec![], vec![]),
+                                Named::boxed("ai", vec![], vec![])]) {
+            Err(EngineError::DuplicateSystem(name)) => assert_eq!(name, "ai"),
+            _ => panic!("duplicate systems were accepted"),
+        }
+
+        match Systems::new(vec![Named::boxed("combat", vec!["ai"], vec![]),
+                                Named::boxed("ai", vec!["combat"], vec![]),
+                                Named::boxed("sound", vec![], vec![])]) {
+            Err(EngineError::SystemCycle(names)) => assert_eq!(names, vec!["combat", "ai"]),
+            _ => panic!("cyclic systems were accepted"),
+        }
+    }
+
+    #[test]
+    fn toggle_systems() {
+        let mut systems = Systems::new(vec![Named::boxed("combat", vec![], vec![]),
+                                            Named::boxed("ai", vec![], vec![])])
+            .unwrap();
+
+        let mut context = EngineContext::new(Vec::new());
+        context.disable_system("ai");
+        context = systems.update(context).unwrap();
+        context.enable_system("ai");
+        context = systems.update(context).unwrap();
+
+        assert_eq!(context.state, vec!["combat", "combat", "ai"]);
+    }
+}
@@ -6,3 +6,9 @@ pub type Health = usize;
 
 /// The type for gold. Used as a currency.
 pub type Gold = usize;
+
+/// The type for the amount of a resource like mana or stamina.
+pub type ResourceAmount = usize;
+
+/// The type for the experience of a character.
+pub type Experience = u64;
@@ -1,3 +1,5 @@
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
 use super::World;
 
 /// A collection of worlds. Usually used to create larger adventures
@@ -21,3 +23,23 @@ impl<T: World> Campaign<T> {
         self.worlds.push(world);
     }
 }
+
+impl<T: World + Encodable> Encodable for Campaign<T> {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("Campaign", 2, |e| {
+            e.emit_struct_field("title", 0, |e| self.title.encode(e))?;
+            e.emit_struct_field("worlds", 1, |e| self.worlds.encode(e))
+        })
+    }
+}
+
+impl<T: World + Decodable> Decodable for Campaign<T> {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Campaign<T>, D::Error> {
+        d.read_struct("Campaign", 2, |d| {
+            Ok(Campaign {
+                title: d.read_struct_field("title", 0, Decodable::decode)?,
+                worlds: d.read_struct_field("worlds", 1, Decodable::decode)?,
+            })
+        })
+    }
+}
@@ -1,8 +1,20 @@
+use std::hash::{Hash, Hasher};
+
+use rand::XorShiftRng;
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+
+use behaviour::TickContext;
+use character::Character;
 use entity::Entity;
+use event::{Actor, Event, Message};
 use super::World;
 
+/// How often messages left by entities are passed on in a single interaction. Stops entities
+/// that keep sending events to each other.
+const MAX_MESSAGE_DEPTH: usize = 16;
+
 /// A single field of the world
-#[derive(Clone)]
+#[derive(Clone, Hash)]
 pub struct Field {
     /// The type of the field
     pub field_type: FieldType,
@@ -12,11 +24,17 @@ pub struct Field {
     pub entity: Option<Entity>,
 }
 
+impl_serialize_struct!(Field {
+    field_type,
+    height,
+    entity,
+});
+
 impl Field {
     /// Creates a new instance of `Field`
     pub fn new(field_type: FieldType) -> Field {
         Field {
-            field_type: field_type,
+            field_type,
             height: 0,
             entity: None,
         }
@@ -36,7 +54,7 @@ impl Field {
 }
 
 /// The field type. Used to determine the optical properties of the ground
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum FieldType {
     /// A field consists of dirt
     Dirt,
@@ -64,9 +82,27 @@ pub enum FieldType {
     WoodenFence,
 }
 
+impl_serialize_enum!(FieldType {
+    Dirt,
+    Grass,
+    Hole,
+    Mud,
+    Quicksand,
+    Sand,
+    Stone,
+    StoneWall,
+    SwampWater,
+    Water,
+    Wood,
+    WoodenFence,
+});
+
 /// A larger section of a campaign containing a starting point and end point. The starting point
 /// is where the character *spawns* and the end point is the point he has to reach for the next
 /// world to begin.
+///
+/// Messages between entities are delivered by the world. Messages for anyone else, like the
+/// player, are kept until they are taken with `World2d::take_messages`.
 pub struct World2d {
     /// The name or title of the world
     pub name: String,
@@ -80,6 +116,49 @@ pub struct World2d {
     size: (usize, usize),
     /// The actual fields, the world consists of
     data: Vec<Vec<Field>>,
+    /// The messages of the entities that are not meant for other entities
+    messages: Vec<Message>,
+}
+
+/// The undelivered messages are neither saved nor hashed
+impl Encodable for World2d {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("World2d", 6, |e| {
+            e.emit_struct_field("name", 0, |e| self.name.encode(e))?;
+            e.emit_struct_field("starting_point", 1, |e| self.starting_point.encode(e))?;
+            e.emit_struct_field("end_point", 2, |e| self.end_point.encode(e))?;
+            e.emit_struct_field("current_position", 3, |e| self.current_position.encode(e))?;
+            e.emit_struct_field("size", 4, |e| self.size.encode(e))?;
+            e.emit_struct_field("data", 5, |e| self.data.encode(e))
+        })
+    }
+}
+
+impl Decodable for World2d {
+    fn decode<D: Decoder>(d: &mut D) -> Result<World2d, D::Error> {
+        d.read_struct("World2d", 6, |d| {
+            Ok(World2d {
+                name: d.read_struct_field("name", 0, Decodable::decode)?,
+                starting_point: d.read_struct_field("starting_point", 1, Decodable::decode)?,
+                end_point: d.read_struct_field("end_point", 2, Decodable::decode)?,
+                current_position: d.read_struct_field("current_position", 3, Decodable::decode)?,
+                size: d.read_struct_field("size", 4, Decodable::decode)?,
+                data: d.read_struct_field("data", 5, Decodable::decode)?,
+                messages: Vec::new(),
+            })
+        })
+    }
+}
+
+impl Hash for World2d {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.starting_point.hash(state);
+        self.end_point.hash(state);
+        self.current_position.hash(state);
+        self.size.hash(state);
+        self.data.hash(state);
+    }
 }
 
 impl World2d {
@@ -93,6 +172,7 @@ impl World2d {
             current_position: (0, 0),
             size: (width, height),
             data: vec![vec![Field::new(FieldType::Grass); height]; width],
+            messages: Vec::new(),
         }
     }
 
@@ -117,6 +197,146 @@ impl World2d {
         self.data[position.0][position.1] = field;
     }
 
+    /// Returns the field at the given position, or `None` if the position is outside of the world
+    pub fn get_field(&self, position: (usize, usize)) -> Option<&Field> {
+        self.data.get(position.0).and_then(|column| column.get(position.1))
+    }
+
+    /// Sends an event of the player to the entity at the given position and returns its
+    /// response, or `None` if there is no entity.
+    ///
+    /// A response of `Event::Push` or `Event::Pull` moves the entity one field away from or
+    /// towards the player. The field has to be inside the world, without an entity and not
+    /// higher than the current one, otherwise the response is `Event::Nothing`. Afterwards the
+    /// messages the entities left are delivered.
+    pub fn interact(&mut self, position: (usize, usize), event: Event) -> Option<Event> {
+        let (response, messages) = {
+            let entity = self.entity_mut(position)?;
+            let response = entity.send_event(event);
+            (response, entity.take_messages())
+        };
+
+        let response = match response {
+            Event::Push | Event::Pull => {
+                let (x, y) = position;
+                let (player_x, player_y) = self.current_position;
+                let mut direction = ((x as i64 - player_x as i64).signum(),
+                                     (y as i64 - player_y as i64).signum());
+                if response == Event::Pull {
+                    direction = (-direction.0, -direction.1);
+                }
+
+                if self.move_entity(position, direction) {
+                    response
+                } else {
+                    Event::Nothing
+                }
+            }
+            response => response,
+        };
+
+        self.deliver(messages);
+
+        Some(response)
+    }
+
+    /// Ticks the behaviours of all entities in the world. Call it on every update, for
+    /// example from a system.
+    ///
+    /// Afterwards every entity walks into the direction set with `TickContext::walk` and the
+    /// messages the entities left are delivered.
+    pub fn tick(&mut self, character: &Character, rng: &mut XorShiftRng) {
+        let positions: Vec<(usize, usize)> = self.data
+            .iter()
+            .enumerate()
+            .flat_map(|(x, column)| {
+                column.iter()
+                    .enumerate()
+                    .filter(|&(_, field)| field.entity.is_some())
+                    .map(move |(y, _)| (x, y))
+            })
+            .collect();
+
+        for position in positions {
+            let mut entity = match self.data[position.0][position.1].entity.take() {
+                Some(entity) => entity,
+                None => continue,
+            };
+
+            let movement = {
+                let mut context = TickContext::new(self, position, character, rng);
+                entity.tick(&mut context);
+                context.movement()
+            };
+            let messages = entity.take_messages();
+            self.data[position.0][position.1].entity = Some(entity);
+
+            if let Some(direction) = movement {
+                self.move_entity(position, direction);
+            }
+            self.deliver(messages);
+        }
+    }
+
+    fn entity_mut(&mut self, position: (usize, usize)) -> Option<&mut Entity> {
+        self.data
+            .get_mut(position.0)
+            .and_then(|column| column.get_mut(position.1))
+            .and_then(|field| field.entity.as_mut())
+    }
+
+    fn move_entity(&mut self, from: (usize, usize), direction: (i64, i64)) -> bool {
+        let to = ((from.0 as i64 + direction.0) as usize, (from.1 as i64 + direction.1) as usize);
+        if direction == (0, 0) || to == self.current_position {
+            return false;
+        }
+
+        let free = match (self.get_field(from), self.get_field(to)) {
+            (Some(from), Some(to)) => to.entity.is_none() && to.height <= from.height,
+            _ => false,
+        };
+        if !free {
+            return false;
+        }
+
+        let entity = self.data[from.0][from.1].entity.take();
+        self.data[to.0][to.1].entity = entity;
+        true
+    }
+
+    /// Removes the messages that were not meant for entities and returns them
+    pub fn take_messages(&mut self) -> Vec<Message> {
+        self.messages.drain(..).collect()
+    }
+
+    /// Delivers messages to all entities they are meant for, except their source. Messages
+    /// that are not only meant for entities are kept for `World2d::take_messages`.
+    fn deliver(&mut self, mut messages: Vec<Message>) {
+        let mut depth = 0;
+        while !messages.is_empty() && depth < MAX_MESSAGE_DEPTH {
+            let mut next = Vec::new();
+            for message in messages {
+                for field in self.data.iter_mut().flat_map(|column| column.iter_mut()) {
+                    if let Some(ref mut entity) = field.entity {
+                        let actor = Actor::Entity(entity.name().to_owned());
+                        if message.is_for(&actor) && message.source != actor {
+                            entity.send_event(message.event.clone());
+                            next.extend(entity.take_messages());
+                        }
+                    }
+                }
+
+                match message.target {
+                    Some(Actor::Entity(_)) => {}
+                    _ => self.messages.push(message),
+                }
+            }
+
+            messages = next;
+            depth += 1;
+        }
+    }
+
     fn is_valid_coord(&mut self, coords: (usize, usize)) -> bool {
         let (x, y) = coords;
         let (width, height) = self.size;
@@ -150,6 +370,8 @@ impl World for World2d {
         self.current_position == self.end_point
     }
 
+    /// Sends an `Event::Leave` to the entity on the field the player leaves and an
+    /// `Event::Enter` to the entity on the field the player enters
     fn move_to(&mut self, movement: Self::Movement) {
         let (mut pos_x, mut pos_y) = self.current_position;
         let (mov_x, mov_y) = movement;
@@ -157,15 +379,27 @@ impl World for World2d {
         pos_x = ((pos_x as i64) + mov_x) as usize;
         pos_y = ((pos_y as i64) + mov_y) as usize;
 
-        self.current_position = (pos_x, pos_y);
+        let left = self.current_position;
+        let entered = (pos_x, pos_y);
+        self.current_position = entered;
+
+        let mut messages = Vec::new();
+        for (position, event) in [(left, Event::Leave(left)), (entered, Event::Enter(entered))] {
+            if let Some(entity) = self.entity_mut(position) {
+                let target = Actor::Entity(entity.name().to_owned());
+                messages.push(Message::new(Actor::Player, target, event));
+            }
+        }
+        self.deliver(messages);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use behaviour::{Behaviour, BehaviourResult, Door};
     use world::campaign::Campaign;
-    use entity::Entity;
+    use entity::{Entity, EntityState};
 
     #[test]
     fn build_campaign() {
@@ -177,6 +411,9 @@ mod tests {
 
         world.set_field(field, (0, 0));
 
+        assert_eq!(world.get_field((0, 0)).unwrap().field_type, FieldType::Stone);
+        assert!(world.get_field((10, 0)).is_none());
+
         camp.add_world(world);
     }
 
@@ -197,5 +434,43 @@ mod tests {
         let entity = Entity::new("Michael");
 
         field = field.entity(entity).height(2);
+
+        assert_eq!(field.height, 2);
+    }
+
+    /// Hurts the player stepping on it and opens the gate
+    #[derive(Clone)]
+    struct Trap;
+
+    impl Behaviour for Trap {
+        fn handle_event(&self, event: &Event, state: &mut EntityState) -> BehaviourResult {
+            if let Event::Enter(_) = *event {
+                state.send_to(Actor::Player, Event::Damage(5));
+                state.send("Gate", Event::Open);
+            }
+            BehaviourResult::Pass
+        }
+    }
+
+    #[test]
+    fn deliver_messages() {
+        let mut trap = Entity::new("Trap");
+        trap.append_behaviour(Trap);
+        let mut gate = Entity::new("Gate");
+        gate.append_behaviour(Door::new());
+
+        let mut world = World2d::new("Dungeon", (3, 1));
+        world.set_field(Field::new(FieldType::Stone).entity(trap), (1, 0));
+        world.set_field(Field::new(FieldType::Stone).entity(gate), (2, 0));
+
+        world.move_to((1, 0));
+
+        let gate = world.get_field((2, 0)).unwrap().entity.as_ref().unwrap();
+        assert!(Door::is_open(gate.state()));
+        assert_eq!(world.take_messages(),
+                   vec![Message::new(Actor::Entity("Trap".to_owned()),
+                                     Actor::Player,
+                                     Event::Damage(5))]);
+        assert!(world.take_messages().is_empty());
     }
 }